@@ -1,85 +1,247 @@
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{window, HtmlCanvasElement, HtmlAudioElement, HtmlImageElement};
+use web_sys::{window, CanvasRenderingContext2d, Document, HtmlAudioElement, HtmlCanvasElement};
 
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use crate::game::{Game, GameState};
+use crate::assets::{draw_load_error, fetch_asset_manifest, Assets, LoadProgress};
+use crate::audio::{load_audio, WebAudioLoader};
+use crate::canvas::{logical_size, watch_pixel_ratio, CanvasRenderer};
+use crate::game::{
+    AudioSink, Difficulty, Game, GameConfig, GameMode, GameState, InputAction, LevelConfig,
+    Orientation, RunSummary, MAX_GAME_SPEED, MIN_GAME_SPEED,
+};
+use crate::console_log;
+use crate::dom::{dom_ready, wait_for_element};
+use crate::error::GameError;
+use crate::levels::fetch_level_config;
+use crate::lifecycle::{
+    debug_counts, EventListeners, FrameLoop, Input, Session, StorageFlusher, Subsystem,
+};
+use crate::settings;
+use crate::share_card;
 
-// グローバルなゲームインスタンス
-static mut GAME: Option<Rc<RefCell<Game>>> = None;
+thread_local! {
+    // 動いているゲームと、停止時に片付けるもの
+    static SESSION: RefCell<Option<Session>> = const { RefCell::new(None) };
+}
+
+// ページの要素を待つ時間の上限(ms)
+const ELEMENT_WAIT_TIMEOUT: f64 = 3000.0;
+
+// ゲームを始めるときの選択
+struct StartOptions {
+    seed: u64,
+    mode: GameMode,
+    difficulty: Difficulty,
+    config: GameConfig,
+}
+
+impl StartOptions {
+    // 指定したシードで、エンドレスを通常の難易度と組み込みの設定で遊ぶ
+    fn new(seed: u64) -> Self {
+        StartOptions {
+            seed,
+            mode: GameMode::Endless,
+            difficulty: Difficulty::Normal,
+            config: GameConfig::default(),
+        }
+    }
+
+    // 現在時刻をシードにする
+    fn now() -> Self {
+        StartOptions::new(js_sys::Date::now() as u64)
+    }
+}
+
+// 現在時刻をシードにしてゲームを開始する
+pub async fn start_game() -> Result<(), GameError> {
+    start_session(StartOptions::now()).await
+}
+
+// 指定したシードでゲームを開始する(同じシードなら同じ敵が出現する)
+pub async fn start_game_with_seed(seed: u64) -> Result<(), GameError> {
+    start_session(StartOptions::new(seed)).await
+}
+
+// 遊び方("endless"、"score_attack"、"practice")を選んだ状態でゲームを開始する
+pub async fn start_game_with_mode(name: &str) -> Result<(), GameError> {
+    let mode = GameMode::from_name(name).ok_or_else(|| GameError::UnknownMode(name.to_string()))?;
+    start_session(StartOptions {
+        mode,
+        ..StartOptions::now()
+    })
+    .await
+}
+
+// 難易度("easy"、"normal"、"hard"、"hardcore")を選んだ状態でゲームを開始する
+pub async fn start_game_with_difficulty(name: &str) -> Result<(), GameError> {
+    let difficulty = Difficulty::from_name(name)
+        .ok_or_else(|| GameError::UnknownDifficulty(name.to_string()))?;
+    start_session(StartOptions {
+        difficulty,
+        ..StartOptions::now()
+    })
+    .await
+}
+
+// ページから渡された設定(GameConfigの項目を持つオブジェクト)でゲームを開始する
+// undefined や null なら組み込みの設定を使う
+pub async fn start_game_with_config(config: JsValue) -> Result<(), GameError> {
+    let config = if config.is_undefined() || config.is_null() {
+        GameConfig::default()
+    } else {
+        serde_wasm_bindgen::from_value::<GameConfig>(config)
+            .map_err(|e| GameError::InvalidConfig(e.to_string()))?
+    };
+    config.validate().map_err(GameError::InvalidConfig)?;
+    start_session(StartOptions {
+        config,
+        ..StartOptions::now()
+    })
+    .await
+}
+
+// 必要な要素が揃わなければパニックせずにエラーを返す
+async fn start_session(options: StartOptions) -> Result<(), GameError> {
+    // 既に動いているゲームがあれば片付けてから始め直す
+    stop_game();
 
-pub fn start_game() {
     // ウィンドウとドキュメントの取得
-    let window = window().expect("no global `window` exists");
-    let document = window.document().expect("should have a document on window");
+    let document = window()
+        .and_then(|window| window.document())
+        .ok_or(GameError::NoDocument)?;
+    dom_ready(&document).await;
 
     // Canvasの取得と2Dコンテキストの設定
-    let canvas = document
-        .get_element_by_id("gameCanvas")
-        .expect("should have gameCanvas element")
-        .dyn_into::<HtmlCanvasElement>()
-        .expect("gameCanvas should be a HtmlCanvasElement");
+    let canvas: HtmlCanvasElement =
+        wait_for_element(&document, &options.config.canvas_id, ELEMENT_WAIT_TIMEOUT).await?;
     let context = canvas
         .get_context("2d")
-        .expect("should have 2d context")
-        .unwrap()
-        .dyn_into::<web_sys::CanvasRenderingContext2d>()
-        .expect("context should be CanvasRenderingContext2d");
+        .ok()
+        .flatten()
+        .and_then(|context| context.dyn_into::<CanvasRenderingContext2d>().ok())
+        .ok_or(GameError::NoContext)?;
 
     // オーディオ要素の取得
-    let shoot_sound = document
-        .get_element_by_id("shootSound")
-        .expect("should have shootSound element")
-        .dyn_into::<HtmlAudioElement>()
-        .expect("shootSound should be HtmlAudioElement");
-    let explosion_sound = document
-        .get_element_by_id("explosionSound")
-        .expect("should have explosionSound element")
-        .dyn_into::<HtmlAudioElement>()
-        .expect("explosionSound should be HtmlAudioElement");
-
-    // 画像のロード
-    let player_image = HtmlImageElement::new().unwrap();
-    player_image.set_src("assets/player.png");
-
-    let background_image = HtmlImageElement::new().unwrap();
-    background_image.set_src("assets/background.png");
-
-    let enemy_image = HtmlImageElement::new().unwrap();
-    enemy_image.set_src("assets/enemy.png");
+    let shoot_sound: HtmlAudioElement =
+        wait_for_element(&document, "shootSound", ELEMENT_WAIT_TIMEOUT).await?;
+    let explosion_sound: HtmlAudioElement =
+        wait_for_element(&document, "explosionSound", ELEMENT_WAIT_TIMEOUT).await?;
+
+    // 画面の大きさはCanvasの大きさに合わせる(前のゲームで拡大した分は含めない)
+    let size = logical_size(&canvas);
+
+    // 読み込む画像と効果音は一覧に従う
+    let manifest = fetch_asset_manifest().await;
+    let kill_sounds = &manifest.kill_sounds;
+
+    // 効果音の読み込みは画像の読み込みと並行して進める
+    let audio_loader =
+        WebAudioLoader::start(&document, &shoot_sound, &explosion_sound, kill_sounds);
+
+    // 画像と効果音をすべて読み込んでからゲームを開始する
+    // 進捗バーは画像のあとに効果音のデコードの分を続けて進める
+    let audio_steps = audio_loader.as_ref().map_or(0, WebAudioLoader::load_steps);
+    let mut progress = LoadProgress::new(&context, Assets::load_steps(&manifest) + audio_steps);
+    match Assets::load(&manifest, &mut progress).await {
+        Ok(assets) => {
+            let audio = load_audio(
+                audio_loader,
+                &document,
+                shoot_sound,
+                explosion_sound,
+                kill_sounds,
+                &mut progress,
+            )
+            .await;
+            let level = fetch_level_config().await;
+            run_game(&document, context, audio, assets, level, options, size);
+        }
+        Err(message) => {
+            // 始めた効果音の読み込みも捨て、AudioContextを残さない
+            if let Ok(loader) = audio_loader {
+                loader.cancel();
+            }
+            draw_load_error(&context, &message);
+        }
+    }
+    Ok(())
+}
+
+fn run_game(
+    document: &Document,
+    context: CanvasRenderingContext2d,
+    audio: Box<dyn AudioSink>,
+    assets: Assets,
+    level: LevelConfig,
+    options: StartOptions,
+    (width, height): (f64, f64),
+) {
+    // 読み込み中に start_game が重ねて呼ばれた場合は、先に動き出したゲームを片付けてから
+    // Canvasを拡大する(後から元の大きさに戻されないように)
+    stop_game();
+
+    let mut listeners = EventListeners::default();
+    let canvas = context.canvas();
+
+    // 高解像度ディスプレイ向けにCanvasを拡大する。ゲームの座標はCSSピクセルのまま
+    let canvas_restore = watch_pixel_ratio(context.clone(), (width, height), &mut listeners);
 
     // ゲームの初期化
-    let game = Game::new(
-        context,
-        shoot_sound,
-        explosion_sound,
-        player_image,
-        background_image,
-        enemy_image,
-    );
+    let kill_sounds = assets.kill_sounds.clone();
+    let renderer = CanvasRenderer::new(document, context, assets);
+    let game = Game::new(Box::new(renderer), audio, options.seed, width, height);
+    game.borrow_mut().set_config(options.config);
+    game.borrow_mut().set_level_config(level);
+    game.borrow_mut().kill_sounds = kill_sounds;
 
-    // グローバルなゲームインスタンスを設定
-    unsafe {
-        GAME = Some(game.clone());
+    // 保存されている設定とプレイの記録を反映
+    {
+        let mut game = game.borrow_mut();
+        game.set_game_speed(settings::load_game_speed());
+        game.set_volume(settings::load_volume());
+        game.set_music_volume(settings::load_music_volume());
+        game.run_store = Some(Box::new(settings::LocalRunStore));
+        game.set_mode(options.mode);
+        game.set_difficulty(options.difficulty);
     }
 
+    let input = attach_input(document, canvas, &game);
+
+    // ゲームの開始
+    let frame_loop = FrameLoop::start(game.clone(), stop_game);
+    let session = Session {
+        storage: StorageFlusher::new(game.clone()),
+        game,
+        frame_loop,
+        input,
+        listeners,
+        canvas: canvas_restore,
+    };
+    SESSION.with(|current| *current.borrow_mut() = Some(session));
+}
+
+// キーボードとマウス、タブやウィンドウのフォーカスの変化をゲームに伝える
+fn attach_input(
+    document: &Document,
+    canvas: Option<HtmlCanvasElement>,
+    game: &Rc<RefCell<Game>>,
+) -> Input {
+    let window = window().expect("no global `window` exists");
+    let mut input = Input::new(game.clone());
+
     // キーボードイベントリスナーの設定
     {
         let game_rc = game.clone();
         let key_down_closure =
             Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
                 let key = event.key();
-                game_rc.borrow_mut().key_down(key);
+                game_rc.borrow_mut().key_down(key, event.time_stamp());
             }) as Box<dyn FnMut(web_sys::KeyboardEvent)>);
-        window
-            .add_event_listener_with_callback(
-                "keydown",
-                key_down_closure.as_ref().unchecked_ref(),
-            )
-            .expect("failed to add keydown listener");
-        key_down_closure.forget();
+        input.listeners.add(&window, "keydown", key_down_closure);
     }
 
     {
@@ -88,23 +250,452 @@ pub fn start_game() {
             let key = event.key();
             game_rc.borrow_mut().key_up(key);
         }) as Box<dyn FnMut(web_sys::KeyboardEvent)>);
-        window
-            .add_event_listener_with_callback("keyup", key_up_closure.as_ref().unchecked_ref())
-            .expect("failed to add keyup listener");
-        key_up_closure.forget();
+        input.listeners.add(&window, "keyup", key_up_closure);
     }
 
-    // ゲームの開始
-    Game::start(game.clone());
+    // マウスの位置を照準にする(Canvas内の位置はゲームの座標と同じCSSピクセル)
+    if let Some(canvas) = canvas {
+        let game_rc = game.clone();
+        let mouse_move_closure = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
+            game_rc
+                .borrow_mut()
+                .set_aim(event.offset_x() as f64, event.offset_y() as f64);
+        }) as Box<dyn FnMut(web_sys::MouseEvent)>);
+        input.listeners.add(&canvas, "mousemove", mouse_move_closure);
+
+        // タイトル画面ではクリックやタップでも始められる
+        let game_rc = game.clone();
+        let click_closure = Closure::wrap(Box::new(move |_event: web_sys::MouseEvent| {
+            game_rc.borrow_mut().begin_run();
+        }) as Box<dyn FnMut(web_sys::MouseEvent)>);
+        input.listeners.add(&canvas, "click", click_closure);
+    }
+
+    // タブやウィンドウから離れたら一時停止する
+    {
+        let game_rc = game.clone();
+        let hidden_document = document.clone();
+        let visibility_closure = Closure::wrap(Box::new(move || {
+            let mut game = game_rc.borrow_mut();
+            if hidden_document.hidden() {
+                game.suspend();
+            } else {
+                game.resume_timing();
+            }
+        }) as Box<dyn FnMut()>);
+        input.listeners.add(document, "visibilitychange", visibility_closure);
+    }
+
+    for (event, suspend) in [("blur", true), ("focus", false)] {
+        let game_rc = game.clone();
+        let focus_closure = Closure::wrap(Box::new(move || {
+            let mut game = game_rc.borrow_mut();
+            if suspend {
+                game.suspend();
+            } else {
+                game.resume_timing();
+            }
+        }) as Box<dyn FnMut()>);
+        input.listeners.add(&window, event, focus_closure);
+    }
+    input
+}
+
+// ゲームを止め、ループやイベントリスナー、音声を片付ける(何度呼んでもよい)
+// ゲームループから送った出来事のリスナーの中で呼ばれたら、そのフレームの終わりに止める
+pub fn stop_game() {
+    let deferred = SESSION.with(|session| {
+        session
+            .borrow()
+            .as_ref()
+            .is_some_and(|session| session.frame_loop.defer_stop())
+    });
+    if deferred {
+        return;
+    }
+    if let Some(mut session) = SESSION.with(|session| session.borrow_mut().take()) {
+        session.shutdown();
+    }
+}
+
+// 調整値のファイルを取得し直し、動いているゲームに反映する(調整中の確認用)
+pub fn reload_config() {
+    wasm_bindgen_futures::spawn_local(async {
+        let level = fetch_level_config().await;
+        if with_game(|game| game.set_level_config(level)).is_some() {
+            console_log!("Reloaded level config");
+        }
+    });
 }
 
 pub fn reset_game() {
-    // グローバルなゲームインスタンスを取得してリセット
-    unsafe {
-        if let Some(game_rc) = &mut GAME {
-            game_rc.borrow_mut().reset();
-            // ゲームループを再開
-            Game::start(game_rc.clone());
+    // タイトル画面に戻る(ゲームループは動き続けている)
+    with_game(|game| game.reset(performance_now()));
+}
+
+// requestAnimationFrame やイベントの時刻と同じ基準の現在時刻(ms)
+fn performance_now() -> f64 {
+    window()
+        .and_then(|window| window.performance())
+        .map_or(0.0, |performance| performance.now())
+}
+
+// グローバルなゲームインスタンスがあれば処理を行う
+fn with_game<R>(f: impl FnOnce(&mut Game) -> R) -> Option<R> {
+    let game_rc = SESSION.with(|session| Some(session.borrow().as_ref()?.game.clone()))?;
+    let mut game = game_rc.borrow_mut();
+    Some(f(&mut game))
+}
+
+// 読み取るだけの処理に使う。ゲームの処理中に呼ばれて借用できない場合はNoneを返す
+fn read_game<R>(f: impl FnOnce(&Game) -> R) -> Option<R> {
+    let game_rc = SESSION.with(|session| Some(session.borrow().as_ref()?.game.clone()))?;
+    let game = game_rc.try_borrow().ok()?;
+    Some(f(&game))
+}
+
+// 現在のスコア(ゲーム開始前は0)
+pub fn get_score() -> u32 {
+    read_game(|game| game.score).unwrap_or(0)
+}
+
+// 残りライフ(ゲーム開始前は0)
+pub fn get_lives() -> u32 {
+    read_game(|game| game.lives).unwrap_or(0)
+}
+
+// ゲームオーバー(終わった場面の再生中を含む)かどうか
+pub fn is_game_over() -> bool {
+    read_game(|game| matches!(game.state, GameState::GameOver | GameState::Spectating))
+        .unwrap_or(false)
+}
+
+// 選んでいる難易度の名前(ゲーム開始前は "normal")
+pub fn get_difficulty() -> String {
+    read_game(|game| game.difficulty)
+        .unwrap_or(Difficulty::Normal)
+        .name()
+        .to_string()
+}
+
+// ページ側から一時停止する。遊んでいないときや既に止まっているときは何もせずfalse
+pub fn pause_game() -> bool {
+    with_game(|game| game.set_paused(true)).unwrap_or(false)
+}
+
+// ページ側から再開する。止まっていないときやゲームオーバー後は何もせずfalse
+pub fn resume_game() -> bool {
+    with_game(|game| game.set_paused(false)).unwrap_or(false)
+}
+
+pub fn set_debug_hitboxes(enabled: bool) {
+    with_game(|game| game.debug_hitboxes = enabled);
+}
+
+pub fn set_brute_force_collisions(enabled: bool) {
+    with_game(|game| game.brute_force_collisions = enabled);
+}
+
+pub fn set_player_wrap(enabled: bool) {
+    with_game(|game| game.set_wrap_horizontal(enabled));
+}
+
+// アシスト設定のゲーム速度を変更して保存する
+pub fn set_game_speed(speed: f64) -> f64 {
+    let speed = speed.clamp(MIN_GAME_SPEED, MAX_GAME_SPEED);
+    with_game(|game| game.set_game_speed(speed));
+    settings::save_game_speed(speed);
+    speed
+}
+
+pub fn get_game_speed() -> f64 {
+    with_game(|game| game.assist_speed).unwrap_or_else(settings::load_game_speed)
+}
+
+// 効果音の音量を変更して保存する
+pub fn set_volume(volume: f64) {
+    let volume = volume.clamp(0.0, 1.0);
+    with_game(|game| game.set_volume(volume));
+    settings::save_volume(volume);
+}
+
+pub fn get_volume() -> f64 {
+    with_game(|game| game.master_volume).unwrap_or_else(settings::load_volume)
+}
+
+// BGMの音量を変更して保存する
+pub fn set_music_volume(volume: f64) {
+    let volume = volume.clamp(0.0, 1.0);
+    with_game(|game| game.set_music_volume(volume));
+    settings::save_music_volume(volume);
+}
+
+pub fn get_music_volume() -> f64 {
+    with_game(|game| game.music_volume).unwrap_or_else(settings::load_music_volume)
+}
+
+// 消音を切り替え、消音中ならtrueを返す
+pub fn toggle_mute() -> bool {
+    with_game(|game| game.toggle_mute()).unwrap_or(false)
+}
+
+pub fn set_reduced_motion(enabled: bool) {
+    with_game(|game| {
+        game.reduced_motion = enabled;
+        if enabled {
+            game.debris.clear();
+            game.shake.clear();
         }
+    });
+}
+
+// ゲームオーバー時のスコア送信用JSON(検証手順はgame/submission.rsを参照)
+pub fn get_score_submission() -> Option<String> {
+    with_game(|game| game.score_submission().map(|submission| submission.to_json())).flatten()
+}
+
+// 操作に割り当てるキーを変更する。未知の操作名やキーが空の場合はエラーを返す
+pub fn set_key_binding(action: &str, code: &str) -> Result<(), JsValue> {
+    let action = InputAction::from_name(action)
+        .ok_or_else(|| JsValue::from_str(&format!("unknown input action: {}", action)))?;
+    if code.is_empty() {
+        return Err(JsValue::from_str("key code must not be empty"));
+    }
+    with_game(|game| game.rebind_key(action, code));
+    Ok(())
+}
+
+// 操作名ごとに割り当てられているキーの配列を持つオブジェクトを返す
+pub fn get_key_bindings() -> JsValue {
+    let result = js_sys::Object::new();
+    with_game(|game| {
+        for action in InputAction::ALL {
+            let keys: js_sys::Array =
+                game.key_bindings.keys_for(action).map(JsValue::from_str).collect();
+            if let Err(e) = js_sys::Reflect::set(&result, &JsValue::from_str(action.name()), &keys)
+            {
+                console_log!("Error building key bindings: {:?}", e);
+            }
+        }
+    });
+    result.into()
+}
+
+pub fn get_share_card() -> Result<String, JsValue> {
+    with_game(|game| share_card::render_share_card(game))
+        .unwrap_or_else(|| Err(JsValue::from_str("game has not started")))
+}
+
+pub fn download_share_card() -> Result<(), JsValue> {
+    with_game(|game| share_card::download_share_card(game))
+        .unwrap_or_else(|| Err(JsValue::from_str("game has not started")))
+}
+
+// 画面の向き("BottomUp" または "LeftToRight")を変えてタイトル画面に戻る
+pub fn set_orientation(name: &str) -> Result<(), JsValue> {
+    let orientation = Orientation::from_name(name)
+        .ok_or_else(|| JsValue::from_str(&format!("unknown orientation: {}", name)))?;
+    with_game(|game| game.set_orientation(orientation, performance_now()));
+    Ok(())
+}
+
+pub fn push_ghost_position(x: f64, y: f64, sent_at: f64) {
+    with_game(|game| game.receive_ghost_position(x, y, sent_at));
+}
+
+// get_run_stats で返す結果(保存する記録に命中率を加えたもの)
+#[derive(Serialize)]
+struct RunStats {
+    #[serde(flatten)]
+    run: RunSummary,
+    accuracy: Option<f64>,
+}
+
+// ゲームオーバーになったプレイの結果。それ以外のときはnull
+pub fn get_run_stats() -> JsValue {
+    let Some(run) = read_game(|game| game.finished_run().copied()).flatten() else {
+        return JsValue::NULL;
+    };
+    let stats = RunStats {
+        run,
+        accuracy: run.accuracy(),
+    };
+    stats
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .unwrap_or_else(|e| {
+            console_log!("Error serializing run stats: {:?}", e);
+            JsValue::NULL
+        })
+}
+
+// ゲームオーバー画面に出しているプレイのまとめ。それ以外のときはnull
+pub fn get_last_run_summary() -> JsValue {
+    let Some(result) = read_game(|game| game.last_run_result()).flatten() else {
+        return JsValue::NULL;
+    };
+    result
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .unwrap_or_else(|e| {
+            console_log!("Error serializing run summary: {:?}", e);
+            JsValue::NULL
+        })
+}
+
+// 登録したままのイベントリスナーの数(停止後に0へ戻るかの確認用)
+pub fn get_listener_count() -> usize {
+    debug_counts().listeners
+}
+
+// 残っているリスナー、ゲームループのクロージャ、AudioContextの数
+pub fn get_debug_counts() -> JsValue {
+    debug_counts()
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .unwrap_or_else(|e| {
+            console_log!("Error serializing debug counts: {:?}", e);
+            JsValue::NULL
+        })
+}
+
+// このプレイで使われた、ランキング対象外になる設定の名前の配列
+pub fn get_run_flags() -> JsValue {
+    let flags = js_sys::Array::new();
+    with_game(|game| {
+        for name in game.run_flags.names() {
+            flags.push(&JsValue::from_str(name));
+        }
+    });
+    flags.into()
+}
+
+// ブラウザで動かすテスト(wasm-pack test --headless --firefox)
+#[cfg(all(test, target_arch = "wasm32"))]
+mod browser_tests {
+    use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+    use super::*;
+    use crate::assets::next_animation_frame;
+    use crate::canvas::CanvasRestore;
+    use crate::game::mock::headless_game;
+    use crate::game::{Bullet, GameEvent};
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    // 画面のないゲームを、入力のリスナーとゲームループ付きで動いているゲームとして登録する
+    pub(crate) fn install_headless_game(seed: u64) -> Rc<RefCell<Game>> {
+        stop_game();
+        let (game, _) = headless_game(seed);
+        let document = window().and_then(|window| window.document()).unwrap();
+        let canvas = document.create_element("canvas").unwrap().dyn_into().ok();
+        let session = Session {
+            game: game.clone(),
+            frame_loop: FrameLoop::start(game.clone(), stop_game),
+            input: attach_input(&document, canvas, &game),
+            listeners: EventListeners::default(),
+            storage: StorageFlusher::new(game.clone()),
+            canvas: CanvasRestore::default(),
+        };
+        SESSION.with(|current| *current.borrow_mut() = Some(session));
+        game
+    }
+
+    #[wasm_bindgen_test]
+    fn exports_read_defaults_without_a_game() {
+        stop_game();
+        assert_eq!(crate::get_score(), 0);
+        assert_eq!(crate::get_lives(), 0);
+        assert!(!crate::is_game_over());
+    }
+
+    #[wasm_bindgen_test]
+    fn exports_match_the_running_game() {
+        let game = install_headless_game(1);
+        game.borrow_mut().begin_run();
+        assert_eq!(crate::get_lives(), game.borrow().lives);
+
+        {
+            let mut game = game.borrow_mut();
+            game.score = 1234;
+            game.lives = 2;
+        }
+        assert_eq!(crate::get_score(), game.borrow().score);
+        assert_eq!(crate::get_lives(), game.borrow().lives);
+        assert!(!crate::is_game_over());
+
+        game.borrow_mut().state = GameState::GameOver;
+        assert!(crate::is_game_over());
+        stop_game();
+        assert_eq!(crate::get_score(), 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn start_stop_cycles_return_every_count_to_the_baseline() {
+        stop_game();
+        let baseline = debug_counts();
+        for _ in 0..3 {
+            let game = install_headless_game(1);
+            let running = debug_counts();
+            assert_eq!(running.frame_closures, baseline.frame_closures + 1);
+            assert!(running.listeners > baseline.listeners);
+            assert_eq!(crate::get_listener_count(), running.listeners);
+
+            stop_game();
+            assert_eq!(debug_counts(), baseline);
+            // 片付けたゲームは誰からも参照されていない
+            assert_eq!(Rc::strong_count(&game), 1);
+            // 2回目の停止では何も起きない
+            stop_game();
+            assert_eq!(debug_counts(), baseline);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn a_page_listener_can_stop_the_game_from_inside_a_frame() {
+        stop_game();
+        let baseline = debug_counts();
+        let game = install_headless_game(1);
+        let window = window().unwrap();
+        let stop = Closure::wrap(Box::new(crate::stop_game) as Box<dyn FnMut()>);
+        let stop_function = stop.as_ref().unchecked_ref();
+        window.add_event_listener_with_callback("alarmshooter:score", stop_function).unwrap();
+        game.borrow_mut().events.push(GameEvent::Score { score: 10 });
+
+        // 送った出来事の中で止められたフレームは、最後まで処理してから片付ける
+        next_animation_frame().await;
+        next_animation_frame().await;
+        window.remove_event_listener_with_callback("alarmshooter:score", stop_function).unwrap();
+        assert!(SESSION.with(|session| session.borrow().is_none()));
+        assert_eq!(debug_counts(), baseline);
+        assert_eq!(Rc::strong_count(&game), 1);
+    }
+
+    #[wasm_bindgen_test]
+    fn the_share_card_of_a_finished_run_is_a_png_data_url() {
+        stop_game();
+        assert!(crate::get_share_card().is_err());
+
+        let game = install_headless_game(1);
+        {
+            let mut game = game.borrow_mut();
+            game.begin_run();
+            game.countdown = 0.0;
+            game.score = 4200;
+            game.lives = 1;
+            // 自機に敵の弾を当て続けてゲームオーバーにする
+            for _ in 0..600 {
+                if game.state == GameState::GameOver {
+                    break;
+                }
+                let center = game.player.center();
+                let shot = Bullet::new_enemy_shot(center.x - 4.0, center.y - 4.0);
+                game.enemy_bullets.push(shot);
+                game.update(16.0);
+            }
+        }
+        assert!(crate::is_game_over());
+
+        let url = crate::get_share_card().unwrap();
+        assert!(url.starts_with("data:image/png;base64,"), "{}", &url[..url.len().min(40)]);
+        assert!(url.len() > "data:image/png;base64,".len());
+        stop_game();
     }
 }
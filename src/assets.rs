@@ -0,0 +1,416 @@
+use std::collections::VecDeque;
+
+use js_sys::Promise;
+use serde::Deserialize;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{window, CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
+
+use crate::console_log;
+use crate::game::KillSounds;
+use crate::levels::fetch_text;
+
+// 読み込む画像と効果音の一覧を書いたファイル
+const ASSET_MANIFEST_URL: &str = "assets/manifest.json";
+// 敵の見た目の種類として並べられる画像の数の上限
+const MAX_ENEMY_VARIANTS: usize = 8;
+
+// 読み込む画像のパスと、撃破時の効果音の候補の一覧
+// 書かれていない項目は組み込みの値を使う
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AssetManifest {
+    pub player: String,
+    pub background: String,
+    pub enemies: Vec<String>, // 敵の見た目の種類ごとの画像(1枚以上)
+    pub starfield: Option<String>, // 視差スクロール用の重ねる背景(なくてもよい)
+    pub boss: Option<String>, // ボスの画像(なくてもよい)
+    pub kill_sounds: KillSounds,
+}
+
+impl Default for AssetManifest {
+    fn default() -> Self {
+        AssetManifest {
+            player: "assets/player.png".to_string(),
+            background: "assets/background.png".to_string(),
+            enemies: vec!["assets/enemy.png".to_string()],
+            starfield: Some("assets/starfield.png".to_string()),
+            boss: Some("assets/boss.png".to_string()),
+            kill_sounds: KillSounds::default(),
+        }
+    }
+}
+
+impl AssetManifest {
+    // JSONの文字列から読み込み、中身を確かめる
+    pub fn parse(text: &str) -> Result<AssetManifest, String> {
+        let manifest: AssetManifest = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.enemies.is_empty() {
+            return Err("enemies must list at least one image".to_string());
+        }
+        if self.enemies.len() > MAX_ENEMY_VARIANTS {
+            return Err(format!(
+                "enemies lists {} images (at most {})",
+                self.enemies.len(),
+                MAX_ENEMY_VARIANTS
+            ));
+        }
+        let paths = [&self.player, &self.background]
+            .into_iter()
+            .chain(&self.enemies)
+            .chain(self.starfield.iter())
+            .chain(self.boss.iter());
+        if paths.into_iter().any(|path| path.trim().is_empty()) {
+            return Err("image paths must not be empty".to_string());
+        }
+        self.kill_sounds.validate()
+    }
+
+    // 必ず読み込む画像(自機、背景、敵の順)
+    fn required_images(&self) -> impl Iterator<Item = &str> {
+        [&self.player, &self.background]
+            .into_iter()
+            .chain(&self.enemies)
+            .map(String::as_str)
+    }
+}
+
+// アセットの一覧を取得する。取得や解釈に失敗した場合は警告を出して組み込みの一覧を使う
+pub async fn fetch_asset_manifest() -> AssetManifest {
+    let text = match fetch_text(ASSET_MANIFEST_URL).await {
+        Ok(text) => text,
+        Err(e) => {
+            console_log!("Warning: could not load {}, using defaults: {:?}", ASSET_MANIFEST_URL, e);
+            return AssetManifest::default();
+        }
+    };
+    AssetManifest::parse(&text).unwrap_or_else(|e| {
+        console_log!("Warning: {} is malformed, using defaults: {}", ASSET_MANIFEST_URL, e);
+        AssetManifest::default()
+    })
+}
+
+// ゲームで使う画像と、撃破時の効果音の候補
+pub struct Assets {
+    pub player_image: HtmlImageElement,
+    pub background_image: HtmlImageElement,
+    pub enemy_images: Vec<HtmlImageElement>, // 敵の見た目の種類ごとの画像(1枚以上)
+    pub starfield_image: Option<HtmlImageElement>, // 視差スクロール用の重ねる背景(なくてもよい)
+    pub boss_image: Option<HtmlImageElement>, // ボスの画像(なくてもよい)
+    pub kill_sounds: KillSounds, // 効果音はページのaudio要素から読み込むので、名前だけ持つ
+}
+
+// 画像の読み込みを開始し、完了を待つPromiseと一緒に返す
+fn start_image_load(src: &str) -> Result<(HtmlImageElement, Promise), JsValue> {
+    let image = HtmlImageElement::new()?;
+    let promise = Promise::new(&mut |resolve, reject| {
+        image.set_onload(Some(&resolve));
+        image.set_onerror(Some(&reject));
+    });
+    image.set_src(src);
+    Ok((image, promise))
+}
+
+// なくてもよい画像を読み込む。読み込めなければNone
+async fn load_optional_image(src: &str) -> Option<HtmlImageElement> {
+    let (image, promise) = start_image_load(src).ok()?;
+    JsFuture::from(promise).await.ok().map(|_| image)
+}
+
+// 読み込み後の重い処理(画像のデコードなど)を1フレームに決まった数ずつ取り出す列
+// 一度に処理すると読み込み画面が止まって見えるため、フレームをまたいで少しずつ進める
+pub struct FrameBudgetQueue<T> {
+    pending: VecDeque<T>,
+    per_frame: usize,
+}
+
+impl<T> FrameBudgetQueue<T> {
+    pub fn new(per_frame: usize) -> Self {
+        FrameBudgetQueue {
+            pending: VecDeque::new(),
+            per_frame: per_frame.max(1),
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.pending.push_back(item);
+    }
+
+    // このフレームで処理する分を取り出す
+    pub fn next_frame(&mut self) -> Vec<T> {
+        let count = self.per_frame.min(self.pending.len());
+        self.pending.drain(..count).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+// 次の描画フレームまで待つ
+pub async fn next_animation_frame() {
+    let promise = Promise::new(&mut |resolve, _| {
+        if let Some(window) = window() {
+            if let Err(e) = window.request_animation_frame(&resolve) {
+                console_log!("Error requesting animation frame: {:?}", e);
+            }
+        }
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+// 読み込みを終えて描画できる画像かどうか(読み込みに失敗した画像は大きさが0になる)
+pub fn is_loaded(image: &HtmlImageElement) -> bool {
+    image.complete() && image.natural_width() > 0
+}
+
+// 画像をデコードし、小さなCanvasに一度描いておく
+// 初めて描画するフレームでデコードが走ってゲーム開始直後に引っかかるのを防ぐ
+async fn decode_image(image: &HtmlImageElement, warm_up: Option<&CanvasRenderingContext2d>) {
+    if let Err(e) = JsFuture::from(image.decode()).await {
+        console_log!("Error decoding image {}: {:?}", image.src(), e);
+    }
+    if let Some(context) = warm_up {
+        let _ = context.draw_image_with_html_image_element_and_dw_and_dh(image, 0.0, 0.0, 1.0, 1.0);
+    }
+}
+
+// 画像を事前に描いておくための1×1のCanvas
+fn warm_up_context() -> Option<CanvasRenderingContext2d> {
+    let canvas = window()?
+        .document()?
+        .create_element("canvas")
+        .ok()?
+        .dyn_into::<HtmlCanvasElement>()
+        .ok()?;
+    canvas.set_width(1);
+    canvas.set_height(1);
+    canvas.get_context("2d").ok()??.dyn_into().ok()
+}
+
+// 読み込み画面の進捗バー。画像と効果音の読み込みで同じバーを進める
+pub struct LoadProgress<'a> {
+    context: &'a CanvasRenderingContext2d,
+    done: usize,
+    total: usize,
+}
+
+impl<'a> LoadProgress<'a> {
+    pub fn new(context: &'a CanvasRenderingContext2d, total: usize) -> Self {
+        LoadProgress {
+            context,
+            done: 0,
+            total,
+        }
+    }
+
+    pub fn draw(&self) {
+        draw_loading(self.context, self.done.min(self.total), self.total);
+    }
+
+    // 1段階進めて描き直す
+    pub fn advance(&mut self) {
+        self.done += 1;
+        self.draw();
+    }
+}
+
+impl Assets {
+    // 画像の読み込みが進捗バーで使う段階の数(必ず読み込む画像を、読み込みとデコードの2段階で数える)
+    pub fn load_steps(manifest: &AssetManifest) -> usize {
+        manifest.required_images().count() * 2
+    }
+
+    // 一覧の画像をすべて読み込む。読み込み中は進捗をCanvasに表示する
+    // 読み込めなかった画像は警告を1度だけ出して続け、描画時に代わりの矩形を塗る
+    // 画像の要素を作れなかった場合は表示用のエラーメッセージを返す
+    pub async fn load(
+        manifest: &AssetManifest,
+        progress: &mut LoadProgress<'_>,
+    ) -> Result<Assets, String> {
+        let sources: Vec<&str> = manifest.required_images().collect();
+
+        // 並行して読み込むため、先にすべての読み込みを開始する
+        let mut loads = Vec::new();
+        for &src in &sources {
+            let load = start_image_load(src)
+                .map_err(|e| format!("画像を作成できませんでした: {} ({:?})", src, e))?;
+            loads.push((src, load));
+        }
+
+        let mut images = Vec::new();
+        for (src, (image, promise)) in loads {
+            progress.draw();
+            if JsFuture::from(promise).await.is_err() {
+                console_log!("Warning: failed to load {}, drawing placeholders instead", src);
+            }
+            progress.done += 1;
+            images.push(image);
+        }
+
+        // 重ねる背景とボスの画像は一覧になければ使わない
+        let starfield_image = match &manifest.starfield {
+            Some(src) => load_optional_image(src).await,
+            None => None,
+        };
+        let boss_image = match &manifest.boss {
+            Some(src) => load_optional_image(src).await,
+            None => None,
+        };
+
+        // デコードは1フレームに1枚ずつ行い、その間も進捗バーを更新する
+        let warm_up = warm_up_context();
+        let mut queue = FrameBudgetQueue::new(1);
+        for image in images
+            .iter()
+            .chain(starfield_image.iter())
+            .chain(boss_image.iter())
+            .filter(|image| is_loaded(image))
+        {
+            queue.push(image);
+        }
+        // なくてもよい画像の分は数えないので、必ず読む画像の段階で止める
+        let end = progress.done + sources.len();
+        while !queue.is_empty() {
+            next_animation_frame().await;
+            for image in queue.next_frame() {
+                decode_image(image, warm_up.as_ref()).await;
+                progress.done = (progress.done + 1).min(end);
+            }
+            progress.draw();
+        }
+        progress.done = end;
+        progress.draw();
+
+        let mut images = images.into_iter();
+        let player_image = images.next().unwrap();
+        let background_image = images.next().unwrap();
+        // 読み込めなかった敵の画像は種類から外す。すべて失敗したら最初の1枚を代わりの矩形で描く
+        let mut enemy_images: Vec<HtmlImageElement> = images.collect();
+        if enemy_images.iter().any(is_loaded) {
+            enemy_images.retain(is_loaded);
+        } else {
+            enemy_images.truncate(1);
+        }
+        Ok(Assets {
+            player_image,
+            background_image,
+            enemy_images,
+            starfield_image,
+            boss_image,
+            kill_sounds: manifest.kill_sounds.clone(),
+        })
+    }
+}
+
+// 読み込みの進捗を表示
+fn draw_loading(context: &CanvasRenderingContext2d, loaded: usize, total: usize) {
+    let canvas = match context.canvas() {
+        Some(canvas) => canvas,
+        None => return,
+    };
+    let width = canvas.width() as f64;
+    let height = canvas.height() as f64;
+
+    context.set_fill_style(&JsValue::from_str("black"));
+    context.fill_rect(0.0, 0.0, width, height);
+
+    context.set_fill_style(&JsValue::from_str("white"));
+    context.set_font("24px sans-serif");
+    context.set_text_align("center");
+    let _ = context.fill_text("Loading…", width / 2.0, height / 2.0 - 20.0);
+
+    // 進捗バー
+    let bar_width = 300.0;
+    let progress = if total == 0 { 1.0 } else { loaded as f64 / total as f64 };
+    context.set_stroke_style(&JsValue::from_str("white"));
+    context.stroke_rect((width - bar_width) / 2.0, height / 2.0, bar_width, 16.0);
+    context.fill_rect((width - bar_width) / 2.0, height / 2.0, bar_width * progress, 16.0);
+    context.set_text_align("start");
+}
+
+// 読み込みに失敗したことをCanvasに表示
+pub fn draw_load_error(context: &CanvasRenderingContext2d, message: &str) {
+    let canvas = match context.canvas() {
+        Some(canvas) => canvas,
+        None => return,
+    };
+    let width = canvas.width() as f64;
+    let height = canvas.height() as f64;
+
+    context.set_fill_style(&JsValue::from_str("black"));
+    context.fill_rect(0.0, 0.0, width, height);
+
+    context.set_fill_style(&JsValue::from_str("red"));
+    context.set_font("20px sans-serif");
+    context.set_text_align("center");
+    let _ = context.fill_text(message, width / 2.0, height / 2.0);
+    context.set_text_align("start");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_full_manifest() {
+        let manifest = AssetManifest::parse(
+            r#"{
+                "player": "img/ship.png",
+                "background": "img/space.png",
+                "enemies": ["img/e1.png", "img/e2.png"],
+                "starfield": null,
+                "boss": "img/boss.png",
+                "kill_sounds": {"small": ["pop"], "boss": ["boom1", "boom2"]}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.player, "img/ship.png");
+        assert_eq!(manifest.enemies, vec!["img/e1.png", "img/e2.png"]);
+        assert_eq!(manifest.starfield, None);
+        assert_eq!(manifest.kill_sounds.boss, vec!["boom1", "boom2"]);
+        // 書かれていない種類は候補なし(通常の爆発音)になる
+        assert!(manifest.kill_sounds.armored.is_empty());
+        assert_eq!(
+            manifest.required_images().collect::<Vec<_>>(),
+            vec!["img/ship.png", "img/space.png", "img/e1.png", "img/e2.png"]
+        );
+        assert_eq!(Assets::load_steps(&manifest), 8);
+    }
+
+    #[test]
+    fn missing_entries_fall_back_to_the_built_in_list() {
+        let manifest = AssetManifest::parse(r#"{"boss": "img/boss.png"}"#).unwrap();
+        let defaults = AssetManifest::default();
+        assert_eq!(manifest.player, defaults.player);
+        assert_eq!(manifest.enemies, defaults.enemies);
+        assert_eq!(manifest.kill_sounds, defaults.kill_sounds);
+        assert_eq!(manifest.boss.as_deref(), Some("img/boss.png"));
+        assert_eq!(AssetManifest::parse("{}"), Ok(defaults));
+    }
+
+    #[test]
+    fn rejects_malformed_manifests() {
+        let errors = [
+            "{",
+            r#"{"player": 3}"#,
+            r#"{"enemies": "img/e1.png"}"#,
+            r#"{"enemies": []}"#,
+            r#"{"background": " "}"#,
+            r#"{"boss": ""}"#,
+            r#"{"kill_sounds": {"small": ["a", "b", "c", "d"]}}"#,
+            r#"{"kill_sounds": {"tank": ["boom"]}}"#,
+            r#"{"sprites": {}}"#,
+        ];
+        for text in errors {
+            assert!(AssetManifest::parse(text).is_err(), "accepted {}", text);
+        }
+        let too_many = format!(r#"{{"enemies": {:?}}}"#, vec!["e.png"; MAX_ENEMY_VARIANTS + 1]);
+        assert!(AssetManifest::parse(&too_many).is_err());
+    }
+}
@@ -0,0 +1,489 @@
+use std::cell::{Cell, RefCell};
+
+use js_sys::{ArrayBuffer, Promise};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    window, AudioBuffer, AudioContext, AudioContextState, Document, GainNode, HtmlAudioElement,
+    Response,
+};
+
+use crate::assets::{next_animation_frame, FrameBudgetQueue, LoadProgress};
+use crate::console_log;
+use crate::game::{
+    AudioSink, KillSounds, BOMB_READY_SOUND, BOSS_WARNING_SOUND, ESCAPE_SOUND, EXTRA_LIFE_SOUND,
+    SHIELD_BREAK_SOUND,
+};
+use crate::lifecycle::{update_debug_counts, EventListeners, Subsystem};
+
+// ループ再生する音のaudio要素のid
+const MUSIC_ID: &str = "bgmSound";
+const LASER_SOUND_ID: &str = "laserSound";
+
+// 1つの効果音を同時に鳴らせる数の既定値
+pub const DEFAULT_POOL_SIZE: usize = 4;
+
+// 同じ効果音を重ねて鳴らすための要素の組。順番に使い回す
+pub struct SoundPool {
+    elements: Vec<HtmlAudioElement>,
+    next: Cell<usize>,
+}
+
+impl SoundPool {
+    // ページ上のaudio要素を複製して size 個の要素を用意する
+    pub fn new(source: HtmlAudioElement, size: usize) -> Self {
+        let mut elements = Vec::with_capacity(size.max(1));
+        for _ in 1..size.max(1) {
+            match source.clone_node() {
+                Ok(node) => match node.dyn_into::<HtmlAudioElement>() {
+                    Ok(element) => elements.push(element),
+                    Err(_) => console_log!("Cloned audio node is not an audio element"),
+                },
+                Err(e) => console_log!("Error cloning audio element: {:?}", e),
+            }
+        }
+        elements.push(source);
+        SoundPool {
+            elements,
+            next: Cell::new(0),
+        }
+    }
+
+    pub fn play(&self, volume: f64) {
+        let index = self.next.get();
+        self.next.set((index + 1) % self.elements.len());
+
+        let element = &self.elements[index];
+        element.set_volume(volume);
+        element.set_current_time(0.0);
+        // 自動再生の制限などで再生が拒否されても、ゲームは止めずに記録だけ残す
+        match element.play() {
+            Ok(promise) => wasm_bindgen_futures::spawn_local(async move {
+                if let Err(e) = JsFuture::from(promise).await {
+                    console_log!("Audio playback was rejected: {:?}", e);
+                }
+            }),
+            Err(e) => console_log!("Error playing audio: {:?}", e),
+        }
+    }
+}
+
+// ループ再生する音(BGMやレーザー)。ページにaudio要素がなければ何もしない
+pub struct LoopingSound {
+    id: &'static str,
+    element: Option<HtmlAudioElement>,
+}
+
+impl LoopingSound {
+    pub fn find(document: &Document, id: &'static str) -> Self {
+        let element = document
+            .get_element_by_id(id)
+            .and_then(|element| element.dyn_into::<HtmlAudioElement>().ok());
+        match &element {
+            Some(element) => element.set_loop(true),
+            None => console_log!("No #{} element, playing without it", id),
+        }
+        LoopingSound { id, element }
+    }
+
+    pub fn set_playing(&self, playing: bool) {
+        let Some(element) = &self.element else {
+            return;
+        };
+        if !playing {
+            if let Err(e) = element.pause() {
+                console_log!("Error pausing {}: {:?}", self.id, e);
+            }
+            return;
+        }
+        let id = self.id;
+        match element.play() {
+            Ok(promise) => wasm_bindgen_futures::spawn_local(async move {
+                if let Err(e) = JsFuture::from(promise).await {
+                    console_log!("Playback of {} was rejected: {:?}", id, e);
+                }
+            }),
+            Err(e) => console_log!("Error playing {}: {:?}", id, e),
+        }
+    }
+
+    // 止めて先頭に戻す
+    pub fn restart(&self) {
+        if let Some(element) = &self.element {
+            self.set_playing(false);
+            element.set_current_time(0.0);
+        }
+    }
+
+    pub fn set_volume(&self, volume: f64) {
+        if let Some(element) = &self.element {
+            element.set_volume(volume);
+        }
+    }
+}
+
+// HTMLのaudio要素で効果音を鳴らす。連続して鳴らしても前の音を途切れさせない
+pub struct SoundPlayer {
+    pub shot: SoundPool,
+    pub explosion: SoundPool,
+    pub clips: Vec<(String, SoundPool)>, // 名前で鳴らす効果音
+    pub volume: Cell<f64>, // 再生のたびに要素へ設定する音量
+    pub music: LoopingSound,
+    pub laser: LoopingSound, // 押している間だけ鳴らすレーザーの音
+}
+
+impl SoundPlayer {
+    pub fn new(
+        document: &Document,
+        shoot_sound: HtmlAudioElement,
+        explosion_sound: HtmlAudioElement,
+        kill_sounds: &KillSounds,
+        pool_size: usize,
+    ) -> Self {
+        let clips = clip_elements(document, kill_sounds)
+            .map(|(name, element)| (name, SoundPool::new(element, pool_size)))
+            .collect();
+        SoundPlayer {
+            shot: SoundPool::new(shoot_sound, pool_size),
+            explosion: SoundPool::new(explosion_sound, pool_size),
+            clips,
+            volume: Cell::new(1.0),
+            music: LoopingSound::find(document, MUSIC_ID),
+            laser: LoopingSound::find(document, LASER_SOUND_ID),
+        }
+    }
+}
+
+// 効果音の一覧にある名前のaudio要素を集める(ページにないものと、重複した名前は飛ばす)
+fn clip_elements<'a>(
+    document: &'a Document,
+    kill_sounds: &'a KillSounds,
+) -> impl Iterator<Item = (String, HtmlAudioElement)> + 'a {
+    let mut seen = Vec::new();
+    kill_sounds
+        .names()
+        .chain(
+            [
+                EXTRA_LIFE_SOUND,
+                SHIELD_BREAK_SOUND,
+                ESCAPE_SOUND,
+                BOSS_WARNING_SOUND,
+                BOMB_READY_SOUND,
+            ],
+        )
+        .filter_map(move |name| {
+            if seen.contains(&name) {
+                return None;
+            }
+            seen.push(name);
+            let element = document
+                .get_element_by_id(name)?
+                .dyn_into::<HtmlAudioElement>()
+                .ok()?;
+            Some((name.to_string(), element))
+        })
+}
+
+impl AudioSink for SoundPlayer {
+    fn play_shot(&self) {
+        self.shot.play(self.volume.get());
+    }
+
+    fn play_explosion(&self) {
+        self.explosion.play(self.volume.get());
+    }
+
+    fn play_clip(&self, name: &str) {
+        match self.clips.iter().find(|(clip, _)| *clip == name) {
+            Some((_, pool)) => pool.play(self.volume.get()),
+            None => self.play_explosion(),
+        }
+    }
+
+    fn set_volume(&self, volume: f64) {
+        self.volume.set(volume);
+        self.laser.set_volume(volume);
+    }
+
+    fn shutdown(&self) {
+        self.music.restart();
+        self.laser.restart();
+    }
+
+    fn set_music_playing(&self, playing: bool) {
+        self.music.set_playing(playing);
+    }
+
+    fn restart_music(&self) {
+        self.music.restart();
+    }
+
+    fn set_music_volume(&self, volume: f64) {
+        self.music.set_volume(volume);
+    }
+
+    fn set_laser_playing(&self, playing: bool) {
+        self.laser.set_playing(playing);
+    }
+}
+
+// Web Audio APIで効果音を読み込む途中の状態
+// 音声ファイルの取得は開始時に始まり、画像の読み込みと並行して進む
+pub struct WebAudioLoader {
+    context: AudioContext,
+    shot: Promise,
+    explosion: Promise,
+    clips: Vec<(String, Promise)>,
+    music: LoopingSound,
+    laser: LoopingSound,
+}
+
+impl WebAudioLoader {
+    // AudioContextを作り、各audio要素の音声ファイルの取得を始める
+    pub fn start(
+        document: &Document,
+        shoot_sound: &HtmlAudioElement,
+        explosion_sound: &HtmlAudioElement,
+        kill_sounds: &KillSounds,
+    ) -> Result<Self, JsValue> {
+        let window = window().ok_or_else(|| JsValue::from_str("no global `window` exists"))?;
+        let context = AudioContext::new()?;
+        update_debug_counts(|counts| counts.audio_contexts += 1);
+        let fetch = |element: &HtmlAudioElement| window.fetch_with_str(&element.src());
+        let clips = clip_elements(document, kill_sounds)
+            .map(|(name, element)| (name, fetch(&element)))
+            .collect();
+        Ok(WebAudioLoader {
+            shot: fetch(shoot_sound),
+            explosion: fetch(explosion_sound),
+            clips,
+            context,
+            music: LoopingSound::find(document, MUSIC_ID),
+            laser: LoopingSound::find(document, LASER_SOUND_ID),
+        })
+    }
+
+    // 進捗バーで使う段階の数(デコードする音声ファイルの数)
+    pub fn load_steps(&self) -> usize {
+        2 + self.clips.len()
+    }
+
+    // 取得した音声ファイルをすべてデコードする
+    // 失敗したときはAudioContextを閉じてから返す(audio要素での再生に切り替えるため)
+    pub async fn finish(self, progress: &mut LoadProgress<'_>) -> Result<WebAudioPlayer, JsValue> {
+        let context = self.context.clone();
+        let result = self.decode_all(progress).await;
+        if result.is_err() {
+            close_context(&context);
+        }
+        result
+    }
+
+    // 読み込みをやめ、AudioContextを閉じる
+    pub fn cancel(self) {
+        close_context(&self.context);
+    }
+
+    // デコードは画像と同じく1フレームに1つずつ行い、終わるたびに進捗バーを進める
+    async fn decode_all(self, progress: &mut LoadProgress<'_>) -> Result<WebAudioPlayer, JsValue> {
+        let context = self.context;
+        let mut queue = FrameBudgetQueue::new(1);
+        queue.push((None, self.shot));
+        queue.push((None, self.explosion));
+        for (name, promise) in self.clips {
+            queue.push((Some(name), promise));
+        }
+
+        let mut required = Vec::with_capacity(2);
+        let mut clips = Vec::new();
+        while !queue.is_empty() {
+            next_animation_frame().await;
+            for (name, promise) in queue.next_frame() {
+                let decoded = decode_sound(&context, promise).await;
+                progress.advance();
+                match (name, decoded) {
+                    (None, decoded) => required.push(decoded?),
+                    (Some(name), Ok(buffer)) => clips.push((name, buffer)),
+                    // 名前で鳴らす効果音は読めなければ爆発音で代用するので、失敗しても続ける
+                    (Some(name), Err(e)) => console_log!("Error decoding sound {}: {:?}", name, e),
+                }
+            }
+        }
+        let mut required = required.into_iter();
+        let shot = required.next().unwrap();
+        let explosion = required.next().unwrap();
+
+        let master = context.create_gain()?;
+        master.connect_with_audio_node(&context.destination())?;
+        let gesture_listeners = RefCell::new(resume_on_gesture(&context));
+        Ok(WebAudioPlayer {
+            context,
+            master,
+            shot,
+            explosion,
+            clips,
+            music: self.music,
+            laser: self.laser,
+            gesture_listeners,
+            closed: Cell::new(false),
+        })
+    }
+}
+
+// AudioContextを閉じる。既に閉じていれば何もしない
+fn close_context(context: &AudioContext) {
+    if context.state() == AudioContextState::Closed {
+        return;
+    }
+    update_debug_counts(|counts| counts.audio_contexts -= 1);
+    match context.close() {
+        Ok(promise) => wasm_bindgen_futures::spawn_local(async move {
+            if let Err(e) = JsFuture::from(promise).await {
+                console_log!("Error closing audio: {:?}", e);
+            }
+        }),
+        Err(e) => console_log!("Error closing audio: {:?}", e),
+    }
+}
+
+// 取得中の音声ファイルを待ってAudioBufferにデコードする
+async fn decode_sound(context: &AudioContext, fetch: Promise) -> Result<AudioBuffer, JsValue> {
+    let response: Response = JsFuture::from(fetch).await?.dyn_into()?;
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!(
+            "failed to fetch {} ({})",
+            response.url(),
+            response.status()
+        )));
+    }
+    let data: ArrayBuffer = JsFuture::from(response.array_buffer()?).await?.dyn_into()?;
+    JsFuture::from(context.decode_audio_data(&data)?).await?.dyn_into()
+}
+
+// 自動再生の制限で止められたAudioContextを、最初の操作で再開する
+fn resume_on_gesture(context: &AudioContext) -> EventListeners {
+    let mut listeners = EventListeners::default();
+    let Some(window) = window() else {
+        return listeners;
+    };
+    for event in ["keydown", "pointerdown"] {
+        let context = context.clone();
+        let closure = Closure::wrap(Box::new(move || {
+            if context.state() != AudioContextState::Suspended {
+                return;
+            }
+            match context.resume() {
+                Ok(promise) => wasm_bindgen_futures::spawn_local(async move {
+                    if let Err(e) = JsFuture::from(promise).await {
+                        console_log!("Error resuming audio: {:?}", e);
+                    }
+                }),
+                Err(e) => console_log!("Error resuming audio: {:?}", e),
+            }
+        }) as Box<dyn FnMut()>);
+        listeners.add(&window, event, closure);
+    }
+    listeners
+}
+
+// デコード済みの音声をWeb Audio APIで鳴らす。遅延が小さく、何重にも重ねて鳴らせる
+pub struct WebAudioPlayer {
+    context: AudioContext,
+    master: GainNode, // すべての効果音が通る音量調整
+    shot: AudioBuffer,
+    explosion: AudioBuffer,
+    clips: Vec<(String, AudioBuffer)>,
+    music: LoopingSound, // BGMは長いのでデコードせずaudio要素で流す
+    laser: LoopingSound, // 途中で止める音なのでaudio要素で流す
+    gesture_listeners: RefCell<EventListeners>, // AudioContextを再開するためのリスナー
+    closed: Cell<bool>,                         // 停止時にAudioContextを閉じたか
+}
+
+impl WebAudioPlayer {
+    // 再生のたびに使い捨ての再生ノードを作る
+    fn play_buffer(&self, buffer: &AudioBuffer) {
+        let result = self.context.create_buffer_source().and_then(|source| {
+            source.set_buffer(Some(buffer));
+            source.connect_with_audio_node(&self.master)?;
+            source.start()
+        });
+        if let Err(e) = result {
+            console_log!("Error playing audio: {:?}", e);
+        }
+    }
+}
+
+impl AudioSink for WebAudioPlayer {
+    fn play_shot(&self) {
+        self.play_buffer(&self.shot);
+    }
+
+    fn play_explosion(&self) {
+        self.play_buffer(&self.explosion);
+    }
+
+    fn play_clip(&self, name: &str) {
+        match self.clips.iter().find(|(clip, _)| *clip == name) {
+            Some((_, buffer)) => self.play_buffer(buffer),
+            None => self.play_explosion(),
+        }
+    }
+
+    fn set_volume(&self, volume: f64) {
+        self.master.gain().set_value(volume as f32);
+        // レーザーの音はmasterを通らないので、要素の音量を合わせる
+        self.laser.set_volume(volume);
+    }
+
+    fn shutdown(&self) {
+        self.music.restart();
+        self.laser.restart();
+        self.gesture_listeners.borrow_mut().shutdown();
+        // 閉じた状態になるのは閉じる処理が終わってからなので、2回目は自分で見分ける
+        if !self.closed.replace(true) {
+            close_context(&self.context);
+        }
+    }
+
+    fn set_music_playing(&self, playing: bool) {
+        self.music.set_playing(playing);
+    }
+
+    fn restart_music(&self) {
+        self.music.restart();
+    }
+
+    fn set_music_volume(&self, volume: f64) {
+        self.music.set_volume(volume);
+    }
+
+    fn set_laser_playing(&self, playing: bool) {
+        self.laser.set_playing(playing);
+    }
+}
+
+// Web Audio APIの効果音を用意する。使えなければaudio要素で鳴らす
+pub async fn load_audio(
+    loader: Result<WebAudioLoader, JsValue>,
+    document: &Document,
+    shoot_sound: HtmlAudioElement,
+    explosion_sound: HtmlAudioElement,
+    kill_sounds: &KillSounds,
+    progress: &mut LoadProgress<'_>,
+) -> Box<dyn AudioSink> {
+    match loader {
+        Ok(loader) => match loader.finish(progress).await {
+            Ok(player) => return Box::new(player),
+            Err(e) => console_log!("Error loading sounds, using audio elements: {:?}", e),
+        },
+        Err(e) => console_log!("Web Audio is unavailable, using audio elements: {:?}", e),
+    }
+    Box::new(SoundPlayer::new(
+        document,
+        shoot_sound,
+        explosion_sound,
+        kill_sounds,
+        DEFAULT_POOL_SIZE,
+    ))
+}
@@ -0,0 +1,353 @@
+use std::cell::{Cell, RefCell};
+use std::f64::consts::PI;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{
+    window, CanvasRenderingContext2d, Document, Element, HtmlCanvasElement, HtmlElement,
+    HtmlImageElement,
+};
+
+use crate::assets::{is_loaded, Assets};
+use crate::console_log;
+use crate::game::{Renderer, Sprite, TextAlign, TextStyle, BOSS_SIZE};
+use crate::lifecycle::{EventListeners, Subsystem};
+use crate::text::{format_number, TextPainter};
+
+// Canvasに描画するRenderer
+pub struct CanvasRenderer {
+    pub context: CanvasRenderingContext2d,
+    pub player_image: HtmlImageElement,
+    pub background_image: HtmlImageElement,
+    pub enemy_images: Vec<HtmlImageElement>, // 敵の見た目の種類ごとの画像(1枚以上)
+    pub starfield_image: Option<HtmlImageElement>,
+    pub boss_image: Option<HtmlImageElement>,
+    pub text: TextPainter,
+    pub number_buffer: RefCell<String>, // スコア表示用の文字列の領域
+    pub score_element: Option<Element>, // ページ側の表示(なければ表示しない)
+    pub lives_element: Option<Element>,
+    pub game_over_element: Option<HtmlElement>,
+    pub last_hud: Cell<Option<(u32, u32, bool)>>, // 最後に書き込んだスコア、ライフ、ゲームオーバー
+}
+
+impl CanvasRenderer {
+    pub fn new(document: &Document, context: CanvasRenderingContext2d, assets: Assets) -> Self {
+        // 表示用の要素は必須ではないので、なければ警告だけ出して続ける
+        let find = |id: &str| {
+            let element = document.get_element_by_id(id);
+            if element.is_none() {
+                console_log!("Warning: #{} element is missing, it will not be updated", id);
+            }
+            element
+        };
+        let score_element = find("score");
+        let lives_element = find("lives");
+        let game_over_element = find("gameOver").and_then(|element| element.dyn_into().ok());
+        CanvasRenderer {
+            context,
+            player_image: assets.player_image,
+            background_image: assets.background_image,
+            enemy_images: assets.enemy_images,
+            starfield_image: assets.starfield_image,
+            boss_image: assets.boss_image,
+            text: TextPainter::new(),
+            number_buffer: RefCell::new(String::new()),
+            score_element,
+            lives_element,
+            game_over_element,
+            last_hud: Cell::new(None),
+        }
+    }
+
+    // 描画できる画像。読み込みに失敗した画像はNone
+    fn image(&self, sprite: Sprite) -> Option<&HtmlImageElement> {
+        let image = match sprite {
+            Sprite::Player => Some(&self.player_image),
+            Sprite::Enemy(index) => self.enemy_images.get(index).or(self.enemy_images.first()),
+            Sprite::Background => Some(&self.background_image),
+            Sprite::Boss => self.boss_image.as_ref(),
+            Sprite::Starfield => self.starfield_image.as_ref(),
+        };
+        image.filter(|image| is_loaded(image))
+    }
+}
+
+// 高解像度ディスプレイでぼやけないよう、Canvasの実ピクセル数を devicePixelRatio 倍にする
+// 表示上の大きさとゲームの座標は size (CSSピクセル) のまま変わらない
+pub fn apply_pixel_ratio(
+    canvas: &HtmlCanvasElement,
+    context: &CanvasRenderingContext2d,
+    (width, height): (f64, f64),
+    ratio: f64,
+) {
+    let style = canvas.style();
+    if let Err(e) = style
+        .set_property("width", &format!("{}px", width))
+        .and_then(|_| style.set_property("height", &format!("{}px", height)))
+    {
+        console_log!("Error setting canvas size: {:?}", e);
+    }
+
+    // 大きさを変えるとコンテキストの状態が初期化されるので、その後で拡大を設定する
+    canvas.set_width((width * ratio).round() as u32);
+    canvas.set_height((height * ratio).round() as u32);
+    if let Err(e) = context.set_transform(ratio, 0.0, 0.0, ratio, 0.0, 0.0) {
+        console_log!("Error scaling canvas: {:?}", e);
+    }
+}
+
+// 拡大する前のCanvasの大きさを残しておく属性
+const BASE_WIDTH_ATTRIBUTE: &str = "data-base-width";
+const BASE_HEIGHT_ATTRIBUTE: &str = "data-base-height";
+
+// ゲームの座標に使うCanvasの大きさ(CSSピクセル)
+// 拡大したままのCanvasでも、実ピクセル数ではなく拡大する前の大きさを返す
+pub fn logical_size(canvas: &HtmlCanvasElement) -> (f64, f64) {
+    let base = |name: &str, pixels: u32| {
+        canvas
+            .get_attribute(name)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(pixels as f64)
+    };
+    (
+        base(BASE_WIDTH_ATTRIBUTE, canvas.width()),
+        base(BASE_HEIGHT_ATTRIBUTE, canvas.height()),
+    )
+}
+
+// 拡大する前のCanvasの状態。停止時に大きさ、CSSの大きさ、変換を元に戻す
+// 既定値はCanvasを持たず、戻すものがない
+#[derive(Default)]
+pub struct CanvasRestore {
+    canvas: Option<(HtmlCanvasElement, CanvasRenderingContext2d)>, // 戻したらNone
+    size: (f64, f64),
+    style_size: (String, String), // 元のCSSの width と height(指定がなければ空)
+}
+
+impl Subsystem for CanvasRestore {
+    fn shutdown(&mut self) {
+        let Some((canvas, context)) = self.canvas.take() else {
+            return;
+        };
+        let (width, height) = self.size;
+        canvas.set_width(width as u32);
+        canvas.set_height(height as u32);
+        for name in [BASE_WIDTH_ATTRIBUTE, BASE_HEIGHT_ATTRIBUTE] {
+            if let Err(e) = canvas.remove_attribute(name) {
+                console_log!("Error removing {}: {:?}", name, e);
+            }
+        }
+        let style = canvas.style();
+        for (property, value) in [("width", &self.style_size.0), ("height", &self.style_size.1)] {
+            let result = if value.is_empty() {
+                style.remove_property(property).map(|_| ())
+            } else {
+                style.set_property(property, value)
+            };
+            if let Err(e) = result {
+                console_log!("Error restoring canvas {}: {:?}", property, e);
+            }
+        }
+        if let Err(e) = context.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0) {
+            console_log!("Error resetting canvas transform: {:?}", e);
+        }
+    }
+}
+
+// 別のモニターへ移動したときなどに devicePixelRatio が変わったら設定し直す
+// size は拡大する前の大きさ(logical_size)で、停止時にはこの大きさに戻す
+pub fn watch_pixel_ratio(
+    context: CanvasRenderingContext2d,
+    size: (f64, f64),
+    listeners: &mut EventListeners,
+) -> CanvasRestore {
+    let Some(canvas) = context.canvas() else {
+        return CanvasRestore {
+            canvas: None,
+            size,
+            style_size: Default::default(),
+        };
+    };
+    let style = canvas.style();
+    let style_value = |property| style.get_property_value(property).unwrap_or_default();
+    let restore = CanvasRestore {
+        canvas: Some((canvas.clone(), context.clone())),
+        size,
+        style_size: (style_value("width"), style_value("height")),
+    };
+    for (name, value) in [(BASE_WIDTH_ATTRIBUTE, size.0), (BASE_HEIGHT_ATTRIBUTE, size.1)] {
+        if let Err(e) = canvas.set_attribute(name, &value.to_string()) {
+            console_log!("Error setting {}: {:?}", name, e);
+        }
+    }
+
+    let current_window = window().expect("no global `window` exists");
+    let current = Cell::new(current_window.device_pixel_ratio());
+    apply_pixel_ratio(&canvas, &context, size, current.get());
+
+    let closure = Closure::wrap(Box::new(move || {
+        let ratio = window().map_or(1.0, |window| window.device_pixel_ratio());
+        if ratio != current.get() {
+            current.set(ratio);
+            apply_pixel_ratio(&canvas, &context, size, ratio);
+        }
+    }) as Box<dyn FnMut()>);
+    listeners.add(&current_window, "resize", closure);
+    restore
+}
+
+impl Renderer for CanvasRenderer {
+    fn clear(&self, width: f64, height: f64) {
+        self.context.clear_rect(0.0, 0.0, width, height);
+    }
+
+    fn draw_sprite(&self, sprite: Sprite, x: f64, y: f64) {
+        // ボスの画像がなければ、通常の敵の画像をボスの大きさに広げて代わりにする
+        let result = match (sprite, self.image(sprite)) {
+            (_, Some(image)) => self.context.draw_image_with_html_image_element(image, x, y),
+            (Sprite::Boss, None) => match self.image(Sprite::Enemy(0)) {
+                Some(image) => self
+                    .context
+                    .draw_image_with_html_image_element_and_dw_and_dh(
+                        image, x, y, BOSS_SIZE, BOSS_SIZE,
+                    ),
+                None => return,
+            },
+            // 読み込まれていない画像は描画しない
+            (_, None) => return,
+        };
+        if let Err(e) = result {
+            console_log!("Error drawing {:?}: {:?}", sprite, e);
+        }
+    }
+
+    fn has_sprite(&self, sprite: Sprite) -> bool {
+        match sprite {
+            Sprite::Boss => self.image(Sprite::Boss).or(self.image(Sprite::Enemy(0))).is_some(),
+            _ => self.image(sprite).is_some(),
+        }
+    }
+
+    fn draw_circle(&self, x: f64, y: f64, radius: f64, color: &str) {
+        self.context.begin_path();
+        if let Err(e) = self.context.arc(x, y, radius, 0.0, PI * 2.0) {
+            console_log!("Error drawing arc: {:?}", e);
+        }
+        self.context.set_fill_style(&JsValue::from_str(color));
+        self.context.fill();
+    }
+
+    fn stroke_circle(&self, x: f64, y: f64, radius: f64, color: &str, line_width: f64) {
+        self.stroke_arc(x, y, radius, (0.0, PI * 2.0), color, line_width);
+    }
+
+    fn stroke_arc(
+        &self,
+        x: f64,
+        y: f64,
+        radius: f64,
+        (start, end): (f64, f64),
+        color: &str,
+        line_width: f64,
+    ) {
+        self.context.begin_path();
+        if let Err(e) = self.context.arc(x, y, radius, start, end) {
+            console_log!("Error drawing arc: {:?}", e);
+        }
+        self.context.set_stroke_style(&JsValue::from_str(color));
+        self.context.set_line_width(line_width);
+        self.context.stroke();
+    }
+
+    fn fill_polygon(&self, points: &[(f64, f64)], color: &str) {
+        let mut points = points.iter();
+        let Some(&(x, y)) = points.next() else {
+            return;
+        };
+        self.context.begin_path();
+        self.context.move_to(x, y);
+        for &(x, y) in points {
+            self.context.line_to(x, y);
+        }
+        self.context.close_path();
+        self.context.set_fill_style(&JsValue::from_str(color));
+        self.context.fill();
+    }
+
+    fn stroke_polygon(&self, points: &[(f64, f64)], color: &str, line_width: f64) {
+        let mut points = points.iter();
+        let Some(&(x, y)) = points.next() else {
+            return;
+        };
+        self.context.begin_path();
+        self.context.move_to(x, y);
+        for &(x, y) in points {
+            self.context.line_to(x, y);
+        }
+        self.context.close_path();
+        self.context.set_stroke_style(&JsValue::from_str(color));
+        self.context.set_line_width(line_width);
+        self.context.stroke();
+    }
+
+    fn enemy_variants(&self) -> usize {
+        self.enemy_images.len().max(1)
+    }
+
+    fn draw_text(
+        &self,
+        text: &str,
+        x: f64,
+        y: f64,
+        size: f64,
+        align: TextAlign,
+        style: &TextStyle,
+    ) {
+        self.text.draw_text(&self.context, text, (x, y), size, align, style);
+    }
+
+    fn push_offset(&self, dx: f64, dy: f64) {
+        self.context.save();
+        if let Err(e) = self.context.translate(dx, dy) {
+            console_log!("Error translating canvas: {:?}", e);
+        }
+    }
+
+    fn pop_offset(&self) {
+        self.context.restore();
+    }
+
+    fn set_alpha(&self, alpha: f64) {
+        self.context.set_global_alpha(alpha);
+    }
+
+    // 毎フレーム呼ばれるので、値が変わったときだけDOMに書き込む
+    fn update_hud(&self, score: u32, lives: u32, game_over: bool) {
+        let last = self.last_hud.replace(Some((score, lives, game_over)));
+        let (score_changed, lives_changed, game_over_changed) = match last {
+            Some((last_score, last_lives, last_game_over)) => (
+                last_score != score,
+                last_lives != lives,
+                last_game_over != game_over,
+            ),
+            None => (true, true, true),
+        };
+        let mut buffer = self.number_buffer.borrow_mut();
+
+        // スコアをHTML要素に反映
+        if let Some(element) = self.score_element.as_ref().filter(|_| score_changed) {
+            element.set_text_content(Some(format_number(score, &mut buffer)));
+        }
+
+        // ライフをHTML要素に反映
+        if let Some(element) = self.lives_element.as_ref().filter(|_| lives_changed) {
+            element.set_text_content(Some(format_number(lives, &mut buffer)));
+        }
+
+        // ゲームオーバー時のみ表示する(切り替わったときだけ書き込む)
+        if let Some(element) = self.game_over_element.as_ref().filter(|_| game_over_changed) {
+            let display = if game_over { "block" } else { "none" };
+            if let Err(e) = element.style().set_property("display", display) {
+                console_log!("Error updating game over display: {:?}", e);
+            }
+        }
+    }
+}
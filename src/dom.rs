@@ -0,0 +1,58 @@
+// ページの読み込みを待つための補助
+use js_sys::Promise;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{window, Document, Element};
+
+use crate::console_log;
+use crate::error::GameError;
+
+// 要素を探し直す間隔(ms)
+const POLL_INTERVAL: i32 = 50;
+
+// 指定した時間だけ待つ
+async fn sleep(ms: i32) {
+    let promise = Promise::new(&mut |resolve, _| {
+        if let Some(window) = window() {
+            if let Err(e) =
+                window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms)
+            {
+                console_log!("Error setting timeout: {:?}", e);
+            }
+        }
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+// DOMの構築が終わるまで待つ(<head>から読み込まれた場合に備える)
+pub async fn dom_ready(document: &Document) {
+    if document.ready_state() != "loading" {
+        return;
+    }
+    let promise = Promise::new(&mut |resolve, _| {
+        if let Err(e) = document.add_event_listener_with_callback("DOMContentLoaded", &resolve) {
+            console_log!("Error adding DOMContentLoaded listener: {:?}", e);
+        }
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+// 要素が現れるまで最大 timeout_ms だけ待つ(後から要素を追加するフレームワーク向け)
+pub async fn wait_for_element<T: JsCast>(
+    document: &Document,
+    id: &str,
+    timeout_ms: f64,
+) -> Result<T, GameError> {
+    let started = js_sys::Date::now();
+    loop {
+        if let Some(element) = document.get_element_by_id(id) {
+            return element
+                .dyn_into::<T>()
+                .map_err(|_: Element| GameError::WrongElementType(id.to_string()));
+        }
+        if js_sys::Date::now() - started >= timeout_ms {
+            return Err(GameError::MissingElement(id.to_string()));
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+use wasm_bindgen::JsValue;
+
+// JSへ返すゲームの初期化エラー
+#[derive(Debug)]
+pub enum GameError {
+    MissingElement(String), // 必要な要素がページにない(要素のid)
+    WrongElementType(String), // 要素の種類が違う(要素のid)
+    NoContext, // Canvasの2Dコンテキストを取得できない
+    NoDocument, // windowやdocumentがない(Workerなどで読み込まれた)
+    UnknownMode(String), // 知らない遊び方の名前が指定された
+    UnknownDifficulty(String), // 知らない難易度の名前が指定された
+    InvalidConfig(String), // start_game_with_config の設定を読めないか、値がおかしい
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::MissingElement(id) => write!(f, "missing element #{}", id),
+            GameError::WrongElementType(id) => write!(f, "element #{} has the wrong type", id),
+            GameError::NoContext => write!(f, "canvas 2d context is not available"),
+            GameError::NoDocument => write!(f, "window.document is not available"),
+            GameError::UnknownMode(name) => write!(f, "unknown game mode: {}", name),
+            GameError::UnknownDifficulty(name) => write!(f, "unknown difficulty: {}", name),
+            GameError::InvalidConfig(message) => write!(f, "invalid game config: {}", message),
+        }
+    }
+}
+
+impl From<GameError> for JsValue {
+    fn from(error: GameError) -> JsValue {
+        js_sys::Error::new(&error.to_string()).into()
+    }
+}
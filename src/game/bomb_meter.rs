@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{GameClock, GameTimer};
+
+// 満タンの量(%)
+pub const BOMB_METER_FULL: u32 = 100;
+// 満タンになってボムが増えたときにHUDのメーターを光らせる時間(ms)
+pub const BOMB_METER_FLASH_DURATION: f64 = 600.0;
+
+// 撃破やボスへのダメージで溜まり、満タンになるとボムが1つ増えるメーター
+// 量は整数の%で持ち、満タンを越えてあふれた分は次に持ち越す
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct BombMeter {
+    pub percent: u32, // 溜まっている量(0〜BOMB_METER_FULL)
+    pub flash: GameTimer, // ボムが増えた直後にHUDのメーターを光らせる
+}
+
+impl BombMeter {
+    pub fn new() -> Self {
+        BombMeter::default()
+    }
+
+    // amount(%)を溜め、満タンになった回数(増やすボムの数)を返す。room は今増やせるボムの数
+    // ボムが上限の間は満タンのまま止め、空きができてから次に溜めたときに増やす
+    pub fn fill(&mut self, amount: u32, room: u32, clock: &GameClock) -> u32 {
+        self.percent += amount;
+        let filled = (self.percent / BOMB_METER_FULL).min(room);
+        self.percent -= filled * BOMB_METER_FULL;
+        if filled == room {
+            self.percent = self.percent.min(BOMB_METER_FULL);
+        }
+        if filled > 0 {
+            self.flash.start(clock, BOMB_METER_FLASH_DURATION);
+        }
+        filled
+    }
+
+    pub fn reset(&mut self) {
+        self.percent = 0;
+    }
+}
+
+// ボスの体力を phases 段階に区切ったとき、削りきった段階の数(倒していれば phases)
+pub fn boss_phase(hp: u32, max_hp: u32, phases: u32) -> u32 {
+    if max_hp == 0 {
+        return phases;
+    }
+    let lost = (max_hp - hp.min(max_hp)) as u64;
+    (lost * phases as u64 / max_hp as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overflow_carries_into_the_next_bomb() {
+        let clock = GameClock::new();
+        let mut meter = BombMeter::new();
+        assert_eq!(meter.fill(96, 3, &clock), 0);
+        assert!(!meter.flash.is_running(&clock));
+        // 満タンを越えた分は次に持ち越す
+        assert_eq!(meter.fill(10, 3, &clock), 1);
+        assert_eq!(meter.percent, 6);
+        assert!(meter.flash.is_running(&clock));
+        // 一度に何本分も溜まれば、空きの数まで増やす
+        assert_eq!(meter.fill(250, 3, &clock), 2);
+        assert_eq!(meter.percent, 56);
+    }
+
+    #[test]
+    fn holds_full_while_bombs_are_at_the_cap() {
+        let clock = GameClock::new();
+        let mut meter = BombMeter::new();
+        meter.percent = 98;
+        assert_eq!(meter.fill(10, 0, &clock), 0);
+        assert_eq!(meter.percent, BOMB_METER_FULL);
+        // 上限の間に溜めた分は捨てる
+        assert_eq!(meter.fill(10, 0, &clock), 0);
+        assert_eq!(meter.percent, BOMB_METER_FULL);
+        // ボムを使って空きができたら、次に溜めたときに増える
+        assert_eq!(meter.fill(2, 1, &clock), 1);
+        assert_eq!(meter.percent, 2);
+        // 空きより多く溜まった分も満タンで止める
+        assert_eq!(meter.fill(300, 1, &clock), 1);
+        assert_eq!(meter.percent, BOMB_METER_FULL);
+    }
+
+    #[test]
+    fn boss_phases_split_its_health_evenly() {
+        assert_eq!(boss_phase(40, 40, 4), 0);
+        assert_eq!(boss_phase(31, 40, 4), 0);
+        assert_eq!(boss_phase(30, 40, 4), 1);
+        assert_eq!(boss_phase(1, 40, 4), 3);
+        assert_eq!(boss_phase(0, 40, 4), 4);
+        assert_eq!(boss_phase(0, 0, 4), 4);
+    }
+}
@@ -1,11 +1,278 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::collision::{Collidable, Rect};
+use crate::game::render::{color_name, ColorName};
+use crate::game::Vec2;
+
+// 弾を撃った側。当たり判定の相手を決める
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum BulletOwner {
+    Player, // 敵に当たる
+    Enemy,  // 自機に当たる
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Bullet {
-    pub x: f64,
-    pub y: f64,
+    pub pos: Vec2, // 左上の位置
+    pub prev: Vec2, // 前フレームの位置
     pub radius: f64,
-    pub speed: f64,
-    pub color: String,
+    pub vel: Vec2, // 1ステップあたりの移動量(ゲーム内座標)
+    #[serde(with = "color_name")]
+    pub color: ColorName,
+    pub owner: BulletOwner,
+    pub damage: u32, // 当たった相手に与えるダメージ(自機ならライフの減る数)
+    pub pierce_remaining: u8, // 倒した敵を突き抜けられる残り回数
+    pub has_hit: bool, // 1体以上に当たったかどうか(命中数と外れの判定に使う)
+    pub bounced: bool, // 画面上端で跳ね返った後かどうか
+    pub active: bool, // 使用中かどうか(未使用の枠は更新も描画もしない)
 }
 
+// 自機の弾の速さ(1ステップあたりのpx)
+pub const PLAYER_BULLET_SPEED: f64 = 7.0;
+// 自機の弾の大きさ
+pub const PLAYER_BULLET_RADIUS: f64 = 5.0;
+// 溜め撃ちの弾の大きさの倍率(溜め始めと最大まで溜めたとき)
+const CHARGED_RADIUS_SCALE: (f64, f64) = (1.4, 2.4);
+// 溜め撃ちの弾のダメージ(溜め始めと最大まで溜めたとき)
+const CHARGED_DAMAGE: (u32, u32) = (2, 5);
+// 敵の弾の大きさ
+const ENEMY_BULLET_RADIUS: f64 = 4.0;
+
+// 跳ね返った後の速度の倍率
+pub const RICOCHET_SPEED_FACTOR: f64 = 0.6;
+
+// 同時に存在できる自機の弾の数
+pub const BULLET_POOL_CAPACITY: usize = 64;
+
 impl Bullet {
-    // 必要に応じてメソッドを追加
+    fn new(x: f64, y: f64, radius: f64, (vx, vy): (f64, f64), owner: BulletOwner) -> Self {
+        let pos = Vec2::new(x, y);
+        Bullet {
+            pos,
+            prev: pos,
+            radius,
+            vel: Vec2::new(vx, vy),
+            color: "red",
+            owner,
+            damage: 1,
+            pierce_remaining: 0,
+            has_hit: false,
+            bounced: false,
+            active: true,
+        }
+    }
+
+    // 未使用の枠
+    fn inactive() -> Self {
+        Bullet {
+            active: false,
+            ..Bullet::new(0.0, 0.0, 0.0, (0.0, 0.0), BulletOwner::Player)
+        }
+    }
+
+    // 自機が真上に撃つ弾。(x, y) は弾の左上
+    pub fn new_player_shot(x: f64, y: f64) -> Self {
+        Bullet::new(x, y, PLAYER_BULLET_RADIUS, (0.0, -PLAYER_BULLET_SPEED), BulletOwner::Player)
+    }
+
+    // 自機が溜め撃ちで真上に撃つ弾。power(0.0〜1.0)が大きいほど大きく、ダメージも高い
+    // (center_x, y) は弾の上端の中央
+    pub fn new_charged_shot(center_x: f64, y: f64, power: f64) -> Self {
+        let power = power.clamp(0.0, 1.0);
+        let (min_scale, max_scale) = CHARGED_RADIUS_SCALE;
+        let radius = PLAYER_BULLET_RADIUS * (min_scale + (max_scale - min_scale) * power);
+        let (min_damage, max_damage) = CHARGED_DAMAGE;
+        Bullet {
+            color: "magenta",
+            damage: min_damage + ((max_damage - min_damage) as f64 * power).round() as u32,
+            ..Bullet::new(
+                center_x - radius,
+                y,
+                radius,
+                (0.0, -PLAYER_BULLET_SPEED),
+                BulletOwner::Player,
+            )
+        }
+    }
+
+    // 敵が真下に撃つ弾
+    pub fn new_enemy_shot(x: f64, y: f64) -> Self {
+        Bullet {
+            color: "yellow",
+            ..Bullet::new(x, y, ENEMY_BULLET_RADIUS, (0.0, 4.0), BulletOwner::Enemy)
+        }
+    }
+
+    // 敵が中心座標と進行方向(ラジアン)を指定して撃つ弾
+    pub fn enemy_shot_with_angle(center_x: f64, center_y: f64, angle: f64, speed: f64) -> Self {
+        let radius = ENEMY_BULLET_RADIUS;
+        Bullet {
+            color: "violet",
+            ..Bullet::new(
+                center_x - radius,
+                center_y - radius,
+                radius,
+                (angle.cos() * speed, angle.sin() * speed),
+                BulletOwner::Enemy,
+            )
+        }
+    }
+
+    // 1ステップ分進める
+    pub fn advance(&mut self, step: f64) {
+        self.prev = self.pos;
+        self.pos += self.vel.scale(step);
+    }
+
+    // 画面上端で跳ね返り、速度を落として下向きに進む
+    pub fn bounce(&mut self) {
+        self.pos.y = 0.0;
+        self.vel = Vec2::new(self.vel.x, -self.vel.y).scale(RICOCHET_SPEED_FACTOR);
+        self.bounced = true;
+    }
+
+    // 描いている円の中心
+    pub fn center(&self) -> Vec2 {
+        self.pos + Vec2::new(self.radius, self.radius)
+    }
+
+    // 場の外に出たかどうか(四辺とも判定する)
+    pub fn is_outside(&self, width: f64, height: f64) -> bool {
+        let size = self.radius * 2.0;
+        let Vec2 { x, y } = self.pos;
+        x + size < 0.0 || x > width || y + size < 0.0 || y > height
+    }
+}
+
+// 描くときは (x + radius, y + radius) を中心とする円なので、それに外接する正方形
+impl Collidable for Bullet {
+    fn bounds(&self) -> Rect {
+        let size = self.radius * 2.0;
+        (self.pos.x, self.pos.y, size, size)
+    }
+}
+
+// 自機の弾の置き場。最初に枠を確保しておき、撃つたびに空いた枠を使い回す
+#[derive(Serialize, Deserialize)]
+pub struct BulletPool {
+    pub slots: Vec<Bullet>,
+}
+
+impl BulletPool {
+    pub fn new() -> Self {
+        BulletPool {
+            slots: (0..BULLET_POOL_CAPACITY).map(|_| Bullet::inactive()).collect(),
+        }
+    }
+
+    // 空いた枠に弾を置く。空きがなければ撃てずにfalseを返す
+    pub fn fire(&mut self, bullet: Bullet) -> bool {
+        match self.slots.iter_mut().find(|slot| !slot.active) {
+            Some(slot) => {
+                *slot = Bullet {
+                    active: true,
+                    ..bullet
+                };
+                true
+            }
+            None => false,
+        }
+    }
+
+    // 使用中の弾
+    pub fn iter(&self) -> impl Iterator<Item = &Bullet> {
+        self.slots.iter().filter(|bullet| bullet.active)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Bullet> {
+        self.slots.iter_mut().filter(|bullet| bullet.active)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advances_along_its_velocity() {
+        let mut bullet = Bullet::enemy_shot_with_angle(100.0, 100.0, 0.0, 3.0);
+        assert_eq!(bullet.owner, BulletOwner::Enemy);
+        let start = bullet.pos;
+        bullet.advance(2.0);
+        assert_eq!(bullet.prev, start);
+        assert_eq!(bullet.pos, start + Vec2::new(6.0, 0.0));
+        // 中心は撃った位置から進んだ分だけずれる
+        assert_eq!(bullet.center(), Vec2::new(106.0, 100.0));
+    }
+
+    #[test]
+    fn player_shots_go_up_and_enemy_shots_go_down() {
+        let player = Bullet::new_player_shot(0.0, 0.0);
+        let enemy = Bullet::new_enemy_shot(0.0, 0.0);
+        assert_eq!(player.owner, BulletOwner::Player);
+        assert!(player.vel.y < 0.0 && player.vel.x == 0.0);
+        assert!(enemy.vel.y > 0.0 && enemy.vel.x == 0.0);
+    }
+
+    #[test]
+    fn charged_shots_grow_with_power() {
+        let weak = Bullet::new_charged_shot(50.0, 0.0, 0.0);
+        let strong = Bullet::new_charged_shot(50.0, 0.0, 1.0);
+        assert!(strong.radius > weak.radius);
+        assert_eq!((weak.damage, strong.damage), CHARGED_DAMAGE);
+        // 大きさが変わっても中央は揃える
+        assert_eq!(weak.center().x, 50.0);
+        assert_eq!(strong.center().x, 50.0);
+    }
+
+    #[test]
+    fn bounce_turns_down_and_slows() {
+        let mut bullet = Bullet::new_player_shot(10.0, -3.0);
+        bullet.bounce();
+        assert_eq!(bullet.pos.y, 0.0);
+        assert!(bullet.bounced);
+        assert_eq!(bullet.vel, Vec2::new(0.0, PLAYER_BULLET_SPEED * RICOCHET_SPEED_FACTOR));
+    }
+
+    #[test]
+    fn leaves_the_field_on_every_side() {
+        let at = |x, y| Bullet::new_player_shot(x, y);
+        assert!(!at(50.0, 50.0).is_outside(100.0, 100.0));
+        assert!(at(-11.0, 50.0).is_outside(100.0, 100.0));
+        assert!(at(101.0, 50.0).is_outside(100.0, 100.0));
+        assert!(at(50.0, -11.0).is_outside(100.0, 100.0));
+        assert!(at(50.0, 101.0).is_outside(100.0, 100.0));
+        // 一部でも見えていれば場の中
+        assert!(!at(-9.0, 50.0).is_outside(100.0, 100.0));
+    }
+
+    #[test]
+    fn pool_fills_up_and_reuses_freed_slots() {
+        let mut pool = BulletPool::new();
+        for i in 0..BULLET_POOL_CAPACITY {
+            assert!(pool.fire(Bullet::new_player_shot(i as f64, 0.0)));
+        }
+        // 満杯なら撃てず、使用中の弾も変わらない
+        assert!(!pool.fire(Bullet::new_player_shot(-1.0, 0.0)));
+        assert_eq!(pool.iter().count(), BULLET_POOL_CAPACITY);
+
+        // 消えた弾の枠に次の弾が入る
+        pool.slots[3].active = false;
+        assert_eq!(pool.iter().count(), BULLET_POOL_CAPACITY - 1);
+        assert!(pool.fire(Bullet::new_player_shot(-1.0, 0.0)));
+        assert_eq!(pool.slots[3].pos.x, -1.0);
+        assert_eq!(pool.slots.len(), BULLET_POOL_CAPACITY);
+    }
+
+    #[test]
+    fn pool_only_yields_active_bullets() {
+        let mut pool = BulletPool::new();
+        assert_eq!(pool.iter().count(), 0);
+        pool.fire(Bullet::new_player_shot(5.0, 5.0));
+        pool.iter_mut().for_each(|bullet| bullet.advance(1.0));
+        let active: Vec<&Bullet> = pool.iter().collect();
+        assert_eq!(active.len(), 1);
+        assert!(active[0].pos.y < 5.0);
+        assert!(pool.slots[1..].iter().all(|slot| slot.pos == Bullet::inactive().pos));
+    }
 }
@@ -0,0 +1,270 @@
+// 当たり判定の計算
+
+// 矩形 (x, y, 幅, 高さ)
+pub type Rect = (f64, f64, f64, f64);
+
+// 当たり判定の矩形を持つ物体
+// 描いている形を囲む矩形を返す(自機と敵は画像の範囲、弾は円に外接する正方形)
+pub trait Collidable {
+    fn bounds(&self) -> Rect;
+}
+
+// 当たり判定を行う物体のグループ
+// 破片などの見た目だけの演出はどのグループにも属さない
+// 表は並び順に判定するので、同じ敵に当たるものは先に判定したいものを前に置く
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CollisionGroup {
+    Laser,
+    Missile,
+    PlayerBullet,
+    Enemy,
+    Player,
+    EnemyBullet,
+    PowerUp,
+}
+
+impl CollisionGroup {
+    // すべてのグループ(並びは当たり判定表の行と列の順)
+    pub const ALL: [CollisionGroup; 7] = [
+        CollisionGroup::Laser,
+        CollisionGroup::Missile,
+        CollisionGroup::PlayerBullet,
+        CollisionGroup::Enemy,
+        CollisionGroup::Player,
+        CollisionGroup::EnemyBullet,
+        CollisionGroup::PowerUp,
+    ];
+}
+
+// グループの数(当たり判定表の大きさ)
+pub const COLLISION_GROUP_COUNT: usize = CollisionGroup::ALL.len();
+
+// 2つの矩形が重なっているかどうか
+pub fn rects_overlap(a: Rect, b: Rect) -> bool {
+    a.0 < b.0 + b.2 && a.0 + a.2 > b.0 && a.1 < b.1 + b.3 && a.1 + a.3 > b.1
+}
+
+// 2つの矩形を両方とも含む最小の矩形
+pub fn rect_union(a: Rect, b: Rect) -> Rect {
+    let left = a.0.min(b.0);
+    let top = a.1.min(b.1);
+    let right = (a.0 + a.2).max(b.0 + b.2);
+    let bottom = (a.1 + a.3).max(b.1 + b.3);
+    (left, top, right - left, bottom - top)
+}
+
+// 点から矩形までの最短距離。点が矩形の中にあれば0
+pub fn distance_to_rect((x, y): (f64, f64), (rx, ry, rw, rh): Rect) -> f64 {
+    let dx = (rx - x).max(x - (rx + rw)).max(0.0);
+    let dy = (ry - y).max(y - (ry + rh)).max(0.0);
+    dx.hypot(dy)
+}
+
+// 円(中心と半径)が矩形と重なっているかどうか。縁が接しているだけの場合も含める
+pub fn circle_overlaps_rect(center: (f64, f64), radius: f64, rect: Rect) -> bool {
+    distance_to_rect(center, rect) <= radius
+}
+
+// 横方向にループする画面で、左右の端をまたいでいる矩形の反対側に見える部分
+pub fn wrapped_copy(rect: Rect, field_width: f64) -> Option<Rect> {
+    if rect.0 + rect.2 > field_width {
+        Some((rect.0 - field_width, rect.1, rect.2, rect.3))
+    } else if rect.0 < 0.0 {
+        Some((rect.0 + field_width, rect.1, rect.2, rect.3))
+    } else {
+        None
+    }
+}
+
+// 線分(start→end)が矩形と交差するかどうか(スラブ法)
+pub fn segment_intersects_rect(
+    start: (f64, f64),
+    end: (f64, f64),
+    rect: Rect,
+) -> bool {
+    let (rx, ry, rw, rh) = rect;
+    let mut t_min: f64 = 0.0;
+    let mut t_max: f64 = 1.0;
+
+    for (p, d, lo, hi) in [
+        (start.0, end.0 - start.0, rx, rx + rw),
+        (start.1, end.1 - start.1, ry, ry + rh),
+    ] {
+        if d.abs() < f64::EPSILON {
+            // この軸方向に動いていない場合は範囲内にあるかだけを見る
+            if p <= lo || p >= hi {
+                return false;
+            }
+        } else {
+            let t0 = (lo - p) / d;
+            let t1 = (hi - p) / d;
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+            if t_min > t_max {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+// 点から線分(start→end)までの最短距離
+pub fn distance_to_segment(point: (f64, f64), start: (f64, f64), end: (f64, f64)) -> f64 {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared > 0.0 {
+        (((point.0 - start.0) * dx + (point.1 - start.1) * dy) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (point.0 - (start.0 + dx * t)).hypot(point.1 - (start.1 + dy * t))
+}
+
+// 半径 radius の円が中心を start から end まで移動する間に矩形と重なったかどうか
+// 中心の軌跡と、矩形を半径の分だけ角を丸めて広げた形との交差として判定する
+// (縦か横にだけ広げた2つの矩形と、四隅を中心とする円に分けて調べる)
+pub fn swept_circle_hits_rect(
+    start: (f64, f64),
+    end: (f64, f64),
+    radius: f64,
+    rect: Rect,
+) -> bool {
+    if circle_overlaps_rect(end, radius, rect) {
+        return true;
+    }
+    let (rx, ry, rw, rh) = rect;
+    let wide = (rx - radius, ry, rw + radius * 2.0, rh);
+    let tall = (rx, ry - radius, rw, rh + radius * 2.0);
+    segment_intersects_rect(start, end, wide)
+        || segment_intersects_rect(start, end, tall)
+        || [(rx, ry), (rx + rw, ry), (rx, ry + rh), (rx + rw, ry + rh)]
+            .into_iter()
+            .any(|corner| distance_to_segment(corner, start, end) <= radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOX: Rect = (0.0, 0.0, 10.0, 10.0);
+
+    #[test]
+    fn touching_edges_do_not_overlap() {
+        assert!(!rects_overlap(BOX, (10.0, 0.0, 10.0, 10.0)));
+        assert!(!rects_overlap(BOX, (0.0, 10.0, 10.0, 10.0)));
+        assert!(!rects_overlap(BOX, (-10.0, -10.0, 10.0, 10.0)));
+        // 少しでも入り込めば重なる
+        assert!(rects_overlap(BOX, (9.999, 0.0, 10.0, 10.0)));
+    }
+
+    #[test]
+    fn containment_overlaps_both_ways() {
+        let inner = (2.0, 3.0, 4.0, 5.0);
+        assert!(rects_overlap(BOX, inner));
+        assert!(rects_overlap(inner, BOX));
+        assert!(rects_overlap(BOX, BOX));
+    }
+
+    #[test]
+    fn zero_size_boxes_overlap_only_strictly_inside() {
+        assert!(rects_overlap(BOX, (5.0, 5.0, 0.0, 0.0)));
+        assert!(!rects_overlap(BOX, (0.0, 5.0, 0.0, 0.0)));
+        assert!(!rects_overlap(BOX, (10.0, 10.0, 0.0, 0.0)));
+        let point = (5.0, 5.0, 0.0, 0.0);
+        assert!(!rects_overlap(point, point));
+    }
+
+    #[test]
+    fn negative_coordinates() {
+        let left = (-20.0, -20.0, 15.0, 15.0);
+        assert!(rects_overlap(left, (-6.0, -6.0, 2.0, 2.0)));
+        assert!(!rects_overlap(left, BOX));
+        assert_eq!(rect_union(left, BOX), (-20.0, -20.0, 30.0, 30.0));
+        assert_eq!(distance_to_rect((-3.0, -4.0), BOX), 5.0);
+    }
+
+    #[test]
+    fn circle_against_rect() {
+        // 中心が中にある
+        assert!(circle_overlaps_rect((5.0, 5.0), 1.0, BOX));
+        // 辺にちょうど接する
+        assert!(circle_overlaps_rect((12.0, 5.0), 2.0, BOX));
+        assert!(!circle_overlaps_rect((12.1, 5.0), 2.0, BOX));
+        // 角の近くでは角までの距離で判定する
+        assert!(circle_overlaps_rect((13.0, 14.0), 5.0, BOX));
+        assert!(!circle_overlaps_rect((13.0, 14.0), 4.99, BOX));
+        // 大きさ0の円は点として扱う
+        assert!(circle_overlaps_rect((0.0, 0.0), 0.0, BOX));
+        assert!(!circle_overlaps_rect((-0.1, 0.0), 0.0, BOX));
+    }
+
+    #[test]
+    fn wrapped_copy_on_either_edge() {
+        assert_eq!(wrapped_copy((95.0, 0.0, 10.0, 10.0), 100.0), Some((-5.0, 0.0, 10.0, 10.0)));
+        assert_eq!(wrapped_copy((-5.0, 0.0, 10.0, 10.0), 100.0), Some((95.0, 0.0, 10.0, 10.0)));
+        assert_eq!(wrapped_copy((90.0, 0.0, 10.0, 10.0), 100.0), None);
+    }
+
+    #[test]
+    fn segment_against_rect() {
+        // 矩形を丸ごと突き抜ける
+        assert!(segment_intersects_rect((5.0, -50.0), (5.0, 50.0), BOX));
+        assert!(segment_intersects_rect((-5.0, -5.0), (15.0, 15.0), BOX));
+        // 横を通り過ぎる、手前で止まる
+        assert!(!segment_intersects_rect((12.0, -50.0), (12.0, 50.0), BOX));
+        assert!(!segment_intersects_rect((5.0, -50.0), (5.0, -1.0), BOX));
+        // 辺の上をなぞるだけでは交差しない
+        assert!(!segment_intersects_rect((0.0, -5.0), (0.0, 15.0), BOX));
+    }
+
+    #[test]
+    fn distance_to_segment_clamps_to_the_ends() {
+        assert_eq!(distance_to_segment((5.0, 3.0), (0.0, 0.0), (10.0, 0.0)), 3.0);
+        assert_eq!(distance_to_segment((13.0, 4.0), (0.0, 0.0), (10.0, 0.0)), 5.0);
+        // 長さ0の線分は点として扱う
+        assert_eq!(distance_to_segment((3.0, 4.0), (0.0, 0.0), (0.0, 0.0)), 5.0);
+    }
+
+    #[test]
+    fn fast_circles_do_not_tunnel_through() {
+        // 1ステップで矩形を飛び越える速さでも、通り道にあれば当たる
+        assert!(!circle_overlaps_rect((5.0, -20.0), 2.0, BOX));
+        assert!(!circle_overlaps_rect((5.0, 30.0), 2.0, BOX));
+        assert!(swept_circle_hits_rect((5.0, 30.0), (5.0, -20.0), 2.0, BOX));
+        // 通り道が半径の分だけ離れていれば当たらない
+        assert!(swept_circle_hits_rect((11.9, 30.0), (11.9, -20.0), 2.0, BOX));
+        assert!(!swept_circle_hits_rect((12.1, 30.0), (12.1, -20.0), 2.0, BOX));
+        // 角をかすめる斜めの軌跡は角からの距離で判定する
+        assert!(swept_circle_hits_rect((13.0, 6.0), (6.0, 13.0), 2.0, BOX));
+        assert!(!swept_circle_hits_rect((15.0, 8.0), (8.0, 15.0), 2.0, BOX));
+        // 動いていない円はその位置だけで判定する
+        assert!(swept_circle_hits_rect((11.0, 5.0), (11.0, 5.0), 2.0, BOX));
+    }
+
+    #[test]
+    fn entity_bounds_match_what_is_drawn() {
+        use crate::game::{Bullet, Enemy, EnemyKind, Player, Vec2};
+
+        let player = Player {
+            pos: Vec2::new(100.0, 200.0),
+            width: 50.0,
+            height: 50.0,
+            speed: 5.0,
+            hurtbox_size: (20.0, 30.0),
+        };
+        assert_eq!(player.bounds(), (100.0, 200.0, 50.0, 50.0));
+        // 被弾判定は画像の中央に置いた小さい矩形
+        assert_eq!(player.hurtbox(), (115.0, 210.0, 20.0, 30.0));
+
+        let enemy = Enemy::for_test(EnemyKind::Small, -10.0, 40.0);
+        assert_eq!(enemy.bounds(), (-10.0, 40.0, enemy.width, enemy.height));
+
+        // 弾は描く円に外接する正方形
+        let bullet = Bullet::new_player_shot(10.0, 20.0);
+        let (x, y, width, height) = bullet.bounds();
+        let center: (f64, f64) = bullet.center().into();
+        assert_eq!((x, y), (10.0, 20.0));
+        assert_eq!((width, height), (bullet.radius * 2.0, bullet.radius * 2.0));
+        assert_eq!(center, (x + width / 2.0, y + height / 2.0));
+    }
+}
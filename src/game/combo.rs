@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{GameClock, GameTimer};
+
+// 前の撃破からこの時間(ms)以内に倒すとコンボが続く
+const COMBO_WINDOW: f64 = 2000.0;
+// 倍率の上限
+const MAX_COMBO: u32 = 8;
+// 倍率が上がったときにHUDの表示を目立たせる時間(ms)
+const COMBO_FLASH_DURATION: f64 = 400.0;
+
+// 連続撃破によるスコアの倍率
+// 間を空けすぎる、弾を外す、被弾する、のいずれかで倍率は1に戻る
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Combo {
+    pub count: u32, // 現在の倍率(1〜MAX_COMBO)
+    pub best: u32, // このプレイで達した最高の倍率(倍率が戻っても残す)
+    window: GameTimer, // 次の撃破でコンボが続く残り時間
+    pub flash: GameTimer, // 倍率が上がった直後の強調表示
+}
+
+impl Combo {
+    pub fn new() -> Self {
+        Combo {
+            count: 1,
+            best: 1,
+            window: GameTimer::default(),
+            flash: GameTimer::default(),
+        }
+    }
+
+    // 撃破を数え、その撃破のスコアに掛ける倍率を返す
+    pub fn register_kill(&mut self, clock: &GameClock) -> u32 {
+        if self.window.is_running(clock) && self.count < MAX_COMBO {
+            self.count += 1;
+            self.flash.start(clock, COMBO_FLASH_DURATION);
+            self.best = self.best.max(self.count);
+        }
+        self.window.start(clock, COMBO_WINDOW);
+        self.count
+    }
+
+    // 時間切れになっていれば倍率を戻す
+    pub fn update(&mut self, clock: &GameClock) {
+        if !self.window.is_running(clock) {
+            self.reset();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.count = 1;
+        self.window.clear();
+        self.flash.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quick_kills_raise_the_multiplier_up_to_the_cap() {
+        let mut clock = GameClock::new();
+        let mut combo = Combo::new();
+        let multipliers: Vec<u32> = (0..10)
+            .map(|_| {
+                clock.advance(COMBO_WINDOW / 2.0);
+                combo.register_kill(&clock)
+            })
+            .collect();
+        assert_eq!(multipliers, vec![1, 2, 3, 4, 5, 6, 7, 8, 8, 8]);
+        assert_eq!(combo.best, MAX_COMBO);
+        // 上限に達した後は倍率が上がらないので強調表示もしない
+        assert!(!combo.flash.is_running(&clock));
+    }
+
+    #[test]
+    fn waiting_too_long_resets_but_keeps_the_best() {
+        let mut clock = GameClock::new();
+        let mut combo = Combo::new();
+        combo.register_kill(&clock);
+        combo.register_kill(&clock);
+        assert_eq!(combo.count, 2);
+
+        clock.advance(COMBO_WINDOW);
+        combo.update(&clock);
+        assert_eq!(combo.count, 1);
+        assert_eq!(combo.best, 2);
+        assert_eq!(combo.register_kill(&clock), 1);
+    }
+
+    #[test]
+    fn reset_breaks_the_chain_immediately() {
+        let clock = GameClock::new();
+        let mut combo = Combo::new();
+        combo.register_kill(&clock);
+        combo.register_kill(&clock);
+        combo.reset();
+        assert_eq!(combo.count, 1);
+        assert!(!combo.flash.is_running(&clock));
+        // 次の撃破は倍率1から数え直す
+        assert_eq!(combo.register_kill(&clock), 1);
+    }
+}
@@ -0,0 +1,110 @@
+use serde::Deserialize;
+
+use crate::game::bullet::PLAYER_BULLET_SPEED;
+
+// 被弾判定の大きさを指定しなかったときの、自機の大きさに対する幅と高さの割合(50pxの自機で30×38)
+const DEFAULT_HURTBOX_SCALE: (f64, f64) = (0.6, 0.76);
+
+// ページに埋め込むときに start_game_with_config で変えられる設定
+// 書かれていない項目は組み込みの値を使い、知らない項目は読み飛ばす
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    pub canvas_id: String, // 描画先のCanvas要素のid
+    pub player_size: f64, // 自機の大きさ(px)
+    pub player_speed: f64, // 自機の速さ(1ステップあたりのpx)
+    pub hurtbox_width: Option<f64>, // 自機の被弾判定の幅(px、未指定なら自機の大きさから決める)
+    pub hurtbox_height: Option<f64>, // 自機の被弾判定の高さ(px)
+    pub bullet_speed: f64, // 自機の弾の速さ(1ステップあたりのpx)
+    pub starting_lives: Option<u32>, // 始めのライフ(未指定なら難易度で決める)
+    pub spawn_interval: Option<f64>, // 最初の波で敵を出す間隔(ms、未指定なら調整値のまま)
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            canvas_id: "gameCanvas".to_string(),
+            player_size: 50.0,
+            player_speed: 5.0,
+            hurtbox_width: None,
+            hurtbox_height: None,
+            bullet_speed: PLAYER_BULLET_SPEED,
+            starting_lives: None,
+            spawn_interval: None,
+        }
+    }
+}
+
+impl GameConfig {
+    // 遊べない値が含まれていれば、どの項目がおかしいかを返す
+    pub fn validate(&self) -> Result<(), String> {
+        if self.canvas_id.is_empty() {
+            return Err("canvas_id must not be empty".to_string());
+        }
+        let positive = [
+            ("player_size", Some(self.player_size)),
+            ("player_speed", Some(self.player_speed)),
+            ("hurtbox_width", self.hurtbox_width),
+            ("hurtbox_height", self.hurtbox_height),
+            ("bullet_speed", Some(self.bullet_speed)),
+            ("spawn_interval", self.spawn_interval),
+        ];
+        for (name, value) in positive {
+            if let Some(value) = value.filter(|value| !(value.is_finite() && *value > 0.0)) {
+                return Err(format!("{} must be a positive number (got {})", name, value));
+            }
+        }
+        let (width, height) = self.hurtbox_size();
+        if width > self.player_size || height > self.player_size {
+            return Err("hurtbox must not be larger than player_size".to_string());
+        }
+        if self.starting_lives == Some(0) {
+            return Err("starting_lives must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    // 遊び方に関わる項目を組み込みの値から変えているか(描画先のidは含めない)
+    pub fn changes_gameplay(&self) -> bool {
+        *self
+            != GameConfig {
+                canvas_id: self.canvas_id.clone(),
+                ..GameConfig::default()
+            }
+    }
+
+    // 自機の被弾判定の幅と高さ
+    pub fn hurtbox_size(&self) -> (f64, f64) {
+        let (width_scale, height_scale) = DEFAULT_HURTBOX_SCALE;
+        (
+            self.hurtbox_width.unwrap_or(self.player_size * width_scale),
+            self.hurtbox_height.unwrap_or(self.player_size * height_scale),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_hurtbox_is_smaller_than_the_sprite() {
+        let config = GameConfig::default();
+        assert_eq!(config.hurtbox_size(), (30.0, 38.0));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_a_hurtbox_larger_than_the_player() {
+        let config = GameConfig {
+            hurtbox_width: Some(60.0),
+            ..GameConfig::default()
+        };
+        assert!(config.validate().is_err());
+        let config = GameConfig {
+            hurtbox_height: Some(0.0),
+            ..GameConfig::default()
+        };
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+// 破片が消えるまでの時間(ms)
+pub const DEBRIS_LIFETIME: f64 = 1500.0;
+// 同時に存在できる破片の上限
+pub const MAX_DEBRIS: usize = 60;
+
+// 撃破された敵が残す破片(見た目だけで当たり判定はない)
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Debris {
+    pub x: f64,
+    pub y: f64,
+    pub vx: f64,
+    pub vy: f64,
+    pub angle: f64, // 回転角(ラジアン)
+    pub spin: f64,  // 1フレームあたりの回転量
+    pub size: f64,
+    pub life: f64, // 残り時間(ms)
+}
+
+impl Debris {
+    // 不透明度(残り時間に合わせて薄くなる)
+    pub fn alpha(&self) -> f64 {
+        (self.life / DEBRIS_LIFETIME).clamp(0.0, 1.0)
+    }
+
+    // 回転を反映した四角形の頂点
+    pub fn corners(&self) -> [(f64, f64); 4] {
+        let half = self.size / 2.0;
+        let (sin, cos) = self.angle.sin_cos();
+        let rotate = |dx: f64, dy: f64| (self.x + dx * cos - dy * sin, self.y + dx * sin + dy * cos);
+        [
+            rotate(-half, -half),
+            rotate(half, -half * 0.6),
+            rotate(half * 0.8, half),
+            rotate(-half * 0.7, half * 0.8),
+        ]
+    }
+}
+
+// 破片の置き場。上限に達したら最も消えかけの破片を再利用する
+#[derive(Serialize, Deserialize)]
+pub struct DebrisPool {
+    pub pieces: Vec<Debris>,
+}
+
+impl DebrisPool {
+    pub fn new() -> Self {
+        DebrisPool {
+            pieces: Vec::with_capacity(MAX_DEBRIS),
+        }
+    }
+
+    pub fn spawn(&mut self, debris: Debris) {
+        if self.pieces.len() < MAX_DEBRIS {
+            self.pieces.push(debris);
+        } else if let Some(oldest) = self
+            .pieces
+            .iter_mut()
+            .min_by(|a, b| a.life.total_cmp(&b.life))
+        {
+            *oldest = debris;
+        }
+    }
+
+    // 破片を動かし、消えたものや画面外に出たものを取り除く
+    pub fn update(&mut self, delta_time: f64, step: f64, field_height: f64) {
+        for piece in &mut self.pieces {
+            piece.x += piece.vx * step;
+            piece.y += piece.vy * step;
+            piece.vy += 0.05 * step; // 少しずつ落下を速める
+            piece.angle += piece.spin * step;
+            piece.life -= delta_time;
+        }
+        self.pieces
+            .retain(|piece| piece.life > 0.0 && piece.y - piece.size <= field_height);
+    }
+
+    pub fn clear(&mut self) {
+        self.pieces.clear();
+    }
+}
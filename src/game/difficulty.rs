@@ -0,0 +1,109 @@
+use serde::Serialize;
+
+use crate::game::level::WaveDef;
+
+// 難易度。記録は難易度ごとに分けて保存し、送る結果にも含める
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Hardcore, // ライフ1つで始まり、敵が速く、出る間隔も早く縮む
+}
+
+// 難易度ごとの調整値
+struct Preset {
+    lives: u32, // 始めのライフ
+    speed_bonus: f64, // 敵の速さの範囲に足す量(1ステップあたりのpx)
+    interval_scale: f64, // 敵を出す間隔に掛ける倍率
+    ramp_factor: f64, // 波ごとに間隔が縮む速さの倍率
+    player_speed_scale: f64, // 自機の速さに掛ける倍率
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] =
+        [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard, Difficulty::Hardcore];
+
+    // 大文字と小文字は区別しない
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        let name = name.to_ascii_lowercase();
+        Difficulty::ALL.into_iter().find(|difficulty| difficulty.name() == name)
+    }
+
+    // start_game_with_difficulty で指定する名前(保存する記録のキーにも使う)
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+            Difficulty::Hardcore => "hardcore",
+        }
+    }
+
+    // HUDの表示
+    pub fn label(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "EASY",
+            Difficulty::Normal => "NORMAL",
+            Difficulty::Hard => "HARD",
+            Difficulty::Hardcore => "HARDCORE",
+        }
+    }
+
+    fn preset(self) -> Preset {
+        match self {
+            Difficulty::Easy => Preset {
+                lives: 5,
+                speed_bonus: -0.5,
+                interval_scale: 1.3,
+                ramp_factor: 1.0,
+                player_speed_scale: 1.2,
+            },
+            Difficulty::Normal => Preset {
+                lives: 3,
+                speed_bonus: 0.0,
+                interval_scale: 1.0,
+                ramp_factor: 1.0,
+                player_speed_scale: 1.0,
+            },
+            Difficulty::Hard => Preset {
+                lives: 2,
+                speed_bonus: 0.75,
+                interval_scale: 0.8,
+                ramp_factor: 1.5,
+                player_speed_scale: 1.0,
+            },
+            Difficulty::Hardcore => Preset {
+                lives: 1,
+                speed_bonus: 1.5,
+                interval_scale: 1.0,
+                ramp_factor: 2.0,
+                player_speed_scale: 1.0,
+            },
+        }
+    }
+
+    pub fn starting_lives(self) -> u32 {
+        self.preset().lives
+    }
+
+    pub fn player_speed_scale(self) -> f64 {
+        self.preset().player_speed_scale
+    }
+
+    // この難易度での波の作り方
+    // 間隔に掛ける倍率を ramp_factor 乗し、1波ごとにその分だけ早く縮める
+    pub fn waves(self, waves: &WaveDef) -> WaveDef {
+        let preset = self.preset();
+        WaveDef {
+            min_speed: waves.min_speed + preset.speed_bonus,
+            max_speed: waves.max_speed + preset.speed_bonus,
+            speed_cap: waves.speed_cap + preset.speed_bonus,
+            spawn_interval: waves.spawn_interval * preset.interval_scale,
+            interval_decay: waves.interval_decay.powf(preset.ramp_factor),
+            min_spawn_interval: waves.min_spawn_interval * preset.interval_scale,
+            ..*waves
+        }
+    }
+}
@@ -0,0 +1,1116 @@
+use crate::game::{
+    Blast, CritEffect, Difficulty, Enemy, EnemyKind, Game, GameMode, GameState, Orientation,
+    PendingSpawn, PowerUp, ScorePopup, Snapshot, Sprite, TextAlign, TextStyle, Vec2,
+    MAX_GAME_SPEED,
+};
+use crate::game::bomb_meter::BOMB_METER_FULL;
+use crate::game::collision::{Collidable, Rect};
+use crate::game::enemy::ENEMY_SIZE;
+use crate::game::game::{
+    BOMB_FLASH_DURATION, CHARGE_MAX_TIME, CHARGE_MIN_TIME, COUNTDOWN_LABELS, COUNTDOWN_STEP,
+    ESCAPE_FLASH_DURATION, ESCAPE_FLASH_HEIGHT, LASER_MIN_ENERGY, SHAKE_DURATION,
+    SHIELD_DURATION,
+};
+use crate::game::label::{format_clock, format_label, format_ratio};
+use crate::game::replay::REPLAY_SPEED;
+use crate::game::wave::BossIntroPhase;
+
+// 記録画面のグラフに並べるプレイの数
+const RECENT_RUNS_CHARTED: usize = 20;
+// 当たり判定を重ねるときの枠の色(敵や背景と見分けやすい色)
+const HITBOX_COLOR: &str = "magenta";
+// 制限時間の残りがこれ(ms)を切ったら表示の色を変える
+const TIME_WARNING: f64 = 10000.0;
+// 霧の中で見える自機の周りの範囲(px)と、その外側で暗さが増していく幅(px)
+const FOG_CLEAR_RADIUS: f64 = 110.0;
+const FOG_EDGE_WIDTH: f64 = 60.0;
+// 霧を重ねる段階の数と、重ねきったときの濃さ
+const FOG_STEPS: usize = 4;
+const FOG_ALPHA: f64 = 0.92;
+
+impl Game {
+    // 現在の状態を描画する
+    pub fn draw(&self) {
+        let renderer = &self.renderer;
+
+        let basis = self.basis();
+        let (screen_width, screen_height) = self.screen_size();
+
+        // 画面をクリア
+        renderer.clear(screen_width, screen_height);
+
+        // 被弾直後は画面全体を揺らす(表示類は揺らさない)
+        let (shake_x, shake_y) = self.shake_offset();
+        renderer.push_offset(shake_x, shake_y);
+
+        // 背景画像をスクロールさせて描画
+        self.draw_scrolling_layer(Sprite::Background, self.background_offset_y);
+        self.draw_scrolling_layer(Sprite::Starfield, self.starfield_offset_y);
+
+        // 対戦相手の自機を半透明で描画
+        if let Some((x, y)) = self.ghost.position_at(self.last_frame_time) {
+            renderer.set_alpha(0.35);
+            self.draw_sprite_in(Sprite::Player, (x, y, self.player.width, self.player.height));
+            renderer.set_alpha(1.0);
+        }
+
+        // プレイヤーを描画(画面端をまたいでいる場合は反対側にも描画)
+        let (player_rect, wrapped_rect) = self.player_rects();
+        for rect in std::iter::once(player_rect).chain(wrapped_rect) {
+            self.draw_sprite_in(Sprite::Player, rect);
+            if self.shield.is_running(&self.clock) {
+                self.draw_shield(rect);
+            }
+        }
+
+        // 溜めている間は自機の上に溜まり具合を出す。溜め撃ちになる長さに達したら色を変える
+        if let Some(progress) = self.charge_progress().filter(|_| !self.paused) {
+            let gauge = (self.player.pos.x, self.player.pos.y - 10.0, self.player.width, 4.0);
+            let color = if progress * CHARGE_MAX_TIME >= CHARGE_MIN_TIME {
+                "magenta"
+            } else {
+                "white"
+            };
+            self.fill_rect(basis.rect(gauge), "rgba(0, 0, 0, 0.6)");
+            self.fill_rect(basis.rect((gauge.0, gauge.1, gauge.2 * progress, gauge.3)), color);
+        }
+
+        // 弾丸を描画
+        for bullet in self.bullets.iter() {
+            // 跳ね返った弾は暗くして区別する
+            if bullet.bounced {
+                renderer.set_alpha(0.5);
+            }
+            let (x, y) = basis.point((bullet.pos.x + bullet.radius, bullet.pos.y + bullet.radius));
+            renderer.draw_circle(x, y, bullet.radius, bullet.color);
+            renderer.set_alpha(1.0);
+        }
+
+        // ミサイルを進む向きの三角形で描画
+        for missile in &self.missiles {
+            renderer.fill_polygon(&missile.corners().map(|corner| basis.point(corner)), "orange");
+        }
+
+        // 敵の弾を描画
+        for bullet in &self.enemy_bullets {
+            let (x, y) = basis.point((bullet.pos.x + bullet.radius, bullet.pos.y + bullet.radius));
+            renderer.draw_circle(x, y, bullet.radius, bullet.color);
+        }
+
+        // 破片を描画
+        for piece in &self.debris.pieces {
+            renderer.set_alpha(piece.alpha());
+            renderer.fill_polygon(&piece.corners().map(|corner| basis.point(corner)), "#333");
+        }
+        renderer.set_alpha(1.0);
+
+        // 火花を描画
+        for particle in &self.particles {
+            let (x, y) = basis.point((particle.x, particle.y));
+            renderer.set_alpha(particle.alpha());
+            renderer.draw_circle(x, y, 2.0, particle.color);
+        }
+        renderer.set_alpha(1.0);
+
+        // 敵を描画
+        for enemy in &self.enemies {
+            // 特攻する敵は赤い光をまとわせて見分けやすくし、突っ込み始めたら濃くする
+            if enemy.kind == EnemyKind::Kamikaze {
+                let center = enemy.pos + Vec2::new(enemy.width / 2.0, enemy.height / 2.0);
+                let (x, y) = basis.point(center.into());
+                renderer.set_alpha(if enemy.dive.is_some() { 0.6 } else { 0.3 });
+                renderer.draw_circle(x, y, enemy.width * 0.6, "red");
+                renderer.set_alpha(1.0);
+            }
+            // 被弾直後の敵は半透明にして点滅させる
+            if enemy.hit_flash > 0.0 {
+                renderer.set_alpha(0.4);
+            }
+            let sprite = if enemy.kind == EnemyKind::Boss {
+                Sprite::Boss
+            } else {
+                Sprite::Enemy(enemy.sprite)
+            };
+            self.draw_sprite_in(sprite, (enemy.pos.x, enemy.pos.y, enemy.width, enemy.height));
+            renderer.set_alpha(1.0);
+            if enemy.kind == EnemyKind::Boss {
+                self.draw_boss_health(enemy);
+            }
+        }
+
+        // レーザーを描画(半透明の太い光線と、明るい芯)
+        if self.laser_active() {
+            let (beam, wrapped) = self.laser_rects();
+            for (x, y, width, height) in std::iter::once(beam).chain(wrapped) {
+                renderer.set_alpha(0.4);
+                self.fill_rect(basis.rect((x, y, width, height)), "deepskyblue");
+                renderer.set_alpha(0.8);
+                let core = (x + width / 3.0, y, width / 3.0, height);
+                self.fill_rect(basis.rect(core), "white");
+            }
+            renderer.set_alpha(1.0);
+        }
+
+        // パワーアップを描画
+        for power_up in &self.power_ups {
+            self.draw_power_up(power_up);
+        }
+
+        // クリティカルヒット演出を描画
+        for effect in &self.crit_effects {
+            self.draw_crit_effect(effect);
+        }
+
+        // ミサイルの爆風を描画
+        for blast in &self.blasts {
+            self.draw_blast(blast);
+        }
+
+        // 獲得スコアを描画
+        for popup in &self.score_popups {
+            self.draw_score_popup(popup);
+        }
+
+        // 敵の出現予告マーカーを描画
+        for pending in &self.pending_spawns {
+            self.draw_spawn_marker(pending);
+        }
+
+        // 霧の掛かった波は、自機の周りだけを残して場を暗くする
+        if self.level.fog {
+            self.draw_fog();
+        }
+
+        // 調査用に当たり判定を重ねる
+        if self.debug_hitboxes {
+            self.draw_hitboxes();
+        }
+
+        renderer.pop_offset();
+
+        // 敵に抜けられた直後は画面下端を赤く光らせる
+        if self.escape_flash.is_running(&self.clock) {
+            let alpha = self.escape_flash.remaining(&self.clock) / ESCAPE_FLASH_DURATION;
+            let band = (0.0, self.height - ESCAPE_FLASH_HEIGHT, self.width, ESCAPE_FLASH_HEIGHT);
+            renderer.set_alpha(0.6 * alpha.min(1.0));
+            self.fill_rect(basis.rect(band), "red");
+            renderer.set_alpha(1.0);
+        }
+
+        // ボムを使った直後は画面全体を白く光らせる
+        if self.bomb_flash.is_running(&self.clock) {
+            let alpha = self.bomb_flash.remaining(&self.clock) / BOMB_FLASH_DURATION;
+            renderer.set_alpha(alpha.min(1.0));
+            self.fill_rect((0.0, 0.0, screen_width, screen_height), "white");
+            renderer.set_alpha(1.0);
+        }
+
+        // マウスの照準
+        if let Some(aim) = self.aim {
+            let (x, y) = basis.point(aim);
+            renderer.stroke_circle(x, y, 8.0, "white", 1.5);
+            renderer.draw_circle(x, y, 1.5, "white");
+        }
+
+        // スコアとライフは敵や弾に隠れないよう最後に描く
+        let mut buffer = self.label_buffer.borrow_mut();
+        let top = 10.0 + self.hud_font_size / 2.0;
+        renderer.draw_text(
+            format_label(&mut buffer, "SCORE ", self.score, ""),
+            10.0,
+            top,
+            self.hud_font_size,
+            TextAlign::Left,
+            &self.hud_style,
+        );
+        // コンボ中は倍率を出し、上がった直後は大きく色を変えて目立たせる
+        if self.combo.count > 1 {
+            let flashing = self.combo.flash.is_running(&self.clock);
+            let (size, style) = if flashing {
+                (self.hud_font_size * 1.4, self.hud_style.with_color("gold"))
+            } else {
+                (self.hud_font_size, self.hud_style)
+            };
+            renderer.draw_text(
+                format_label(&mut buffer, "x", self.combo.count, ""),
+                10.0,
+                top + self.hud_font_size * 1.4,
+                size,
+                TextAlign::Left,
+                &style,
+            );
+        }
+        // ライフが増えた直後は点滅させる
+        let lives_style = if (self.life_flash.remaining(&self.clock) / 100.0) as u32 % 2 == 1 {
+            self.hud_style.with_color("lime")
+        } else {
+            self.hud_style
+        };
+        // ライフの減らないモードでは数の代わりに無限を、練習モードでは被弾した回数を出す
+        let lives_label = match self.mode {
+            GameMode::Practice => format_label(&mut buffer, "HITS ", self.hits_taken, ""),
+            mode if mode.unlimited_lives() => "LIVES ∞",
+            _ => format_label(&mut buffer, "LIVES ", self.lives, ""),
+        };
+        renderer.draw_text(
+            lives_label,
+            screen_width - 10.0,
+            top,
+            self.hud_font_size,
+            TextAlign::Right,
+            &lives_style,
+        );
+        // ボムの数の左にメーターを出し、満タンになってボムが増えた直後は光らせる
+        let bombs_y = top + self.hud_font_size * 1.4;
+        let bomb_ready = self.bomb_meter.flash.is_running(&self.clock);
+        let bombs_style = if bomb_ready {
+            self.hud_style.with_color("gold")
+        } else {
+            self.hud_style
+        };
+        renderer.draw_text(
+            format_label(&mut buffer, "BOMBS ", self.bombs, ""),
+            screen_width - 10.0,
+            bombs_y,
+            self.hud_font_size * 0.8,
+            TextAlign::Right,
+            &bombs_style,
+        );
+        let meter = (screen_width - 150.0, bombs_y - 3.0, 50.0, 6.0);
+        let ratio = self.bomb_meter.percent as f64 / BOMB_METER_FULL as f64;
+        self.fill_rect(meter, "rgba(0, 0, 0, 0.6)");
+        self.fill_rect(
+            (meter.0, meter.1, meter.2 * ratio, meter.3),
+            if bomb_ready { "white" } else { "orange" },
+        );
+        // レーザーのエネルギー。照射を始められない量の間は赤くする
+        let meter = (screen_width - 90.0, top + self.hud_font_size * 2.3, 80.0, 6.0);
+        let color = if self.laser_energy < LASER_MIN_ENERGY && !self.laser_firing {
+            "red"
+        } else {
+            "deepskyblue"
+        };
+        self.fill_rect(meter, "rgba(0, 0, 0, 0.6)");
+        self.fill_rect((meter.0, meter.1, meter.2 * self.laser_energy, meter.3), color);
+        renderer.draw_text(
+            format_label(&mut buffer, "WEAPON Lv", self.weapon_level as u32, ""),
+            screen_width - 10.0,
+            top + self.hud_font_size * 3.2,
+            self.hud_font_size * 0.8,
+            TextAlign::Right,
+            &self.hud_style,
+        );
+        // ミサイルは再使用までの残り秒数を出す
+        let missile_label = if self.missile_cooldown.is_running(&self.clock) {
+            let seconds = (self.missile_cooldown.remaining(&self.clock) / 1000.0).ceil();
+            format_label(&mut buffer, "MISSILE ", seconds as u32, "s")
+        } else {
+            "MISSILE READY"
+        };
+        renderer.draw_text(
+            missile_label,
+            screen_width - 10.0,
+            top + self.hud_font_size * 4.2,
+            self.hud_font_size * 0.8,
+            TextAlign::Right,
+            &self.hud_style,
+        );
+
+        // 遊んだ時間を上端の中央に出す。制限時間のあるモードは残り時間を数え下げ、
+        // 残り少なくなったら色を変える
+        let (clock, clock_style) = match self.time_remaining() {
+            Some(remaining) => {
+                let style = if remaining <= TIME_WARNING {
+                    self.hud_style.with_color("orange")
+                } else {
+                    self.hud_style
+                };
+                ((remaining / 1000.0).ceil() * 1000.0, style)
+            }
+            None => (self.play_time, self.hud_style),
+        };
+        renderer.draw_text(
+            format_clock(clock, &mut buffer),
+            screen_width / 2.0,
+            top,
+            self.hud_font_size,
+            TextAlign::Center,
+            &clock_style,
+        );
+
+        // 通常以外の難易度で遊んでいることを時間の下に出す
+        let difficulty_color = match self.difficulty {
+            Difficulty::Normal => None,
+            Difficulty::Easy => Some("lime"),
+            Difficulty::Hard => Some("orange"),
+            Difficulty::Hardcore => Some("red"),
+        };
+        if let Some(color) = difficulty_color {
+            renderer.draw_text(
+                self.difficulty.label(),
+                screen_width / 2.0,
+                top + self.hud_font_size * 1.4,
+                self.hud_font_size * 0.8,
+                TextAlign::Center,
+                &self.hud_style.with_color(color),
+            );
+        }
+
+        // 波の合間は次の波の番号と、その波に掛かる変化を出す
+        let modifier = self.wave_modifier.as_ref().map(|active| active.modifier.entry());
+        let wave_break = self.wave_break.is_running(&self.clock);
+        if self.state == GameState::Playing && wave_break {
+            renderer.draw_text(
+                format_label(&mut buffer, "WAVE ", self.current_wave.number, ""),
+                screen_width / 2.0,
+                screen_height / 2.0 - 40.0,
+                40.0,
+                TextAlign::Center,
+                &TextStyle::BANNER,
+            );
+            if let Some(entry) = modifier {
+                renderer.draw_text(
+                    entry.name,
+                    screen_width / 2.0,
+                    screen_height / 2.0 + 5.0,
+                    24.0,
+                    TextAlign::Center,
+                    &TextStyle::BANNER.with_color(entry.color),
+                );
+            }
+        }
+
+        // 変化の掛かった波の間は、時間の下に変化の印を出す
+        if let (Some(entry), false) = (modifier, wave_break) {
+            let size = self.hud_font_size * 1.2;
+            let icon = (
+                (screen_width - size) / 2.0,
+                top + self.hud_font_size * 2.2,
+                size,
+                size,
+            );
+            renderer.set_alpha(0.6);
+            self.fill_rect(icon, "black");
+            renderer.set_alpha(1.0);
+            self.stroke_rect(icon, entry.color);
+            renderer.draw_text(
+                entry.icon,
+                screen_width / 2.0,
+                icon.1 + size / 2.0,
+                self.hud_font_size,
+                TextAlign::Center,
+                &self.hud_style.with_color(entry.color),
+            );
+        }
+
+        // ボスの登場前は「WARNING」を横から滑り込ませ、赤い帯の上で点滅させる
+        let warning = self.boss_intro.filter(|intro| intro.phase == BossIntroPhase::Warning);
+        if let (GameState::Playing, Some(intro)) = (self.state, warning) {
+            let center_y = screen_height / 2.0 - 40.0;
+            let (x, alpha) = if self.reduced_motion {
+                (screen_width / 2.0, 1.0)
+            } else {
+                // 最初の2割で右端の外から中央へ減速しながら入ってくる
+                let slide = (intro.progress(&self.clock) / 0.2).min(1.0);
+                let x = screen_width / 2.0 + screen_width * (1.0 - slide).powi(2);
+                let blink = ((intro.timer.remaining(&self.clock) / 250.0) as u32).is_multiple_of(2);
+                (x, if blink { 1.0 } else { 0.35 })
+            };
+            renderer.set_alpha(0.5 * alpha);
+            self.fill_rect((0.0, center_y - 35.0, screen_width, 70.0), "darkred");
+            renderer.set_alpha(alpha);
+            renderer.draw_text(
+                "WARNING",
+                x,
+                center_y,
+                48.0,
+                TextAlign::Center,
+                &TextStyle::BANNER.with_color("red"),
+            );
+            renderer.set_alpha(1.0);
+        }
+
+        // アシスト速度で遊んでいる場合は表示する
+        let y = screen_height - 15.0;
+        if self.assist_speed < MAX_GAME_SPEED {
+            let label = format_ratio(&mut buffer, "ASSIST x", self.assist_speed);
+            renderer.draw_text(label, 10.0, y, 14.0, TextAlign::Left, &TextStyle::PLAIN);
+        }
+
+        // ランキング対象外になったプレイは印を付ける
+        if !self.run_flags.is_ranked() {
+            let style = TextStyle::PLAIN.with_color("orange");
+            renderer.draw_text("UNRANKED", screen_width - 10.0, y, 14.0, TextAlign::Right, &style);
+        }
+    }
+
+    // 背景を2枚並べて描画し、継ぎ目なく流れているように見せる
+    // offset は場の前後方向のずれで、画面の向きに合わせてスクロール方向を変える
+    fn draw_scrolling_layer(&self, sprite: Sprite, offset: f64) {
+        if !self.renderer.has_sprite(sprite) {
+            // 読み込めなかった背景は1色で塗る。重ねる星は描かない
+            if sprite == Sprite::Background {
+                self.draw_background();
+            }
+            return;
+        }
+        let (screen_width, screen_height) = self.screen_size();
+        let positions = match self.orientation {
+            Orientation::BottomUp => [(0.0, offset), (0.0, offset - screen_height)],
+            Orientation::LeftToRight => [(-offset, 0.0), (screen_width - offset, 0.0)],
+        };
+        for (x, y) in positions {
+            self.renderer.draw_sprite(sprite, x, y);
+        }
+    }
+
+    // 自機を中心に FOG_CLEAR_RADIUS の範囲だけを残し、その外側を暗く塗る
+    // 境目を少しぼかすため、外側ほど濃くなるように段階を分けて重ねる
+    fn draw_fog(&self) {
+        let basis = self.basis();
+        let center = self.player.center();
+        let (width, height) = (self.width, self.height);
+        // 全段階が重なった外側で FOG_ALPHA の濃さになる、1段階あたりの濃さ
+        let alpha = 1.0 - (1.0 - FOG_ALPHA).powf(1.0 / FOG_STEPS as f64);
+        self.renderer.set_alpha(alpha);
+        for step in 0..FOG_STEPS {
+            let radius = FOG_CLEAR_RADIUS + step as f64 * FOG_EDGE_WIDTH / FOG_STEPS as f64;
+            let (left, top) = (center.x - radius, center.y - radius);
+            let (right, bottom) = (center.x + radius, center.y + radius);
+            // 残す範囲の上下と左右を塗る(場の外にはみ出した分は描かれない)
+            let bands = [
+                (0.0, 0.0, width, top),
+                (0.0, bottom, width, height - bottom),
+                (0.0, top, left, bottom - top),
+                (right, top, width - right, bottom - top),
+            ];
+            for band in bands.into_iter().filter(|band| band.2 > 0.0 && band.3 > 0.0) {
+                self.fill_rect(basis.rect(band), "black");
+            }
+        }
+        self.renderer.set_alpha(1.0);
+    }
+
+    // 画面の揺れによるずれ。残り時間に合わせて小さくなる
+    // 描画のたびに乱数を引くとゲームの再現性が崩れるので、時刻から揺れを作る
+    fn shake_offset(&self) -> (f64, f64) {
+        let remaining = self.shake.remaining(&self.clock);
+        if remaining <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let magnitude = self.shake_magnitude * remaining / SHAKE_DURATION;
+        let t = self.clock.now();
+        (magnitude * (t * 0.37).sin(), magnitude * (t * 0.53).cos())
+    }
+
+    // ゲーム内の矩形に収まるように画像を描画する
+    // 画像を読み込めなかったときは、画像の代わりにその矩形を塗る
+    fn draw_sprite_in(&self, sprite: Sprite, rect: Rect) {
+        let rect = self.basis().rect(rect);
+        if self.renderer.has_sprite(sprite) {
+            self.renderer.draw_sprite(sprite, rect.0, rect.1);
+        } else {
+            self.fill_rect(rect, sprite.placeholder_color());
+        }
+    }
+
+    // 背景を画面の左上に合わせて描画する。画像を読み込めなかったときは画面全体を塗る
+    fn draw_background(&self) {
+        if self.renderer.has_sprite(Sprite::Background) {
+            self.renderer.draw_sprite(Sprite::Background, 0.0, 0.0);
+        } else {
+            let (screen_width, screen_height) = self.screen_size();
+            self.fill_rect(
+                (0.0, 0.0, screen_width, screen_height),
+                Sprite::Background.placeholder_color(),
+            );
+        }
+    }
+
+    // タイトル画面を描画する
+    pub fn draw_menu(&self) {
+        let renderer = &self.renderer;
+        let (screen_width, screen_height) = self.screen_size();
+        renderer.clear(screen_width, screen_height);
+        self.draw_background();
+        renderer.draw_text(
+            "ALARM SHOOTER",
+            screen_width / 2.0,
+            screen_height / 2.0 - 50.0,
+            48.0,
+            TextAlign::Center,
+            &TextStyle::BANNER,
+        );
+        // 遊び方は左右キーで選ぶ
+        let mut buffer = self.label_buffer.borrow_mut();
+        buffer.clear();
+        buffer.push_str("< ");
+        buffer.push_str(self.mode.label());
+        buffer.push_str(" >");
+        renderer.draw_text(
+            &buffer,
+            screen_width / 2.0,
+            screen_height / 2.0 - 5.0,
+            20.0,
+            TextAlign::Center,
+            &TextStyle::PLAIN.with_color("gold"),
+        );
+        renderer.draw_text(
+            "Press Enter or click to start",
+            screen_width / 2.0,
+            screen_height / 2.0 + 30.0,
+            24.0,
+            TextAlign::Center,
+            &TextStyle::PLAIN,
+        );
+        renderer.draw_text(
+            "Press S for stats",
+            screen_width / 2.0,
+            screen_height / 2.0 + 110.0,
+            16.0,
+            TextAlign::Center,
+            &TextStyle::PLAIN,
+        );
+
+        // 選んでいるモードと難易度での最高スコア
+        let best_score = self.history.lifetime().best_score;
+        if best_score > 0 {
+            renderer.draw_text(
+                format_label(&mut buffer, "HIGH SCORE ", best_score, ""),
+                screen_width / 2.0,
+                screen_height / 2.0 - 120.0,
+                20.0,
+                TextAlign::Center,
+                &TextStyle::HUD,
+            );
+        }
+
+        // 操作の説明。キーの割り当てを変えていれば変えた後のキーを出す
+        for (i, hint) in self.key_hints.iter().enumerate() {
+            renderer.draw_text(
+                hint,
+                screen_width / 2.0,
+                screen_height - 50.0 + i as f64 * 20.0,
+                14.0,
+                TextAlign::Center,
+                &TextStyle::PLAIN,
+            );
+        }
+
+        drop(buffer);
+        self.draw_undo_reset_prompt(screen_height / 2.0 + 70.0);
+    }
+
+    // リセット直後は取り消しを受け付けていることを表示する
+    fn draw_undo_reset_prompt(&self, y: f64) {
+        let Some(remaining) = self.undo_reset_remaining() else {
+            return;
+        };
+        let (screen_width, _) = self.screen_size();
+        let seconds = (remaining / 1000.0).ceil() as u32;
+        let mut buffer = self.label_buffer.borrow_mut();
+        self.renderer.draw_text(
+            format_label(&mut buffer, "Undo reset? Press U (", seconds, "s)"),
+            screen_width / 2.0,
+            y,
+            18.0,
+            TextAlign::Center,
+            &TextStyle::PLAIN.with_color("orange"),
+        );
+    }
+
+    // これまでのプレイの合計と、直近のスコアのグラフを描画する
+    pub fn draw_stats(&self) {
+        let renderer = &self.renderer;
+        let (screen_width, screen_height) = self.screen_size();
+        renderer.clear(screen_width, screen_height);
+        self.draw_background();
+        renderer.draw_text(
+            "STATS",
+            screen_width / 2.0,
+            50.0,
+            40.0,
+            TextAlign::Center,
+            &TextStyle::BANNER,
+        );
+        renderer.draw_text(
+            "Press Enter to return",
+            screen_width / 2.0,
+            screen_height - 25.0,
+            16.0,
+            TextAlign::Center,
+            &TextStyle::PLAIN,
+        );
+
+        if self.history.runs.is_empty() {
+            renderer.draw_text(
+                "No runs yet",
+                screen_width / 2.0,
+                screen_height / 2.0,
+                24.0,
+                TextAlign::Center,
+                &TextStyle::PLAIN,
+            );
+            return;
+        }
+
+        let lifetime = self.history.lifetime();
+        let accuracy = match lifetime.accuracy() {
+            Some(accuracy) => format!("{:.0}%", accuracy * 100.0),
+            None => "-".to_string(),
+        };
+        let lines = [
+            ("Runs", lifetime.runs.to_string()),
+            ("Best score", lifetime.best_score.to_string()),
+            ("Total kills", lifetime.total_kills.to_string()),
+            ("Playtime", format_playtime(lifetime.total_playtime_ms)),
+            ("Accuracy", accuracy),
+        ];
+        for (i, (label, value)) in lines.iter().enumerate() {
+            let y = 100.0 + i as f64 * 26.0;
+            renderer.draw_text(
+                label,
+                screen_width / 2.0 - 10.0,
+                y,
+                18.0,
+                TextAlign::Right,
+                &TextStyle::PLAIN,
+            );
+            renderer.draw_text(
+                value,
+                screen_width / 2.0 + 10.0,
+                y,
+                18.0,
+                TextAlign::Left,
+                &TextStyle::PLAIN.bold(),
+            );
+        }
+
+        let recent: Vec<u32> = self
+            .history
+            .recent(RECENT_RUNS_CHARTED)
+            .iter()
+            .map(|run| run.score)
+            .collect();
+        let chart_top = 100.0 + lines.len() as f64 * 26.0 + 30.0;
+        let chart = (60.0, chart_top, screen_width - 100.0, screen_height - chart_top - 70.0);
+        self.draw_bar_chart(&recent, chart);
+        renderer.draw_text(
+            &format!("Last {} scores", recent.len()),
+            screen_width / 2.0,
+            chart_top + chart.3 + 22.0,
+            14.0,
+            TextAlign::Center,
+            &TextStyle::PLAIN,
+        );
+    }
+
+    // 棒グラフを area (x, y, 幅, 高さ) に描画する。縦軸の上端は最大値に合わせる
+    fn draw_bar_chart(&self, values: &[u32], (x, y, width, height): Rect) {
+        let renderer = &self.renderer;
+        let bottom = y + height;
+
+        // 軸
+        self.fill_rect((x, y, 2.0, height), "white");
+        self.fill_rect((x, bottom, width, 2.0), "white");
+
+        let max = values.iter().copied().max().unwrap_or(0).max(1);
+        renderer.draw_text(
+            &max.to_string(),
+            x - 6.0,
+            y,
+            12.0,
+            TextAlign::Right,
+            &TextStyle::PLAIN,
+        );
+        renderer.draw_text("0", x - 6.0, bottom, 12.0, TextAlign::Right, &TextStyle::PLAIN);
+
+        // 棒の数が少なくても幅が広がりすぎないよう、枠は常に最大本数分に分ける
+        let slot = width / RECENT_RUNS_CHARTED as f64;
+        for (i, &value) in values.iter().enumerate() {
+            let bar_height = height * value as f64 / max as f64;
+            self.fill_rect(
+                (
+                    x + 4.0 + slot * i as f64,
+                    bottom - bar_height,
+                    slot - 4.0,
+                    bar_height,
+                ),
+                "orange",
+            );
+        }
+    }
+
+    // 画面座標の矩形の枠を描く
+    fn stroke_rect(&self, (x, y, width, height): Rect, color: &str) {
+        self.renderer.stroke_polygon(
+            &[(x, y), (x + width, y), (x + width, y + height), (x, y + height)],
+            color,
+            1.0,
+        );
+    }
+
+    // 当たり判定に使っている形を枠で描く。弾は判定に使う円だけを出す
+    fn draw_hitboxes(&self) {
+        let basis = self.basis();
+        // 自機は画像の範囲を白で、被弾判定をその内側に出す
+        let (player_rect, wrapped_rect) = self.player_rects();
+        for rect in std::iter::once(player_rect).chain(wrapped_rect) {
+            self.stroke_rect(basis.rect(rect), "white");
+        }
+        let (hurtbox, wrapped_hurtbox) = self.player_hurtboxes();
+        for rect in std::iter::once(hurtbox).chain(wrapped_hurtbox) {
+            self.stroke_rect(basis.rect(rect), HITBOX_COLOR);
+        }
+        for enemy in &self.enemies {
+            self.stroke_rect(basis.rect(enemy.bounds()), HITBOX_COLOR);
+            if let Some(weak_point) = enemy.weak_point() {
+                self.stroke_rect(basis.rect(weak_point), "yellow");
+            }
+        }
+        for bullet in self.bullets.iter().chain(&self.enemy_bullets) {
+            let (x, y) = basis.point(bullet.center().into());
+            self.renderer.stroke_circle(x, y, bullet.radius, HITBOX_COLOR, 1.0);
+        }
+    }
+
+    // 画面座標の矩形を塗りつぶす
+    fn fill_rect(&self, (x, y, width, height): Rect, color: &str) {
+        self.renderer.fill_polygon(
+            &[(x, y), (x + width, y), (x + width, y + height), (x, y + height)],
+            color,
+        );
+    }
+
+    // プレイ画面の上にレベルクリアの表示を重ねる
+    pub fn draw_level_complete(&self) {
+        let (screen_width, screen_height) = self.screen_size();
+        self.renderer.draw_text(
+            "LEVEL COMPLETE",
+            screen_width / 2.0,
+            screen_height / 2.0 - 40.0,
+            40.0,
+            TextAlign::Center,
+            &TextStyle::BANNER,
+        );
+        self.renderer.draw_text(
+            "Press Enter to continue",
+            screen_width / 2.0,
+            screen_height / 2.0 + 20.0,
+            20.0,
+            TextAlign::Center,
+            &TextStyle::PLAIN,
+        );
+    }
+
+    // ゲームオーバーの表示
+    pub fn draw_game_over(&self) {
+        let (screen_width, screen_height) = self.screen_size();
+        let (center_x, center_y) = (screen_width / 2.0, screen_height / 2.0);
+        // 結果の文字が場の上でも読めるよう、暗い板を敷く
+        self.fill_rect((center_x - 190.0, center_y - 135.0, 380.0, 290.0), "rgba(0, 0, 0, 0.6)");
+        // 時間切れで終わったときは「TIME UP」と出す
+        let title = if self.mode.time_limit().is_some() { "TIME UP" } else { "GAME OVER" };
+        self.renderer.draw_text(
+            title,
+            center_x,
+            center_y - 85.0,
+            48.0,
+            TextAlign::Center,
+            &TextStyle::BANNER,
+        );
+
+        // このプレイの結果
+        if let (Some(run), Some(result)) = (self.finished_run(), self.last_run_result()) {
+            if result.new_best {
+                self.renderer.draw_text(
+                    "NEW BEST!",
+                    center_x,
+                    center_y - 52.0,
+                    18.0,
+                    TextAlign::Center,
+                    &TextStyle::HUD.with_color("gold"),
+                );
+            }
+            let accuracy = result
+                .accuracy
+                .map_or("-".to_string(), |accuracy| format!("{:.1}%", accuracy * 100.0));
+            let lines = [
+                format!("Score  {}", result.score),
+                format!("High score  {}", result.high_score),
+                format!("Best combo  x{}", result.best_combo),
+                format!("Accuracy  {} ({}/{})", accuracy, run.shots_hit, run.shots_fired),
+                format!("Destroyed  {}   Escaped  {}", result.kills, run.enemies_escaped),
+                format!(
+                    "Time survived  {}   Bonus  +{}",
+                    format_playtime(result.duration_ms),
+                    self.survival_bonus
+                ),
+            ];
+            for (i, line) in lines.iter().enumerate() {
+                self.renderer.draw_text(
+                    line,
+                    center_x,
+                    center_y - 20.0 + i as f64 * 24.0,
+                    18.0,
+                    TextAlign::Center,
+                    &TextStyle::HUD,
+                );
+            }
+        }
+
+        self.renderer.draw_text(
+            "Press R to restart, Enter for title",
+            center_x,
+            center_y + 135.0,
+            20.0,
+            TextAlign::Center,
+            &TextStyle::PLAIN,
+        );
+    }
+
+    // 調査用の表示(フレームレート、最長のフレーム時間、物体の数)
+    pub fn draw_debug_overlay(&self) {
+        let (_, screen_height) = self.screen_size();
+        let lines = [
+            format!(
+                "FPS {:.0}  max {:.1}ms",
+                self.frame_stats.average_fps(),
+                self.frame_stats.max_frame_time()
+            ),
+            format!(
+                "bullets {}  enemy bullets {}",
+                self.bullets.iter().count(),
+                self.enemy_bullets.len()
+            ),
+            format!("enemies {}  pending {}", self.enemies.len(), self.pending_spawns.len()),
+        ];
+        let style = TextStyle::HUD.with_color("lime");
+        for (i, line) in lines.iter().enumerate() {
+            let y = screen_height - 75.0 + i as f64 * 18.0;
+            self.renderer.draw_text(line, 10.0, y, 14.0, TextAlign::Left, &style);
+        }
+    }
+
+    // 始める前のカウントダウン。残り時間から今出す数字を選ぶ
+    pub fn draw_countdown(&self) {
+        let (screen_width, screen_height) = self.screen_size();
+        let total = COUNTDOWN_STEP * COUNTDOWN_LABELS.len() as f64;
+        let index = ((total - self.countdown) / COUNTDOWN_STEP) as usize;
+        let label = COUNTDOWN_LABELS[index.min(COUNTDOWN_LABELS.len() - 1)];
+        self.renderer.draw_text(
+            label,
+            screen_width / 2.0,
+            screen_height / 2.0,
+            72.0,
+            TextAlign::Center,
+            &TextStyle::BANNER,
+        );
+        self.draw_undo_reset_prompt(screen_height / 2.0 + 70.0);
+    }
+
+    // 一時停止中の表示
+    pub fn draw_paused(&self) {
+        let (screen_width, screen_height) = self.screen_size();
+        self.renderer.draw_text(
+            "PAUSED",
+            screen_width / 2.0,
+            screen_height / 2.0,
+            40.0,
+            TextAlign::Center,
+            &TextStyle::BANNER,
+        );
+    }
+
+    pub fn render_spectate(&mut self, delta_time: f64) {
+        // 記録をスローモーションで再生する
+        // 再生中に動きを抑える設定にされたら、そこで再生をやめる
+        self.spectate_time += delta_time * REPLAY_SPEED;
+        if self.spectate_time > self.replay.end_time() || !self.replays_final_moments() {
+            self.finish_spectate();
+            return;
+        }
+
+        let (screen_width, screen_height) = self.screen_size();
+        self.renderer.clear(screen_width, screen_height);
+        if let Some(snapshot) = self.replay.snapshot_at(self.spectate_time) {
+            self.draw_snapshot(snapshot);
+        }
+
+        self.renderer.draw_text(
+            "FINAL MOMENTS",
+            screen_width / 2.0,
+            50.0,
+            32.0,
+            TextAlign::Center,
+            &TextStyle::BANNER,
+        );
+    }
+
+    fn draw_snapshot(&self, snapshot: &Snapshot) {
+        let renderer = &self.renderer;
+        self.draw_background();
+        self.draw_sprite_in(
+            Sprite::Player,
+            (snapshot.player_x, snapshot.player_y, self.player.width, self.player.height),
+        );
+
+        let basis = self.basis();
+        for &(x, y, radius) in &snapshot.bullets {
+            let (x, y) = basis.point((x + radius, y + radius));
+            renderer.draw_circle(x, y, radius, "red");
+        }
+        for &(x, y, radius) in &snapshot.enemy_bullets {
+            let (x, y) = basis.point((x + radius, y + radius));
+            renderer.draw_circle(x, y, radius, "yellow");
+        }
+
+        for &(x, y, flashing, sprite) in &snapshot.enemies {
+            if flashing {
+                renderer.set_alpha(0.4);
+            }
+            self.draw_sprite_in(Sprite::Enemy(sprite), (x, y, ENEMY_SIZE, ENEMY_SIZE));
+            renderer.set_alpha(1.0);
+        }
+    }
+
+    fn draw_power_up(&self, power_up: &PowerUp) {
+        let (center_x, center_y) = self.basis().point((
+            power_up.x + power_up.width / 2.0,
+            power_up.y + power_up.height / 2.0,
+        ));
+        self.renderer.draw_circle(
+            center_x,
+            center_y,
+            power_up.width / 2.0,
+            power_up.kind.color(),
+        );
+        self.renderer.draw_text(
+            power_up.kind.label(),
+            center_x,
+            center_y,
+            14.0,
+            TextAlign::Center,
+            &TextStyle::PLAIN.with_color("black").bold(),
+        );
+    }
+
+    // 自機を囲む半透明の円と、残り時間に合わせて短くなる円弧
+    fn draw_shield(&self, (x, y, width, height): Rect) {
+        let (center_x, center_y) = self.basis().point((x + width / 2.0, y + height / 2.0));
+        let radius = width.max(height) * 0.7;
+        self.renderer.set_alpha(0.25);
+        self.renderer.draw_circle(center_x, center_y, radius, "cyan");
+        self.renderer.set_alpha(1.0);
+
+        let ratio = self.shield.remaining(&self.clock) / SHIELD_DURATION;
+        let start = -std::f64::consts::FRAC_PI_2;
+        self.renderer.stroke_arc(
+            center_x,
+            center_y,
+            radius,
+            (start, start + ratio * std::f64::consts::TAU),
+            "cyan",
+            2.0,
+        );
+    }
+
+    fn draw_crit_effect(&self, effect: &CritEffect) {
+        let progress = effect.progress();
+        self.renderer.set_alpha(1.0 - progress);
+        let (x, y) = self.basis().point((effect.x, effect.y));
+        self.renderer
+            .stroke_circle(x, y, 6.0 + progress * 24.0, "cyan", 3.0);
+        self.renderer.set_alpha(1.0);
+    }
+
+    // 爆発した範囲いっぱいまで広がりながら薄れる円
+    fn draw_blast(&self, blast: &Blast) {
+        let progress = blast.progress();
+        let (x, y) = self.basis().point((blast.x, blast.y));
+        let radius = blast.radius * progress.sqrt();
+        self.renderer.set_alpha(0.3 * (1.0 - progress));
+        self.renderer.draw_circle(x, y, radius, "orange");
+        self.renderer.set_alpha(1.0 - progress);
+        self.renderer.stroke_circle(x, y, radius, "orange", 3.0);
+        self.renderer.set_alpha(1.0);
+    }
+
+    fn draw_score_popup(&self, popup: &ScorePopup) {
+        let (x, y) = self.basis().point(popup.position());
+        let style = TextStyle::HUD.with_color("gold");
+        self.renderer.set_alpha(1.0 - popup.progress());
+        let mut buffer = self.label_buffer.borrow_mut();
+        let label = format_label(&mut buffer, "+", popup.value, "");
+        self.renderer.draw_text(label, x, y, 16.0, TextAlign::Center, &style);
+        self.renderer.set_alpha(1.0);
+    }
+
+    // ボスの上に残り体力のバーを描く
+    fn draw_boss_health(&self, boss: &Enemy) {
+        let basis = self.basis();
+        let bar = (boss.pos.x, boss.pos.y - 12.0, boss.width, 6.0);
+        let ratio = boss.hp as f64 / boss.max_hp.max(1) as f64;
+        self.fill_rect(basis.rect(bar), "rgba(0, 0, 0, 0.6)");
+        self.fill_rect(basis.rect((bar.0, bar.1, bar.2 * ratio, bar.3)), "red");
+    }
+
+    fn draw_spawn_marker(&self, pending: &PendingSpawn) {
+        // 残り時間に合わせて点滅させる
+        let alpha = 0.55 + 0.45 * (pending.remaining / 80.0).sin();
+        let x = pending.marker_x();
+
+        self.renderer.set_alpha(alpha);
+        let points = [(x - 10.0, 4.0), (x + 10.0, 4.0), (x, 16.0)];
+        let basis = self.basis();
+        self.renderer
+            .fill_polygon(&points.map(|point| basis.point(point)), "orange");
+        self.renderer.set_alpha(1.0);
+    }
+}
+
+// プレイ時間を "1h 02m 03s" や "2m 03s" の形にする
+fn format_playtime(ms: f64) -> String {
+    let seconds = (ms / 1000.0) as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else {
+        format!("{}m {:02}s", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::render::mock::recorded_game;
+    use crate::game::InputAction;
+
+    #[test]
+    fn unranked_runs_are_marked_on_the_field() {
+        let (game, renderer, _) = recorded_game(1);
+        let mut game = game.borrow_mut();
+        game.begin_run();
+        game.draw();
+        assert!(!renderer.texts().iter().any(|text| text == "UNRANKED"));
+
+        renderer.clear_calls();
+        game.set_wrap_horizontal(true);
+        game.draw();
+        assert!(renderer.texts().iter().any(|text| text == "UNRANKED"));
+    }
+
+    #[test]
+    fn the_menu_shows_rebound_keys_and_the_undo_countdown() {
+        let (game, renderer, _) = recorded_game(1);
+        let mut game = game.borrow_mut();
+        game.rebind_key(InputAction::Bomb, "q");
+        game.begin_run();
+        game.reset(1000.0);
+        game.last_frame_time = 1000.0;
+        game.draw_menu();
+        let texts = renderer.texts();
+        assert!(texts.iter().any(|text| text.starts_with("Bomb q ")), "{:?}", texts);
+        assert!(texts.contains(&"Undo reset? Press U (10s)".to_string()));
+        assert!(texts.contains(&"< ENDLESS >".to_string()), "{:?}", texts);
+    }
+}
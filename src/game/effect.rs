@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+// クリティカルヒット演出の表示時間(ms)
+pub const CRIT_EFFECT_DURATION: f64 = 300.0;
+// 獲得スコアの表示時間(ms)と、その間に浮き上がる距離(px)
+const SCORE_POPUP_DURATION: f64 = 700.0;
+const SCORE_POPUP_RISE: f64 = 24.0;
+// ミサイルの爆風の表示時間(ms)
+const BLAST_DURATION: f64 = 250.0;
+
+// 弱点に命中したときに表示する広がるリング
+#[derive(Serialize, Deserialize)]
+pub struct CritEffect {
+    pub x: f64,
+    pub y: f64,
+    pub remaining: f64, // 残り表示時間(ms)
+}
+
+impl CritEffect {
+    pub fn new(x: f64, y: f64) -> Self {
+        CritEffect {
+            x,
+            y,
+            remaining: CRIT_EFFECT_DURATION,
+        }
+    }
+
+    // 経過割合(0.0〜1.0)
+    pub fn progress(&self) -> f64 {
+        1.0 - self.remaining / CRIT_EFFECT_DURATION
+    }
+}
+
+// 敵を倒した位置に浮かべる獲得スコア
+#[derive(Serialize, Deserialize)]
+pub struct ScorePopup {
+    pub x: f64,
+    pub y: f64,
+    pub value: u32,
+    pub remaining: f64, // 残り表示時間(ms)
+}
+
+impl ScorePopup {
+    pub fn new(x: f64, y: f64, value: u32) -> Self {
+        ScorePopup {
+            x,
+            y,
+            value,
+            remaining: SCORE_POPUP_DURATION,
+        }
+    }
+
+    // 経過割合(0.0〜1.0)
+    pub fn progress(&self) -> f64 {
+        1.0 - self.remaining / SCORE_POPUP_DURATION
+    }
+
+    // 現在の表示位置(時間とともに上へ浮かぶ)
+    pub fn position(&self) -> (f64, f64) {
+        (self.x, self.y - self.progress() * SCORE_POPUP_RISE)
+    }
+}
+
+// ミサイルが爆発した範囲に表示する広がる円
+#[derive(Serialize, Deserialize)]
+pub struct Blast {
+    pub x: f64,
+    pub y: f64,
+    pub radius: f64, // 広がりきったときの半径
+    pub remaining: f64, // 残り表示時間(ms)
+}
+
+impl Blast {
+    pub fn new(x: f64, y: f64, radius: f64) -> Self {
+        Blast {
+            x,
+            y,
+            radius,
+            remaining: BLAST_DURATION,
+        }
+    }
+
+    // 経過割合(0.0〜1.0)
+    pub fn progress(&self) -> f64 {
+        1.0 - self.remaining / BLAST_DURATION
+    }
+}
@@ -1,15 +1,316 @@
-use web_sys::HtmlImageElement;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+use crate::game::collision::{Collidable, Rect};
+use crate::game::{EnemyKind, PatternRunner, Vec2};
+
+// 敵の大きさ
+pub const ENEMY_SIZE: f64 = 50.0;
+// 被弾時に点滅させる時間(ms)
+pub const HIT_FLASH_DURATION: f64 = 150.0;
+// 敵が弾を撃つ間隔(ms)
+pub const ENEMY_FIRE_INTERVAL: f64 = 1500.0;
+// 弱点に命中したときのダメージ倍率
+pub const WEAK_POINT_MULTIPLIER: u32 = 3;
+// この速さ以上の敵は、速さに応じて撃破時のスコアを上乗せする
+pub const FAST_ENEMY_SPEED: f64 = 3.0;
+// 上乗せを1点増やす速さの幅
+const SPEED_BONUS_STEP: f64 = 2.0;
+// ボスの大きさ
+pub const BOSS_SIZE: f64 = 120.0;
+// ボスが降下をやめて左右に動き始める高さ
+pub const BOSS_HOLD_Y: f64 = 100.0;
+// 特攻する敵が自機に狙いを定める高さ(敵の中心)
+const KAMIKAZE_LOCK_Y: f64 = 150.0;
+// 特攻中の加速度(1ステップごとに増える速さ)
+const KAMIKAZE_ACCELERATION: f64 = 0.15;
+
+// 横方向の動き方。出現位置のxを基準にしたずれを経過時間から求める
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum MovementPattern {
+    Straight, // 真下に落ちるだけ
+    // 正弦波で左右に揺れる。frequencyは1秒あたりの往復数、phaseはラジアン
+    Sine { amplitude: f64, frequency: f64, phase: f64 },
+    // 一定の速さで左右に折り返す。periodは1往復の時間(ms)、dxは片側への振れ幅(px)
+    Zigzag { period: f64, dx: f64 },
+}
+
+impl MovementPattern {
+    // 出現からの経過時間(ms)における、出現位置からの横方向のずれ
+    pub fn offset_x(self, elapsed: f64) -> f64 {
+        match self {
+            MovementPattern::Straight => 0.0,
+            MovementPattern::Sine {
+                amplitude,
+                frequency,
+                phase,
+            } => amplitude * (std::f64::consts::TAU * frequency * elapsed / 1000.0 + phase).sin(),
+            MovementPattern::Zigzag { period, dx } => {
+                // 0→dx→0→-dx→0 と折り返す三角波
+                let t = (elapsed / period).rem_euclid(1.0);
+                let wave = if t < 0.25 {
+                    t * 4.0
+                } else if t < 0.75 {
+                    2.0 - t * 4.0
+                } else {
+                    t * 4.0 - 4.0
+                };
+                dx * wave
+            }
+        }
+    }
+}
+
+// 弾が命中した部位
+#[derive(Clone, Copy, PartialEq)]
+pub enum HitZone {
+    Body,
+    WeakPoint,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Enemy {
-    pub x: f64,
-    pub y: f64,
+    pub pos: Vec2, // 左上の位置
+    pub prev: Vec2, // 前フレームの位置
     pub width: f64,
     pub height: f64,
     pub speed: f64,
-    pub image: HtmlImageElement, // 敵の画像
+    pub kind: EnemyKind,
+    pub score_value: u32, // 撃破時に得られるスコア
+    pub hp: u32, // 残り体力
+    pub max_hp: u32, // 最大体力
+    pub hit_flash: f64, // 被弾点滅の残り時間(ms)
+    pub fire_timer: Option<f64>, // 次の射撃までの残り時間(ms)、撃たない敵はNone
+    pub pattern: Option<PatternRunner>, // 弾幕パターン(装甲付きの敵のみ)
+    pub movement: MovementPattern, // 横方向の動き方
+    pub spawn_x: f64, // 出現時のx(横の動きの基準)
+    pub elapsed: f64, // 出現からの経過時間(ms)
+    pub dive: Option<(f64, f64)>, // 特攻する向き(単位ベクトル)。狙いを定めるまではNone
+    pub sprite: usize, // 見た目の種類(敵の画像の番号)
+    pub dead: bool, // 倒されたか自機にぶつかったか(当たり判定をすべて終えてからまとめて取り除く)
+}
+
+impl Enemy {
+    // ダメージを与え、撃破された場合はtrueを返す
+    pub fn take_damage(&mut self, amount: u32) -> bool {
+        self.hp = self.hp.saturating_sub(amount);
+        if self.hp == 0 {
+            return true;
+        }
+        self.hit_flash = HIT_FLASH_DURATION;
+        false
+    }
+
+    // 1ステップ分動かす。delta_timeはゲーム内時間(ms)、targetは自機の中心
+    // ボスは所定の高さまで降りたら止まり、そこから横の動きを始める
+    // 特攻する敵は所定の高さでその時点の自機の位置に狙いを定め、以降はその向きへ加速し続ける
+    pub fn advance(&mut self, step: f64, delta_time: f64, field_width: f64, target: (f64, f64)) {
+        self.prev = self.pos;
+        if self.kind == EnemyKind::Kamikaze {
+            let center = (self.pos.x + self.width / 2.0, self.pos.y + self.height / 2.0);
+            if self.dive.is_none() && center.1 >= KAMIKAZE_LOCK_Y {
+                self.dive = Some(dive_direction(center, target));
+            }
+            if let Some((dx, dy)) = self.dive {
+                self.speed += KAMIKAZE_ACCELERATION * step;
+                self.pos += Vec2::new(dx * self.speed * step, dy * self.speed * step);
+                return;
+            }
+        }
+        if self.kind == EnemyKind::Boss {
+            if self.pos.y >= BOSS_HOLD_Y {
+                self.elapsed += delta_time;
+            }
+            self.pos.y = (self.pos.y + self.speed * step).min(BOSS_HOLD_Y);
+        } else {
+            self.pos.y += self.speed * step;
+            self.elapsed += delta_time;
+        }
+        self.pos.x = self.movement_x(field_width);
+    }
+
+    // 経過時間に応じた横位置。場の幅からはみ出さないよう収める
+    pub fn movement_x(&self, field_width: f64) -> f64 {
+        let x = self.spawn_x + self.movement.offset_x(self.elapsed);
+        x.clamp(0.0, (field_width - self.width).max(0.0))
+    }
+
+    // 速い敵ほど高得点にするための上乗せ分
+    pub fn speed_bonus(speed: f64) -> u32 {
+        ((speed - FAST_ENEMY_SPEED) / SPEED_BONUS_STEP).ceil().max(0.0) as u32
+    }
+
+    // 装甲付きの敵かどうか
+    pub fn is_armored(&self) -> bool {
+        matches!(self.kind, EnemyKind::Armored | EnemyKind::Boss)
+    }
+
+    // 場から出ていったかどうか。特攻中の敵は横や上からも出ていく
+    pub fn has_left_field(&self, field_width: f64, field_height: f64) -> bool {
+        let Vec2 { x, y } = self.pos;
+        if y > field_height {
+            return true;
+        }
+        self.dive.is_some() && (x + self.width < 0.0 || x > field_width || y + self.height < 0.0)
+    }
+
+    // 弱点(コックピット)の矩形 (x, y, 幅, 高さ)。装甲付きの敵のみ持つ
+    pub fn weak_point(&self) -> Option<(f64, f64, f64, f64)> {
+        if !self.is_armored() {
+            return None;
+        }
+        let width = self.width * 0.3;
+        let height = self.height * 0.25;
+        Some((
+            self.pos.x + (self.width - width) / 2.0,
+            self.pos.y + self.height * 0.6,
+            width,
+            height,
+        ))
+    }
 }
 
+// テストで使う、止まったままの敵
+#[cfg(test)]
 impl Enemy {
-    // 必要に応じてメソッドを追加
+    pub fn for_test(kind: EnemyKind, x: f64, y: f64) -> Enemy {
+        let size = if kind == EnemyKind::Boss { BOSS_SIZE } else { ENEMY_SIZE };
+        let hp = if matches!(kind, EnemyKind::Armored | EnemyKind::Boss) { 3 } else { 1 };
+        Enemy {
+            pos: Vec2::new(x, y),
+            prev: Vec2::new(x, y),
+            width: size,
+            height: size,
+            speed: 0.0,
+            kind,
+            score_value: 10,
+            hp,
+            max_hp: hp,
+            hit_flash: 0.0,
+            fire_timer: None,
+            pattern: None,
+            movement: MovementPattern::Straight,
+            spawn_x: x,
+            elapsed: 0.0,
+            dive: None,
+            sprite: 0,
+            dead: false,
+        }
+    }
+}
+
+impl Collidable for Enemy {
+    fn bounds(&self) -> Rect {
+        (self.pos.x, self.pos.y, self.width, self.height)
+    }
+}
+
+// fromからtoへ向かう単位ベクトル。同じ位置なら真下
+pub fn dive_direction(from: (f64, f64), to: (f64, f64)) -> (f64, f64) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = dx.hypot(dy);
+    if length < f64::EPSILON {
+        return (0.0, 1.0);
+    }
+    (dx / length, dy / length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn straight_never_moves_sideways() {
+        assert_eq!(MovementPattern::Straight.offset_x(0.0), 0.0);
+        assert_eq!(MovementPattern::Straight.offset_x(12345.0), 0.0);
+    }
+
+    #[test]
+    fn sine_weaves_within_its_amplitude() {
+        let sine = MovementPattern::Sine {
+            amplitude: 40.0,
+            frequency: 0.5,
+            phase: 0.0,
+        };
+        assert!(close(sine.offset_x(0.0), 0.0));
+        // 1往復2秒なので、0.5秒で右端、1.5秒で左端
+        assert!(close(sine.offset_x(500.0), 40.0));
+        assert!(close(sine.offset_x(1500.0), -40.0));
+        assert!((0..100).all(|i| sine.offset_x(i as f64 * 37.0).abs() <= 40.0 + 1e-9));
+    }
+
+    #[test]
+    fn zigzag_is_a_triangle_wave() {
+        let zigzag = MovementPattern::Zigzag {
+            period: 1000.0,
+            dx: 60.0,
+        };
+        assert!(close(zigzag.offset_x(0.0), 0.0));
+        assert!(close(zigzag.offset_x(125.0), 30.0));
+        assert!(close(zigzag.offset_x(250.0), 60.0));
+        assert!(close(zigzag.offset_x(500.0), 0.0));
+        assert!(close(zigzag.offset_x(750.0), -60.0));
+        assert!(close(zigzag.offset_x(1000.0), 0.0));
+        assert!(close(zigzag.offset_x(1250.0), 60.0));
+    }
+
+    #[test]
+    fn sideways_movement_stays_in_the_field() {
+        let mut enemy = Enemy::for_test(EnemyKind::Small, 10.0, 0.0);
+        enemy.movement = MovementPattern::Zigzag {
+            period: 1000.0,
+            dx: 100.0,
+        };
+        enemy.elapsed = 750.0;
+        assert_eq!(enemy.movement_x(400.0), 0.0);
+        enemy.spawn_x = 340.0;
+        enemy.elapsed = 250.0;
+        assert_eq!(enemy.movement_x(400.0), 400.0 - enemy.width);
+    }
+
+    #[test]
+    fn dive_direction_is_a_unit_vector_toward_the_target() {
+        assert_eq!(dive_direction((0.0, 0.0), (30.0, 40.0)), (0.6, 0.8));
+        // 同じ位置なら真下へ
+        assert_eq!(dive_direction((5.0, 5.0), (5.0, 5.0)), (0.0, 1.0));
+    }
+
+    #[test]
+    fn kamikaze_locks_on_once_and_accelerates() {
+        let mut enemy = Enemy::for_test(EnemyKind::Kamikaze, 100.0, 0.0);
+        enemy.speed = 2.0;
+        // 狙いを定める高さまではまっすぐ降りる
+        enemy.advance(1.0, 16.0, 400.0, (125.0, 600.0));
+        assert_eq!(enemy.dive, None);
+        assert_eq!(enemy.pos, Vec2::new(100.0, 2.0));
+
+        enemy.pos.y = KAMIKAZE_LOCK_Y;
+        enemy.advance(1.0, 16.0, 400.0, (425.0, 575.0));
+        let (dx, dy) = enemy.dive.unwrap();
+        assert!(close(dx, 0.6) && close(dy, 0.8));
+        let speed = enemy.speed;
+        assert!(close(speed, 2.0 + KAMIKAZE_ACCELERATION));
+
+        // 自機が動いても向きは変えず、速さだけ増えていく
+        enemy.advance(1.0, 16.0, 400.0, (0.0, 0.0));
+        assert_eq!(enemy.dive, Some((dx, dy)));
+        assert!(enemy.speed > speed);
+    }
+
+    #[test]
+    fn only_armored_enemies_have_a_weak_point_inside_their_body() {
+        assert_eq!(Enemy::for_test(EnemyKind::Small, 0.0, 0.0).weak_point(), None);
+        for kind in [EnemyKind::Armored, EnemyKind::Boss] {
+            let enemy = Enemy::for_test(kind, 30.0, 40.0);
+            let (x, y, width, height) = enemy.weak_point().unwrap();
+            let (bx, by, bw, bh) = enemy.bounds();
+            assert!(x > bx && y > by && x + width < bx + bw && y + height < by + bh);
+            // 機体の中央、下寄り
+            assert!(close(x + width / 2.0, bx + bw / 2.0));
+            assert!(y > by + bh / 2.0);
+        }
+    }
 }
@@ -0,0 +1,10 @@
+use crate::game::Difficulty;
+
+// ページ側に知らせるゲーム内の出来事
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameEvent {
+    Score { score: u32 },
+    LifeLost { lives: u32 },
+    Lives { lives: u32 }, // 減った以外の理由でライフの数が変わった(リセットの取り消しなど)
+    GameOver { score: u32, duration_ms: f64, difficulty: Difficulty },
+}
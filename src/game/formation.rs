@@ -0,0 +1,58 @@
+use crate::game::MovementPattern;
+
+// 編隊の人数
+pub const FORMATION_SIZE: usize = 5;
+// 隊員どうしの横の間隔(敵の大きさに対する割合)
+const FORMATION_SPACING: f64 = 1.5;
+// V字の後ろの段ほど上(画面の外側)から進入させるずれ(px)
+const VEE_ROW_OFFSET: f64 = 40.0;
+// 横の揺れの幅(px)と、1秒あたりの往復数
+const FORMATION_SWAY: f64 = 30.0;
+const FORMATION_SWAY_FREQUENCY: f64 = 0.5;
+
+// 編隊の形
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Formation {
+    Row, // 横一列に並び、全員がそろって左右に揺れる
+    Vee, // 先頭を中心にV字に並び、両翼が左右対称に開いたり閉じたりする
+}
+
+// 編隊の隊員1人分の配置
+pub struct FormationSlot {
+    pub x: f64, // 出現位置のx(敵の左端)
+    pub y_offset: f64, // 先頭より上に下げて出すずれ(px)
+    pub movement: MovementPattern,
+}
+
+impl Formation {
+    // center_xを中心に並べた各隊員の配置
+    // 揺れを含めて場の幅に収まるよう、狭い場合は間隔を詰め、端に寄りすぎた場合は全体をずらす
+    pub fn slots(self, center_x: f64, field_width: f64, size: f64) -> Vec<FormationSlot> {
+        let gaps = (FORMATION_SIZE - 1) as f64;
+        let usable = (field_width - size - FORMATION_SWAY * 2.0).max(0.0);
+        let spacing = (size * FORMATION_SPACING).min(usable / gaps);
+        let span = spacing * gaps;
+        let max_left = (field_width - size - FORMATION_SWAY - span).max(FORMATION_SWAY);
+        let left = (center_x - size / 2.0 - span / 2.0).clamp(FORMATION_SWAY, max_left);
+
+        (0..FORMATION_SIZE)
+            .map(|i| {
+                // 中央を0として左右に-1.0〜1.0
+                let side = (i as f64 - gaps / 2.0) / (gaps / 2.0);
+                let (y_offset, amplitude) = match self {
+                    Formation::Row => (0.0, FORMATION_SWAY),
+                    Formation::Vee => (side.abs() * 2.0 * VEE_ROW_OFFSET, side * FORMATION_SWAY),
+                };
+                FormationSlot {
+                    x: left + spacing * i as f64,
+                    y_offset,
+                    movement: MovementPattern::Sine {
+                        amplitude,
+                        frequency: FORMATION_SWAY_FREQUENCY,
+                        phase: 0.0,
+                    },
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,44 @@
+// フレーム時間を平均する直近のフレーム数
+pub const FRAME_SAMPLES: usize = 60;
+
+// 直近のフレーム時間(実時間、ms)。決まった数の枠を順に上書きして使う
+pub struct FrameStats {
+    samples: [f64; FRAME_SAMPLES],
+    next: usize, // 次に書き込む枠
+    count: usize, // 書き込んだ枠の数(FRAME_SAMPLESまで)
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        FrameStats {
+            samples: [0.0; FRAME_SAMPLES],
+            next: 0,
+            count: 0,
+        }
+    }
+
+    pub fn record(&mut self, frame_time: f64) {
+        self.samples[self.next] = frame_time;
+        self.next = (self.next + 1) % FRAME_SAMPLES;
+        self.count = (self.count + 1).min(FRAME_SAMPLES);
+    }
+
+    fn recorded(&self) -> &[f64] {
+        &self.samples[..self.count]
+    }
+
+    // 直近のフレームの平均から求めたフレームレート。まだ測れていなければ0
+    pub fn average_fps(&self) -> f64 {
+        let total: f64 = self.recorded().iter().sum();
+        if total > 0.0 {
+            self.count as f64 * 1000.0 / total
+        } else {
+            0.0
+        }
+    }
+
+    // 直近のフレームで最も長かったフレーム時間(ms)
+    pub fn max_frame_time(&self) -> f64 {
+        self.recorded().iter().copied().fold(0.0, f64::max)
+    }
+}
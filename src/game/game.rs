@@ -1,394 +1,3021 @@
-use wasm_bindgen::{prelude::*, JsCast, closure::Closure, JsValue};
-use web_sys::{window, CanvasRenderingContext2d, HtmlAudioElement, HtmlImageElement, HtmlElement};
 use std::cell::RefCell;
 use std::rc::Rc;
-use js_sys::Math::random;
-use std::f64::consts::PI;
 
-use crate::game::{Player, Bullet, Enemy, GameState};
-use crate::console_log;
+use crate::game::{
+    Player, Bullet, BulletOwner, BulletPool, Enemy, GameState, PendingSpawn, ReplayBuffer, PowerUp,
+    PowerUpKind, CritEffect, BulletPattern, PatternRunner, GameClock, GameTimer, Renderer,
+    AudioSink, Debris, DebrisPool, Rng, ScoreSubmission, InputAction, KeyBindings, Basis,
+    Orientation, Particle, GhostBuffer, RunFlags, RunStash, RunHistory, RunResult, RunStore,
+    RunSummary, GameEvent, TextStyle, MovementPattern, EnemyKind, LevelConfig, Wave,
+    normalize_key, move_direction, ScorePopup, Combo, Missile, Blast, GameMode, Difficulty,
+    GameConfig, FrameStats, Vec2, SpatialGrid, BombMeter,
+};
+use crate::game::undo::UNDO_RESET_WINDOW;
+use crate::game::bomb_meter::boss_phase;
+use crate::game::sound::{
+    KillSounds, BOMB_READY_SOUND, BOSS_WARNING_SOUND, ESCAPE_SOUND, EXTRA_LIFE_SOUND,
+    SHIELD_BREAK_SOUND,
+};
+use crate::game::particles::{MAX_PARTICLES, PARTICLE_COLORS, PARTICLE_LIFETIME};
+use crate::game::submission::{empty_input_hash, fnv1a};
+use crate::game::debris::DEBRIS_LIFETIME;
+use crate::game::enemy::{HitZone, WEAK_POINT_MULTIPLIER};
+use crate::game::collision::{
+    circle_overlaps_rect, rect_union, rects_overlap, swept_circle_hits_rect, wrapped_copy,
+    Collidable, CollisionGroup, Rect, COLLISION_GROUP_COUNT,
+};
+use crate::game::enemy::{BOSS_SIZE, ENEMY_FIRE_INTERVAL, ENEMY_SIZE, FAST_ENEMY_SPEED};
+use crate::game::spawn::{pick_sprite, pick_spawn_x, SPAWN_GUARD_DURATION};
+use crate::game::wave::{
+    boss_entry_y, BossIntro, BossIntroPhase, BOSS_ENTRY_DURATION, BOSS_WARNING_DURATION,
+    WAVE_BREAK_DURATION,
+};
+use crate::game::bullet::PLAYER_BULLET_RADIUS;
+use crate::game::weapon::{barrels, rotate, MAX_WEAPON_LEVEL};
+use crate::game::formation::{Formation, FORMATION_SIZE};
+use crate::game::missile::{nearest_enemy, MISSILE_BLAST_RADIUS, MISSILE_COOLDOWN, MISSILE_DAMAGE};
+use crate::game::modifier::{roll_modifier, ActiveModifier};
+
+// 敵の撃破時にパワーアップを落とす確率
+const POWER_UP_DROP_CHANCE: f64 = 0.15;
+// スコアボーナスの加算量
+const SCORE_BONUS: u32 = 5;
+// スピードアップの効果時間(ms)と倍率
+const SPEED_BOOST_DURATION: f64 = 5000.0;
+const SPEED_BOOST_MULTIPLIER: f64 = 1.6;
+// 跳弾の効果時間(ms)
+const RICOCHET_DURATION: f64 = 10000.0;
+// プレイ開始時のボムの数と、補充で持てる上限
+pub const STARTING_BOMBS: u32 = 2;
+const MAX_BOMBS: u32 = 3;
+// ボムがボスに与えるダメージ
+const BOMB_BOSS_DAMAGE: u32 = 10;
+// ボムを使ったときに画面を白く光らせる時間(ms)
+pub const BOMB_FLASH_DURATION: f64 = 80.0;
+// 敵に抜けられたときに画面下端を赤く光らせる時間(ms)と、光らせる帯の高さ(px)
+pub const ESCAPE_FLASH_DURATION: f64 = 400.0;
+pub const ESCAPE_FLASH_HEIGHT: f64 = 40.0;
+// ライフが増えたときにHUDのライフ表示を目立たせる時間(ms)
+const LIFE_FLASH_DURATION: f64 = 1000.0;
+// 貫通弾の効果時間(ms)と、1発で突き抜けられる敵の数
+const PIERCE_DURATION: f64 = 8000.0;
+const PIERCE_COUNT: u8 = 3;
+// この波ごとに、波の最初の出現を編隊にする
+const FORMATION_WAVE_INTERVAL: u32 = 3;
+// 2波目以降、出現のたびに編隊になる確率
+const FORMATION_CHANCE: f64 = 0.1;
+// レーザーの幅(px)と、照射中に重なっている敵へ与える毎秒のダメージ
+const LASER_WIDTH: f64 = 14.0;
+const LASER_DAMAGE_PER_SECOND: f64 = 8.0;
+// エネルギーが満タンから尽きるまでの照射時間と、空から満タンまでの回復時間(ms)
+const LASER_DRAIN_TIME: f64 = 3000.0;
+const LASER_RECHARGE_TIME: f64 = 6000.0;
+// 照射を始めるのに必要なエネルギー(尽きた直後に途切れ途切れに撃てないようにする)
+pub const LASER_MIN_ENERGY: f64 = 0.2;
+// 射撃キーをこの時間(ms)以上押してから離すと溜め撃ちになり、CHARGE_MAX_TIMEで最大になる
+pub const CHARGE_MIN_TIME: f64 = 500.0;
+pub const CHARGE_MAX_TIME: f64 = 1500.0;
+// シールドの効果時間(ms)
+pub const SHIELD_DURATION: f64 = 10000.0;
+// 被弾時の画面の揺れの時間(ms)と大きさ(px)
+pub const SHAKE_DURATION: f64 = 300.0;
+const SHAKE_MAGNITUDE: f64 = 8.0;
+// 背景が流れる速さ(px/ms)。重ねる星は遅く流して奥行きを出す
+const BACKGROUND_SCROLL_SPEED: f64 = 0.06;
+const STARFIELD_SCROLL_SPEED: f64 = 0.025;
+// ゲームオーバー時に、生き残った時間この長さ(ms)ごとにスコアへ1点加える
+const SURVIVAL_BONUS_INTERVAL: f64 = 5000.0;
+// 始める前のカウントダウン(「3」「2」「1」「GO!」)の1つあたりの表示時間(実時間、ms)
+pub const COUNTDOWN_STEP: f64 = 700.0;
+pub const COUNTDOWN_LABELS: [&str; 4] = ["3", "2", "1", "GO!"];
+// 1フレームで進める実時間の上限(ms)
+const MAX_FRAME_DELTA: f64 = 100.0;
+// アシスト設定で選べるゲーム速度の範囲
+pub const MIN_GAME_SPEED: f64 = 0.5;
+pub const MAX_GAME_SPEED: f64 = 1.0;
+
+// グループの組み合わせに対する当たり判定処理
+type CollisionHandler = fn(&mut Game);
+
+// どのグループ同士を判定し、どの処理を呼ぶかの表
+// 組み合わせは上三角(行 <= 列)にだけ置き、判定は行、列の順に行う
+// 新しい種類の物体を追加するときは、グループと表の行と処理を追加する
+const COLLISION_MATRIX: [[Option<CollisionHandler>; COLLISION_GROUP_COUNT];
+    COLLISION_GROUP_COUNT] = {
+    use CollisionGroup::*;
+    let mut matrix: [[Option<CollisionHandler>; COLLISION_GROUP_COUNT];
+        COLLISION_GROUP_COUNT] = [[None; COLLISION_GROUP_COUNT]; COLLISION_GROUP_COUNT];
+    matrix[Laser as usize][Enemy as usize] = Some(Game::collide_laser_with_enemies);
+    matrix[Missile as usize][Enemy as usize] = Some(Game::collide_missiles_with_enemies);
+    matrix[PlayerBullet as usize][Enemy as usize] = Some(Game::collide_bullets_with_enemies);
+    matrix[Enemy as usize][Player as usize] = Some(Game::collide_enemies_with_player);
+    matrix[Player as usize][EnemyBullet as usize] =
+        Some(Game::collide_enemy_bullets_with_player);
+    matrix[Player as usize][PowerUp as usize] = Some(Game::collect_power_ups);
+    matrix
+};
+
+// 2つのグループの組み合わせに対する処理(どちらの順に指定しても同じ処理を返す)
+fn collision_handler(a: CollisionGroup, b: CollisionGroup) -> Option<CollisionHandler> {
+    let (a, b) = (a as usize, b as usize);
+    COLLISION_MATRIX[a.min(b)][a.max(b)]
+}
 
 pub struct Game {
+    pub width: f64, // ゲーム内の場の幅(自機から見て左右方向)
+    pub height: f64, // ゲーム内の場の高さ(自機から見て前後方向)
+    pub orientation: Orientation, // 画面の向き
     pub player: Player,
-    pub bullets: Vec<Bullet>,
+    pub bullets: BulletPool, // 自機の弾(使い回す枠)
+    pub enemy_bullets: Vec<Bullet>, // 敵の弾
     pub enemies: Vec<Enemy>,
-    pub last_enemy_spawn: f64,
-    pub enemy_spawn_interval: f64,
+    pub pending_spawns: Vec<PendingSpawn>, // 予告表示中の出現待ちの敵
+    pub power_ups: Vec<PowerUp>,
+    pub speed_boost: GameTimer, // スピードアップの効果時間
+    pub ricochet: GameTimer, // 跳弾の効果時間
+    pub crit_effects: Vec<CritEffect>,
+    pub score_popups: Vec<ScorePopup>, // 撃破した位置に浮かべる獲得スコア
+    pub combo: Combo, // 連続撃破によるスコアの倍率
+    pub debris: DebrisPool, // 撃破された敵の破片
+    pub particles: Vec<Particle>, // 撃破時の火花
+    pub shake: GameTimer, // 被弾時の画面の揺れ
+    pub background_offset_y: f64, // 背景のスクロール量(場の高さで折り返す)
+    pub starfield_offset_y: f64,  // 重ねる星のスクロール量
+    pub ghost: GhostBuffer, // 対戦相手の自機の位置(当たり判定はない)
+    pub shake_magnitude: f64, // 揺れ始めの大きさ(px)
+    pub crit_hits: u32, // 弱点への命中数
+    pub kills: u32, // 撃破した敵の数
+    pub enemies_escaped: u32, // 倒せずに画面下へ抜けた敵の数
+    pub next_life_at: u32, // このスコアに達したらライフを1つ増やす
+    pub bombs: u32, // 残りのボムの数
+    pub bomb_meter: BombMeter, // 撃破で溜まり、満タンになるとボムが1つ増えるメーター
+    pub shield: GameTimer, // 被弾を1回防ぐシールドの残り時間(防いだ時点で消える)
+    pub pierce: GameTimer, // 貫通弾の効果時間
+    pub laser_energy: f64, // レーザーの残りエネルギー(0.0〜1.0)
+    pub laser_firing: bool, // このフレームでレーザーを照射しているか
+    pub laser_damage: f64, // レーザーが与えるダメージのうち、まだ1に満たない端数
+    pub charge_start: Option<f64>, // 射撃キーを押し始めたゲーム内時刻(溜めていなければNone)
+    pub weapon_level: u8, // 武器の強化段階(1〜MAX_WEAPON_LEVEL)
+    pub missiles: Vec<Missile>, // 飛んでいる追尾ミサイル
+    pub missile_cooldown: GameTimer, // 次のミサイルを撃てるようになるまでの時間
+    pub blasts: Vec<Blast>, // ミサイルの爆風
+    pub bomb_flash: GameTimer, // ボムを使った直後に画面を白く光らせる
+    pub life_flash: GameTimer, // ライフが増えた直後にHUDのライフ表示を目立たせる
+    pub escape_flash: GameTimer, // 敵に抜けられた直後に画面下端を赤く光らせる
+    pub play_time: f64, // 一時停止中を除いて遊んだ実時間(ms)
+    pub survival_bonus: u32, // ゲームオーバー時に加えた生存ボーナス
+    pub hits_taken: u32, // 被弾した回数(練習モードではライフの代わりに表示する)
+    pub countdown: f64, // 始める前のカウントダウンの残り(実時間、ms)。この間は場を止める
+    pub previous_best: u32, // ゲームオーバー時点での、このプレイより前の最高スコア
+    pub shots_fired: u32, // 撃った弾の数
+    pub shots_hit: u32, // 敵に当たった弾の数
+    pub level: LevelConfig, // 敵の出方やスコアの調整値
+    pub current_wave: Wave, // 進行中の波
+    pub wave_spawned: u32, // 進行中の波で出した敵の数
+    pub wave_break: GameTimer, // 次の波までの休憩(「WAVE N」を表示する)
+    pub wave_modifier: Option<ActiveModifier>, // 進行中の波に掛けている変化。掛けていなければNone
+    pub modifier_waves: u32, // このプレイで変化の掛かった波の数
+    pub spawn_timer: GameTimer, // 次の敵の生成までの時間
+    pub next_boss_score: u32, // このスコアを超えたら次のボスを出す
+    pub boss_intro: Option<BossIntro>, // ボス登場の演出中の波の進行。演出中でなければNone
+    pub spawn_guard: GameTimer, // 被弾後、自機の真上に敵を出しにくくする時間
     pub score: u32,
     pub lives: u32,
     pub state: GameState,
-    pub keys_pressed: Vec<String>,
-    pub context: CanvasRenderingContext2d,
-    pub shoot_sound: HtmlAudioElement,
-    pub explosion_sound: HtmlAudioElement,
+    pub mode: GameMode, // 遊び方(タイトル画面で選び、リセットしても引き継ぐ)
+    pub difficulty: Difficulty, // 難易度(リセットしても引き継ぐ)
+    pub config: GameConfig, // ページから渡された自機や弾の設定
+    pub key_bindings: KeyBindings,
+    pub key_hints: [String; 2], // タイトル画面の操作の説明(割り当てを変えたときだけ作り直す)
+    pub keys_pressed: Vec<(String, InputAction)>, // 押されているキーと対応する操作
+    pub paused: bool,
+    pub renderer: Box<dyn Renderer>, // 描画先
+    pub audio: Box<dyn AudioSink>,   // 効果音の再生先
+    pub kill_sounds: KillSounds, // 撃破した敵の種類ごとの効果音(アセットの一覧から読み込む)
+    pub master_volume: f64, // 効果音の音量(0.0〜1.0)
+    pub music_volume: f64, // BGMの音量(0.0〜1.0)
+    pub muted: bool, // 消音中か(音量の設定は残す)
+    pub music_playing: bool, // BGMを流しているか
+    pub laser_playing: bool, // レーザーの音を流しているか
     pub last_frame_time: f64,
-    pub background_image: HtmlImageElement, // 背景画像
-    pub enemy_image: HtmlImageElement,      // 敵の共通画像
+    pub loop_generation: u32, // 動かしてよいゲームループの世代(古いループは自分で止まる)
+    pub clock: GameClock, // ゲーム内時間
+    pub assist_speed: f64, // アシスト設定によるゲーム速度(0.5〜1.0)
+    pub wrap_horizontal: bool, // プレイヤーが左右の端でループするか
+    pub run_flags: RunFlags, // このプレイ中に使われた、公正さに影響する設定
+    pub reduced_motion: bool, // 動きの多い演出を抑えるか
+    pub headless: bool, // 画面に描かずに動かしているか(テストやボット)
+    pub replay: ReplayBuffer, // 直近の場面の記録
+    pub spectate_time: f64, // 再生中の記録上の時刻
+    pub rng: Rng, // ゲーム内の乱数はすべてここから取る
+    pub seed: u64, // 現在のプレイの乱数シード
+    pub input_hash: u64, // 現在のプレイのキー入力記録のハッシュ
+    pub reset_stash: Option<RunStash>, // リセットを取り消すために残した直前のプレイ
+    pub history: RunHistory, // これまでのプレイの記録
+    pub run_store: Option<Box<dyn RunStore>>, // プレイの記録の保存先
+    pub events: Vec<GameEvent>, // ページ側へ知らせる前の出来事(フレームの処理後に送る)
+    pub hud_font_size: f64, // Canvasに描くスコアとライフの文字の大きさ(px)
+    pub hud_style: TextStyle,
+    pub label_buffer: RefCell<String>, // HUDの文字列を毎フレーム作り直さないための領域
+    pub aim: Option<(f64, f64)>, // マウスで狙っている位置(ゲーム内座標)。未操作ならNone
+    pub debug_overlay: bool, // フレームレートなどの調査用の表示を出すか(F3で切り替え)
+    pub debug_hitboxes: bool, // 当たり判定の枠を重ねて描くか(F4で切り替え)
+    pub frame_stats: FrameStats, // 直近のフレーム時間
+    pub enemy_grid: SpatialGrid, // 弾と敵の当たり判定の候補を絞るための敵の配置(毎フレーム作り直す)
+    pub brute_force_collisions: bool, // 格子を使わず、弾と敵の全組み合わせを判定するか(確認用)
 }
 
 impl Game {
+    // width と height は画面(Canvas)の大きさ
     pub fn new(
-        context: CanvasRenderingContext2d,
-        shoot_sound: HtmlAudioElement,
-        explosion_sound: HtmlAudioElement,
-        player_image: HtmlImageElement,
-        background_image: HtmlImageElement,
-        enemy_image: HtmlImageElement,
+        renderer: Box<dyn Renderer>,
+        audio: Box<dyn AudioSink>,
+        seed: u64,
+        width: f64,
+        height: f64,
     ) -> Rc<RefCell<Game>> {
+        let clock = GameClock::new();
+        let config = GameConfig::default();
+        let player_size = config.player_size;
+        let level = LevelConfig::default();
         Rc::new(RefCell::new(Game {
+            width,
+            height,
+            orientation: Orientation::BottomUp,
             player: Player {
-                x: 300.0,
-                y: 550.0,
-                width: 50.0,
-                height: 50.0,
-                speed: 5.0,
-                image: player_image,
+                // 画面下端の中央から始める
+                pos: Vec2::new((width - player_size) / 2.0, height - player_size),
+                width: player_size,
+                height: player_size,
+                speed: config.player_speed,
+                hurtbox_size: config.hurtbox_size(),
             },
-            bullets: Vec::new(),
+            bullets: BulletPool::new(),
+            enemy_bullets: Vec::new(),
             enemies: Vec::new(),
-            last_enemy_spawn: 0.0,
-            enemy_spawn_interval: 2000.0, // 毎2秒に1体の敵を生成
+            pending_spawns: Vec::new(),
+            power_ups: Vec::new(),
+            speed_boost: GameTimer::default(),
+            ricochet: GameTimer::default(),
+            crit_effects: Vec::new(),
+            score_popups: Vec::new(),
+            combo: Combo::new(),
+            debris: DebrisPool::new(),
+            particles: Vec::with_capacity(MAX_PARTICLES),
+            shake: GameTimer::default(),
+            background_offset_y: 0.0,
+            starfield_offset_y: 0.0,
+            ghost: GhostBuffer::new(),
+            shake_magnitude: 0.0,
+            crit_hits: 0,
+            kills: 0,
+            enemies_escaped: 0,
+            next_life_at: level.extra_life_interval(),
+            bombs: STARTING_BOMBS,
+            bomb_meter: BombMeter::new(),
+            shield: GameTimer::default(),
+            pierce: GameTimer::default(),
+            laser_energy: 1.0,
+            laser_firing: false,
+            laser_damage: 0.0,
+            charge_start: None,
+            weapon_level: 1,
+            missiles: Vec::new(),
+            missile_cooldown: GameTimer::default(),
+            blasts: Vec::new(),
+            bomb_flash: GameTimer::default(),
+            life_flash: GameTimer::default(),
+            escape_flash: GameTimer::default(),
+            play_time: 0.0,
+            survival_bonus: 0,
+            hits_taken: 0,
+            countdown: 0.0,
+            previous_best: 0,
+            shots_fired: 0,
+            shots_hit: 0,
+            current_wave: level.waves.wave(1),
+            wave_spawned: 0,
+            wave_break: GameTimer::started(&clock, WAVE_BREAK_DURATION),
+            wave_modifier: None,
+            modifier_waves: 0,
+            spawn_timer: GameTimer::default(),
+            next_boss_score: level.boss_interval(),
+            boss_intro: None,
+            level,
+            spawn_guard: GameTimer::default(),
             score: 0,
             lives: 3,
-            state: GameState::Playing,
+            state: GameState::Menu,
+            mode: GameMode::Endless,
+            difficulty: Difficulty::Normal,
+            config: GameConfig::default(),
+            key_bindings: KeyBindings::default(),
+            key_hints: KeyBindings::default().menu_hints(),
             keys_pressed: Vec::new(),
-            context,
-            shoot_sound,
-            explosion_sound,
+            paused: false,
+            renderer,
+            audio,
+            kill_sounds: KillSounds::default(),
+            master_volume: 1.0,
+            music_volume: 1.0,
+            muted: false,
+            music_playing: false,
+            laser_playing: false,
             last_frame_time: 0.0,
-            background_image,
-            enemy_image,
+            loop_generation: 0,
+            clock,
+            assist_speed: MAX_GAME_SPEED,
+            wrap_horizontal: false,
+            run_flags: RunFlags::default(),
+            reduced_motion: false,
+            headless: false,
+            replay: ReplayBuffer::new(),
+            spectate_time: 0.0,
+            rng: Rng::new(seed),
+            seed,
+            input_hash: empty_input_hash(),
+            reset_stash: None,
+            history: RunHistory::default(),
+            run_store: None,
+            events: Vec::new(),
+            hud_font_size: 20.0,
+            hud_style: TextStyle::HUD,
+            label_buffer: RefCell::new(String::new()),
+            aim: None,
+            debug_overlay: false,
+            debug_hitboxes: false,
+            frame_stats: FrameStats::new(),
+            enemy_grid: SpatialGrid::new(width, height),
+            brute_force_collisions: false,
         }))
     }
 
-    pub fn key_down(&mut self, key: String) {
-        if !self.keys_pressed.contains(&key) {
-            self.keys_pressed.push(key.clone());
+    // timestamp はキーを押した時刻(requestAnimationFrame の時刻と同じ基準、ms)
+    pub fn key_down(&mut self, key: String, timestamp: f64) {
+        let key = normalize_key(key);
+
+        // 消音と調査用の表示はどの画面でも切り替えられる
+        if key.eq_ignore_ascii_case("m") {
+            self.toggle_mute();
+            return;
+        }
+        if key == "F3" {
+            self.debug_overlay = !self.debug_overlay;
+            return;
+        }
+        if key == "F4" {
+            self.debug_hitboxes = !self.debug_hitboxes;
+            return;
+        }
+
+        match self.state {
+            // タイトル画面とレベルクリア画面はEnterで次へ進む
+            GameState::Menu | GameState::LevelComplete => {
+                if key == "Enter" && self.state == GameState::Menu {
+                    self.begin_run();
+                } else if key == "Enter" {
+                    self.state = GameState::Playing;
+                } else if self.state == GameState::Menu && key.eq_ignore_ascii_case("u") {
+                    self.undo_reset();
+                } else if self.state == GameState::Menu && key.eq_ignore_ascii_case("s") {
+                    self.state = GameState::Stats;
+                } else if self.state == GameState::Menu && key == "ArrowLeft" {
+                    self.set_mode(self.mode.cycle(-1));
+                } else if self.state == GameState::Menu && key == "ArrowRight" {
+                    self.set_mode(self.mode.cycle(1));
+                }
+                return;
+            }
+            // 記録画面はEnterかEscapeでタイトル画面に戻る
+            GameState::Stats => {
+                if key == "Enter" || key == "Escape" {
+                    self.state = GameState::Menu;
+                }
+                return;
+            }
+            // 再生中は任意のキーでスキップ
+            GameState::Spectating => {
+                self.finish_spectate();
+                return;
+            }
+            // Rですぐにやり直し、Enterでタイトル画面に戻る
+            // (ゲームループは動き続けているので新しく始めない)
+            GameState::GameOver => {
+                if key.eq_ignore_ascii_case("r") {
+                    self.reset(timestamp);
+                    self.begin_run();
+                } else if key == "Enter" {
+                    self.reset(timestamp);
+                }
+                // ページ側のゲームオーバー表示は次のフレームを待たずに消す
+                self.update_ui();
+                return;
+            }
+            GameState::Playing => {}
+        }
+
+        // 練習モードはゲームオーバーにならないので、遊んでいる途中でもRでやり直せる
+        if self.mode == GameMode::Practice && key.eq_ignore_ascii_case("r") {
+            self.reset(timestamp);
+            self.begin_run();
+            return;
+        }
+
+        // リセット直後のカウントダウン中はUでリセットを取り消せる
+        if self.countdown > 0.0 && self.reset_stash.is_some() && key.eq_ignore_ascii_case("u") {
+            self.undo_reset();
+            return;
+        }
+
+        // カウントダウン中は一時停止の切り替えだけ受け付ける(押しても弾は出ない)
+        let pause = self.key_bindings.action_for(&key) == Some(InputAction::Pause);
+        if self.countdown > 0.0 && !pause {
+            return;
+        }
+
+        self.record_input(&key, true);
+
+        // キーは受け取った時点で一度だけ操作に置き換える
+        let Some(action) = self.key_bindings.action_for(&key) else {
+            return;
+        };
+
+        if action == InputAction::Pause {
+            self.set_paused(!self.paused);
+            return;
+        }
+        if self.paused {
+            return;
+        }
+
+        // 押しっぱなしによるキーリピートではボムを続けて使わない
+        let repeated = self.keys_pressed.iter().any(|(pressed, _)| *pressed == key);
+        if !repeated {
+            self.keys_pressed.push((key, action));
         }
 
-        if key == " " || key == "Space" {
-            // スペースバーが押された場合、弾丸を発射
-            self.fire_bullet();
+        // 射撃は押した時点では溜め始めるだけで、離したときに撃つ
+        match action {
+            InputAction::Fire if !repeated => self.charge_start = Some(self.clock.now()),
+            InputAction::Bomb if !repeated => self.use_bomb(),
+            InputAction::Missile if !repeated => self.fire_missile(),
+            _ => {}
         }
     }
 
     pub fn key_up(&mut self, key: String) {
-        if let Some(pos) = self.keys_pressed.iter().position(|x| *x == key) {
-            self.keys_pressed.remove(pos);
+        let key = normalize_key(key);
+        if self.state == GameState::Playing {
+            self.record_input(&key, false);
+        }
+        let released = self
+            .keys_pressed
+            .iter()
+            .find(|(pressed, _)| *pressed == key)
+            .map(|&(_, action)| action);
+        self.keys_pressed.retain(|(pressed, _)| *pressed != key);
+
+        if released != Some(InputAction::Fire) {
+            return;
+        }
+        let Some(start) = self.charge_start.take() else {
+            return;
+        };
+        if self.state == GameState::Playing && !self.paused {
+            let held = self.clock.now() - start;
+            self.fire_bullet(charge_power(held));
+        }
+    }
+
+    // 溜めている間の進み具合(0.0〜1.0)。溜めていなければNone
+    pub fn charge_progress(&self) -> Option<f64> {
+        let start = self.charge_start?;
+        Some(((self.clock.now() - start) / CHARGE_MAX_TIME).clamp(0.0, 1.0))
+    }
+
+    // ゲーム内の向きの操作に対応するキーのいずれかが押されているかどうか
+    pub fn is_pressed(&self, action: InputAction) -> bool {
+        self.keys_pressed
+            .iter()
+            .any(|&(_, pressed)| self.orientation.local_action(pressed) == action)
+    }
+
+    // 対戦相手の自機の位置を受け取る(sent_at は相手側の時刻 ms)
+    pub fn receive_ghost_position(&mut self, x: f64, y: f64, sent_at: f64) {
+        self.ghost.push(sent_at, x, y, self.last_frame_time);
+    }
+
+    // 画面の大きさ
+    pub fn screen_size(&self) -> (f64, f64) {
+        self.orientation.field_size((self.width, self.height))
+    }
+
+    // ゲーム内座標から画面座標への変換
+    pub fn basis(&self) -> Basis {
+        self.orientation.basis((self.width, self.height))
+    }
+
+    // 画面の向きを変えて最初からやり直す。now は現在時刻(ms、reset を参照)
+    pub fn set_orientation(&mut self, orientation: Orientation, now: f64) {
+        let screen = self.screen_size();
+        self.orientation = orientation;
+        (self.width, self.height) = orientation.field_size(screen);
+        self.reset(now);
+    }
+
+    // キー入力をゲーム内時刻とともに入力記録のハッシュへ混ぜ込む
+    fn record_input(&mut self, key: &str, pressed: bool) {
+        let time = self.clock.now() as u64;
+        self.input_hash = fnv1a(self.input_hash, &time.to_le_bytes());
+        self.input_hash = fnv1a(self.input_hash, &[pressed as u8]);
+        self.input_hash = fnv1a(self.input_hash, key.as_bytes());
+    }
+
+    // ゲームオーバー後にリーダーボードへ送る内容を作る
+    pub fn score_submission(&self) -> Option<ScoreSubmission> {
+        if self.state != GameState::GameOver {
+            return None;
         }
+        Some(ScoreSubmission::new(
+            self.seed,
+            self.clock.now() as u64,
+            self.score,
+            self.input_hash,
+            self.run_flags,
+        ))
     }
 
-    pub fn fire_bullet(&mut self) {
-        let bullet = Bullet {
-            x: self.player.x + self.player.width / 2.0 - 5.0, // 弾丸の中央に合わせる
-            y: self.player.y,
-            radius: 5.0,
-            speed: 7.0,
-            color: "red".to_string(),
+    // charge は溜め撃ちの強さ(0.0〜1.0)。Noneなら通常の弾を撃つ
+    pub fn fire_bullet(&mut self, charge: Option<f64>) {
+        // 弾丸の中央を自機の中央に合わせる
+        let center_x = self.player.pos.x + self.player.width / 2.0;
+        let mut shot = match charge {
+            Some(power) => Bullet::new_charged_shot(center_x, self.player.pos.y, power),
+            None => Bullet::new_player_shot(center_x - PLAYER_BULLET_RADIUS, self.player.pos.y),
         };
-        self.bullets.push(bullet);
+        let (dx, dy) = self.aim_direction();
+        // 照準の向きも再現に必要なので、入力の記録に含める
+        if self.aim.is_some() {
+            self.input_hash = fnv1a(self.input_hash, &dx.to_le_bytes());
+            self.input_hash = fnv1a(self.input_hash, &dy.to_le_bytes());
+        }
+        // 貫通弾は色を変えて区別する(溜め撃ちの弾は溜め撃ちの色のまま)
+        if self.pierce.is_running(&self.clock) {
+            shot.pierce_remaining = PIERCE_COUNT;
+            if charge.is_none() {
+                shot.color = "orange";
+            }
+        }
+
+        // 武器の強化段階に応じて、ずらした位置や向きに複数の弾を撃つ
+        let mut fired = 0;
+        for (offset_x, angle) in barrels(self.weapon_level, self.player.width) {
+            let (dx, dy) = rotate((dx, dy), angle);
+            let offset = Vec2::new(offset_x, 0.0);
+            let bullet = Bullet {
+                pos: shot.pos + offset,
+                prev: shot.prev + offset,
+                vel: Vec2::new(dx, dy).scale(self.config.bullet_speed),
+                ..shot
+            };
+            // 画面上の弾が上限に達している間は撃てない
+            if self.bullets.fire(bullet) {
+                fired += 1;
+            }
+        }
+        if fired == 0 {
+            return;
+        }
+        self.shots_fired += fired;
 
         // 射撃音を再生
-        let _ = self.shoot_sound.play();
+        self.audio.play_shot();
+    }
+
+    // 自機の中央から照準へ向かう単位ベクトル。照準がなければ真上
+    fn aim_direction(&self) -> (f64, f64) {
+        let Some(aim) = self.aim else {
+            return (0.0, -1.0);
+        };
+        let offset = Vec2::from(aim) - self.player.center();
+        if offset.length() < 1.0 {
+            return (0.0, -1.0);
+        }
+        offset.normalize().into()
+    }
+
+    // 照準の位置を画面座標で受け取る(マウスの位置)
+    pub fn set_aim(&mut self, screen_x: f64, screen_y: f64) {
+        self.aim = Some(self.basis().local_point((screen_x, screen_y)));
     }
 
     pub fn spawn_enemy(&mut self) {
-        let enemy_width = 50.0;
-        let enemy_height = 50.0;
-        let x = random() * (800.0 - enemy_width);
-        let y = 0.0;
-        let speed = 2.0 + random() * 3.0; // 2.0から5.0の速度
+        // 被弾直後は自機の真上を避ける
+        let guard_x = self
+            .spawn_guard
+            .is_running(&self.clock)
+            .then(|| self.player.pos.x + self.player.width / 2.0);
+        let x = pick_spawn_x(&mut self.rng, self.width, ENEMY_SIZE, guard_x);
+        // 画面の少し上から進入させる
+        let y = -ENEMY_SIZE;
+        let (min_speed, max_speed) = self.current_wave.speed_range;
+        let speed = min_speed + self.rng.next_f64() * (max_speed - min_speed);
+        // 一定確率で体力3の装甲付きの敵を生成
+        // スコアが一定を超えてからは、自機に突っ込んでくる敵も混ぜる
+        let kind = if self.rng.next_f64() < self.level.armored_chance {
+            EnemyKind::Armored
+        } else if self.score > self.level.kamikaze_min_score
+            && self.rng.next_f64() < self.level.kamikaze_chance
+        {
+            EnemyKind::Kamikaze
+        } else {
+            EnemyKind::Small
+        };
+        // 横の動き方を重み付きで選ぶ(真下50%、正弦波30%、ジグザグ20%)
+        // 特攻する敵は狙いを定めるまで真下に降りる
+        let roll = self.rng.next_f64();
+        let movement = if roll < 0.5 || kind == EnemyKind::Kamikaze {
+            MovementPattern::Straight
+        } else if roll < 0.8 {
+            MovementPattern::Sine {
+                amplitude: 30.0 + self.rng.next_f64() * 50.0,
+                frequency: 0.5 + self.rng.next_f64() * 0.5,
+                phase: self.rng.next_f64() * std::f64::consts::TAU,
+            }
+        } else {
+            MovementPattern::Zigzag {
+                period: 1000.0 + self.rng.next_f64() * 1000.0,
+                dx: 40.0 + self.rng.next_f64() * 40.0,
+            }
+        };
 
-        let enemy = Enemy {
-            x,
-            y,
-            width: enemy_width,
-            height: enemy_height,
+        let enemy = self.build_enemy(kind, (x, y), speed, movement);
+        // 予告マーカーを表示してから出現させる
+        self.pending_spawns.push(PendingSpawn::new(enemy));
+    }
+
+    // 編隊を組んだ通常の敵をまとめて出す。隊員は同じ速さで、横の動きも連動させる
+    pub fn spawn_formation(&mut self, formation: Formation) {
+        // 編隊が崩れて見えないよう、速さは波の範囲の遅い側から選ぶ
+        let (min_speed, max_speed) = self.current_wave.speed_range;
+        let speed = min_speed + self.rng.next_f64() * (max_speed - min_speed) / 2.0;
+        let center_x = ENEMY_SIZE / 2.0 + self.rng.next_f64() * (self.width - ENEMY_SIZE);
+        for slot in formation.slots(center_x, self.width, ENEMY_SIZE) {
+            let position = (slot.x, -ENEMY_SIZE - slot.y_offset);
+            let enemy = self.build_enemy(EnemyKind::Small, position, speed, slot.movement);
+            self.pending_spawns.push(PendingSpawn::new(enemy));
+        }
+    }
+
+    // 種類、出現位置、速さ、横の動き方を決めた敵を作る。体力や攻撃の仕方は種類から決める
+    fn build_enemy(
+        &mut self,
+        kind: EnemyKind,
+        (x, y): (f64, f64),
+        speed: f64,
+        movement: MovementPattern,
+    ) -> Enemy {
+        let def = *self.level.enemy(kind);
+        // 速い敵は見た目を変えて見分けられるようにする
+        let sprite = pick_sprite(
+            &mut self.rng,
+            self.renderer.enemy_variants(),
+            speed >= FAST_ENEMY_SPEED,
+        );
+        // 一部の敵は弾を撃ち返してくる
+        let fire_timer = if self.rng.next_f64() < def.fire_chance {
+            Some(self.rng.next_f64() * ENEMY_FIRE_INTERVAL)
+        } else {
+            None
+        };
+        // 装甲付きの敵は弾幕パターンで攻撃する
+        let pattern = if kind == EnemyKind::Armored {
+            let roll = self.rng.next_f64();
+            let pattern = if roll < 0.33 {
+                BulletPattern::Ring { count: 8, speed: 2.5 }
+            } else if roll < 0.66 {
+                BulletPattern::Spiral {
+                    rate: 6.0,
+                    angular_step: 0.5,
+                    speed: 2.5,
+                }
+            } else {
+                BulletPattern::AimedFan {
+                    count: 3,
+                    spread: 0.6,
+                    speed: 3.0,
+                }
+            };
+            Some(PatternRunner::new(pattern, 2000.0))
+        } else {
+            None
+        };
+
+        Enemy {
+            pos: Vec2::new(x, y),
+            prev: Vec2::new(x, y),
+            width: ENEMY_SIZE,
+            height: ENEMY_SIZE,
             speed,
-            image: self.enemy_image.clone(),
+            kind,
+            score_value: (def.score + Enemy::speed_bonus(speed)) * self.level.score_multiplier,
+            hp: def.hp,
+            max_hp: def.hp,
+            hit_flash: 0.0,
+            fire_timer,
+            pattern,
+            movement,
+            spawn_x: x,
+            elapsed: 0.0,
+            dive: None,
+            sprite,
+            dead: false,
+        }
+    }
+
+    fn spawn_boss(&mut self) {
+        let x = (self.width - BOSS_SIZE) / 2.0;
+        let y = boss_entry_y(0.0);
+        let enemy = Enemy {
+            pos: Vec2::new(x, y),
+            prev: Vec2::new(x, y),
+            width: BOSS_SIZE,
+            height: BOSS_SIZE,
+            speed: 1.0,
+            kind: EnemyKind::Boss,
+            score_value: self.level.boss.score * self.level.score_multiplier,
+            hp: self.level.boss.hp,
+            max_hp: self.level.boss.hp,
+            hit_flash: 0.0,
+            fire_timer: (self.rng.next_f64() < self.level.boss.fire_chance)
+                .then_some(ENEMY_FIRE_INTERVAL),
+            pattern: Some(PatternRunner::new(
+                BulletPattern::Ring { count: 12, speed: 2.0 },
+                1500.0,
+            )),
+            // 止まった位置から場の両端まで往復する
+            movement: MovementPattern::Zigzag {
+                period: 6000.0,
+                dx: (self.width - BOSS_SIZE) / 2.0,
+            },
+            spawn_x: x,
+            elapsed: 0.0,
+            dive: None,
+            sprite: 0,
+            dead: false,
         };
+        // 登場の予告は「WARNING」の表示で済ませているので、すぐに場へ出す
         self.enemies.push(enemy);
     }
 
-    pub fn update_enemies(&mut self, _delta_time: f64) {
-        for enemy in &mut self.enemies {
-            enemy.y += enemy.speed;
+    // 波に沿って敵を出す。ボスがいる間と波の合間の休憩中は通常の敵を出さない
+    fn update_waves(&mut self) {
+        if self.boss_intro.is_some() {
+            self.update_boss_intro();
+            return;
+        }
+        if self.boss_active() || self.wave_break.is_running(&self.clock) {
+            return;
+        }
+        if self.score >= self.next_boss_score {
+            self.next_boss_score += self.level.boss_interval();
+            self.start_boss_intro();
+        } else if self.wave_spawned < self.current_wave.enemy_count {
+            // 場の敵が上限に達している間は、減るまで次を出さない
+            let room = self.level.max_enemies.saturating_sub(self.enemy_count());
+            if !self.spawn_timer.is_running(&self.clock) && room > 0 {
+                let remaining = (self.current_wave.enemy_count - self.wave_spawned) as usize;
+                match self.roll_formation(remaining.min(room)) {
+                    Some(formation) => {
+                        self.spawn_formation(formation);
+                        self.wave_spawned += FORMATION_SIZE as u32;
+                    }
+                    None => {
+                        self.spawn_enemy();
+                        self.wave_spawned += 1;
+                    }
+                }
+                self.spawn_timer.start(&self.clock, self.current_wave.spawn_interval);
+            }
+        } else if self.enemies.is_empty() && self.pending_spawns.is_empty() {
+            // 出した敵がすべて倒されるか画面外に出たら、休憩を挟んで次の波へ
+            self.end_wave_modifier();
+            self.current_wave = self.wave(self.current_wave.number + 1);
+            self.wave_spawned = 0;
+            self.wave_break.start(&self.clock, WAVE_BREAK_DURATION);
+            self.spawn_timer.clear();
+            self.start_wave_modifier();
+        }
+    }
+
+    // 始まる波に変化を掛けるか選び、掛けるなら調整値に重ねる(休憩中の表示で予告する)
+    fn start_wave_modifier(&mut self) {
+        let number = self.current_wave.number;
+        let Some(modifier) = roll_modifier(&mut self.rng, &self.level.wave_modifiers, number)
+        else {
+            return;
+        };
+        self.wave_modifier =
+            Some(ActiveModifier::apply(modifier, &mut self.level, &mut self.current_wave));
+        self.modifier_waves += 1;
+    }
+
+    // 進行中の波に掛けている変化を外し、調整値を重ねる前の値に戻す
+    pub fn end_wave_modifier(&mut self) {
+        if let Some(active) = self.wave_modifier.take() {
+            active.revert(&mut self.level, &mut self.current_wave);
         }
+    }
 
-        // 敵が画面下に到達した場合、敵を削除
-        self.enemies.retain(|enemy| enemy.y <= 600.0);
+    // ボス登場の演出を始める。出現待ちの敵は取り消し、場にいる敵は警告の間に抜けさせる
+    // 次の敵を出すまでの残り時間は止めておき、演出が終わったら続きから数える
+    fn start_boss_intro(&mut self) {
+        self.pending_spawns.clear();
+        self.boss_intro = Some(BossIntro {
+            phase: BossIntroPhase::Warning,
+            timer: GameTimer::started(&self.clock, BOSS_WARNING_DURATION),
+            spawn_remaining: self.spawn_timer.remaining(&self.clock),
+        });
+        self.spawn_timer.clear();
+        self.audio.play_clip(BOSS_WARNING_SOUND);
     }
 
-    pub fn check_collisions(&mut self) {
-        let mut bullets_to_remove = Vec::new();
-        let mut enemies_to_remove = Vec::new();
-
-        // 弾と敵の当たり判定
-        for (b_idx, bullet) in self.bullets.iter().enumerate() {
-            for (e_idx, enemy) in self.enemies.iter().enumerate() {
-                // 弾を矩形として扱うために、幅と高さを設定
-                let bullet_width = bullet.radius * 2.0;
-                let bullet_height = bullet.radius * 2.0;
-
-                if bullet.x < enemy.x + enemy.width
-                    && bullet.x + bullet_width > enemy.x
-                    && bullet.y < enemy.y + enemy.height
-                    && bullet.y + bullet_height > enemy.y
-                {
-                    bullets_to_remove.push(b_idx);
-                    enemies_to_remove.push(e_idx);
-                    self.score += 1;
-
-                    // 爆発音を再生
-                    let _ = self.explosion_sound.play();
+    // 警告が終わったら残った通常の敵を消してボスを出し、定位置に着いたら波の進行に戻す
+    // 降りてくる途中でボスが倒された場合も、その時点で演出を終える
+    fn update_boss_intro(&mut self) {
+        let Some(mut intro) = self.boss_intro else {
+            return;
+        };
+        let running = intro.timer.is_running(&self.clock);
+        match intro.phase {
+            BossIntroPhase::Warning => {
+                if !running {
+                    self.enemies.clear();
+                    self.spawn_boss();
+                    intro.phase = BossIntroPhase::Entering;
+                    intro.timer.start(&self.clock, BOSS_ENTRY_DURATION);
+                }
+                self.boss_intro = Some(intro);
+            }
+            BossIntroPhase::Entering => {
+                let y = boss_entry_y(intro.progress(&self.clock));
+                let boss = self.enemies.iter_mut().find(|enemy| enemy.kind == EnemyKind::Boss);
+                let Some(boss) = boss else {
+                    self.end_boss_intro();
+                    return;
+                };
+                boss.prev = boss.pos;
+                boss.pos.y = y;
+                if !running {
+                    self.end_boss_intro();
                 }
             }
         }
+    }
+
+    // ボス登場の演出を終え、止めておいた出現間隔の続きから波の進行に戻す
+    fn end_boss_intro(&mut self) {
+        if let Some(intro) = self.boss_intro.take() {
+            self.spawn_timer.start(&self.clock, intro.spawn_remaining);
+        }
+    }
+
+    // 出現待ちを含めてボスがいるかどうか
+    pub fn boss_active(&self) -> bool {
+        self.enemies
+            .iter()
+            .chain(self.pending_spawns.iter().map(|pending| &pending.enemy))
+            .any(|enemy| enemy.kind == EnemyKind::Boss)
+    }
+
+    pub fn update_pending_spawns(&mut self, delta_time: f64) {
+        for pending in &mut self.pending_spawns {
+            pending.remaining -= delta_time;
+        }
+
+        // 予告時間が終わった敵を出現させる
+        let mut i = 0;
+        while i < self.pending_spawns.len() {
+            if self.pending_spawns[i].remaining <= 0.0 {
+                let pending = self.pending_spawns.remove(i);
+                self.enemies.push(pending.enemy);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    pub fn update_enemies(&mut self, delta_time: f64) {
+        let step = self.time_scale();
+        let player_center = self.player.center().into();
+        let boss_entering = self.boss_intro.is_some();
+        for enemy in &mut self.enemies {
+            enemy.hit_flash = (enemy.hit_flash - delta_time).max(0.0);
+            // 登場中のボスは演出に合わせて降ろすだけで、まだ動き回らず攻撃もしない
+            if boss_entering && enemy.kind == EnemyKind::Boss {
+                continue;
+            }
+            enemy.advance(step, delta_time, self.width, player_center);
 
-        // プレイヤーと敵の衝突判定
-        let mut enemies_to_remove_on_collision = Vec::new();
-        for (e_idx, enemy) in self.enemies.iter().enumerate() {
-            if self.player.x < enemy.x + enemy.width
-                && self.player.x + self.player.width > enemy.x
-                && self.player.y < enemy.y + enemy.height
-                && self.player.y + self.player.height > enemy.y
-            {
-                enemies_to_remove_on_collision.push(e_idx);
-                self.lives = self.lives.saturating_sub(1);
+            // 画面内にいる間だけ一定間隔で弾を撃つ
+            if let Some(timer) = enemy.fire_timer.as_mut() {
+                *timer -= delta_time;
+                if *timer <= 0.0 && enemy.pos.y >= 0.0 {
+                    *timer = ENEMY_FIRE_INTERVAL;
+                    self.enemy_bullets.push(Bullet::new_enemy_shot(
+                        enemy.pos.x + enemy.width / 2.0 - 4.0,
+                        enemy.pos.y + enemy.height,
+                    ));
+                }
+            }
 
-                // ダメージ音やエフェクトを追加する場合はここに記述
+            // 弾幕パターンを実行
+            if let Some(runner) = enemy.pattern.as_mut() {
+                if enemy.pos.y >= 0.0 {
+                    runner.update(
+                        delta_time,
+                        (enemy.pos.x + enemy.width / 2.0, enemy.pos.y + enemy.height / 2.0),
+                        player_center,
+                        &mut self.enemy_bullets,
+                    );
+                }
             }
         }
 
-        // 重複削除
-        bullets_to_remove.sort_unstable();
-        bullets_to_remove.dedup();
-        enemies_to_remove.sort_unstable();
-        enemies_to_remove.dedup();
-        enemies_to_remove_on_collision.sort_unstable();
-        enemies_to_remove_on_collision.dedup();
+        // 敵が画面下に到達した場合(特攻した敵は場の外に出た場合)、敵を削除
+        // ボス登場の演出中は、場を空けるために抜けさせているのでライフを減らさない
+        let crossed_bottom = self.cull_enemies();
+        if crossed_bottom > 0 && self.level.escape_costs_life && self.boss_intro.is_none() {
+            self.on_enemies_escaped(crossed_bottom);
+        }
+    }
+
+    // 場から出ていった敵を削除して記録に数え、そのうち画面下端を越えた数を返す
+    fn cull_enemies(&mut self) -> u32 {
+        let (width, height) = (self.width, self.height);
+        let mut crossed_bottom = 0;
+        let count = self.enemies.len();
+        self.enemies.retain(|enemy| {
+            let gone = enemy.has_left_field(width, height);
+            if gone && enemy.pos.y > height {
+                crossed_bottom += 1;
+            }
+            !gone
+        });
+        self.enemies_escaped += (count - self.enemies.len()) as u32;
+        crossed_bottom
+    }
+
+    // 画面下まで抜けた敵の数だけライフを減らす。ゲームオーバーの判定は当たり判定の後で行う
+    fn on_enemies_escaped(&mut self, count: u32) {
+        let lives = self.lives;
+        if !self.mode.unlimited_lives() {
+            self.lives = self.lives.saturating_sub(count);
+        }
+        if self.lives < lives {
+            self.events.push(GameEvent::LifeLost { lives: self.lives });
+        }
+        self.audio.play_clip(ESCAPE_SOUND);
+        self.escape_flash.start(&self.clock, ESCAPE_FLASH_DURATION);
+    }
 
-        // 弾丸と敵を削除
-        for &b_idx in bullets_to_remove.iter().rev() {
-            self.bullets.remove(b_idx);
+    // 当たり判定表に従って、判定するグループの組み合わせごとに処理を呼び出す
+    pub fn check_collisions(&mut self) {
+        let (score, lives, kills) = (self.score, self.lives, self.kills);
+        let boss_phase = self.boss_phase();
+        // 敵を格子に入れ直す。弾は敵から見た相対的な軌跡で判定するので、
+        // 敵は今の矩形をこのフレームの移動分ずらした範囲まで含めて入れる
+        self.enemy_grid.rebuild(
+            self.enemies
+                .iter()
+                .map(|enemy| swept_bounds(enemy.bounds(), enemy.pos - enemy.prev)),
+        );
+        for (index, &a) in CollisionGroup::ALL.iter().enumerate() {
+            for &b in &CollisionGroup::ALL[index..] {
+                if let Some(handler) = collision_handler(a, b) {
+                    handler(self);
+                }
+            }
         }
-        for &e_idx in enemies_to_remove.iter().rev() {
-            self.enemies.remove(e_idx);
+        // 判定の途中では取り除かずに印だけ付け、すべての判定を終えてから1度に取り除く
+        self.enemies.retain(|enemy| !enemy.dead);
+        self.enemy_bullets.retain(|bullet| bullet.active);
+        self.charge_bomb_meter(kills, boss_phase);
+
+        if self.score != score {
+            self.events.push(GameEvent::Score { score: self.score });
         }
-        // プレイヤーと衝突した敵を削除
-        for &e_idx in enemies_to_remove_on_collision.iter().rev() {
-            self.enemies.remove(e_idx);
+        if self.lives < lives {
+            self.events.push(GameEvent::LifeLost { lives: self.lives });
         }
+        self.award_extra_lives();
 
         // ライフが0になったらゲームオーバー
         if self.lives == 0 {
-            self.state = GameState::GameOver;
+            self.end_run();
         }
     }
 
-    pub fn start(game_rc: Rc<RefCell<Self>>) {
-        let closure = Closure::wrap(Box::new(move |timestamp: f64| {
-            {
-                let mut game = game_rc.borrow_mut();
-                if game.state == GameState::Playing {
-                    game.render_frame(timestamp);
-                }
+    // プレイを終えてゲームオーバーにする
+    fn end_run(&mut self) {
+        self.state = GameState::GameOver;
+        self.charge_start = None;
+        // ボス登場の演出や波の変化の途中で終わった場合は打ち切る
+        self.boss_intro = None;
+        self.end_wave_modifier();
+        // 生き残った時間に応じたボーナスを加えてから記録する
+        self.survival_bonus = (self.play_time / SURVIVAL_BONUS_INTERVAL) as u32;
+        if self.survival_bonus > 0 {
+            self.score += self.survival_bonus;
+            self.events.push(GameEvent::Score { score: self.score });
+        }
+        self.previous_best = self.history.lifetime().best_score;
+        self.record_run();
+        self.events.push(GameEvent::GameOver {
+            score: self.score,
+            duration_ms: self.play_time,
+            difficulty: self.difficulty,
+        });
+    }
+
+    // 制限時間のあるモードの残り時間(ms)。制限のないモードではNone
+    pub fn time_remaining(&self) -> Option<f64> {
+        let limit = self.mode.time_limit()?;
+        Some((limit - self.play_time).max(0.0))
+    }
+
+    // 遊び方を変え、そのモードの記録を読み込む
+    pub fn set_mode(&mut self, mode: GameMode) {
+        self.mode = mode;
+        // 別のモードで遊んでいたプレイには戻さない
+        self.reset_stash = None;
+        self.load_history();
+    }
+
+    // 難易度を変え、その難易度の記録を読み込む
+    // 始める前(タイトル画面)に呼ぶ。ライフと最初の波は新しい難易度で作り直す
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        self.lives = self.starting_lives();
+        self.current_wave = self.wave(self.current_wave.number);
+        self.reset_stash = None;
+        self.load_history();
+    }
+
+    // ページから渡された設定を反映する。始める前(タイトル画面)に呼ぶ
+    pub fn set_config(&mut self, config: GameConfig) {
+        let size = config.player_size;
+        self.player.width = size;
+        self.player.height = size;
+        self.player.pos.x = (self.width - size) / 2.0;
+        self.player.pos.y = self.height - size;
+        self.player.speed = config.player_speed;
+        self.player.hurtbox_size = config.hurtbox_size();
+        self.config = config;
+        self.lives = self.starting_lives();
+        self.current_wave = self.wave(self.current_wave.number);
+        self.latch_run_flags();
+    }
+
+    // プレイヤーが左右の端でループするかを切り替える
+    pub fn set_wrap_horizontal(&mut self, enabled: bool) {
+        self.wrap_horizontal = enabled;
+        self.latch_run_flags();
+    }
+
+    // プレイ開始時のライフ。設定で指定されていなければ難易度で決める
+    pub fn starting_lives(&self) -> u32 {
+        self.config
+            .starting_lives
+            .unwrap_or_else(|| self.difficulty.starting_lives())
+    }
+
+    fn load_history(&mut self) {
+        if let Some(store) = &self.run_store {
+            self.history = store.load_runs(self.mode, self.difficulty);
+        }
+    }
+
+    // 矩形(画面端をまたいだ反対側の分を含む)と同じマスにいる敵の番号を小さい順に重複なく返す
+    // 総当たりで判定する設定のときはすべての敵を返す
+    fn enemies_near(&self, (main, wrapped): (Rect, Option<Rect>)) -> Vec<usize> {
+        if self.brute_force_collisions {
+            return (0..self.enemies.len()).collect();
+        }
+        let mut candidates = Vec::new();
+        self.enemy_grid.query(main, &mut candidates);
+        if let Some(wrapped) = wrapped {
+            let mut more = Vec::new();
+            self.enemy_grid.query(wrapped, &mut more);
+            candidates.extend(more);
+            candidates.sort_unstable();
+            candidates.dedup();
+        }
+        candidates
+    }
+
+    // 自機の弾と敵の当たり判定
+    // 弾の軌跡と同じマスにいる敵だけを判定する(格子は check_collisions で作り直す)
+    fn collide_bullets_with_enemies(&mut self) {
+        let mut candidates = Vec::new();
+        for bullet in self.bullets.iter_mut() {
+            if bullet.owner != BulletOwner::Player {
+                continue;
             }
-            // 再度アニメーションフレームを要求
-            if game_rc.borrow().state == GameState::Playing {
-                Game::start(game_rc.clone());
+            if self.brute_force_collisions {
+                candidates.clear();
+                candidates.extend(0..self.enemies.len());
+            } else {
+                let swept = swept_bounds(bullet.bounds(), bullet.prev - bullet.pos);
+                self.enemy_grid.query(swept, &mut candidates);
             }
-        }) as Box<dyn FnMut(f64)>);
+            for &e_idx in &candidates {
+                let enemy = &mut self.enemies[e_idx];
+                // 既に撃破済みの敵は無視
+                if enemy.dead {
+                    continue;
+                }
+
+                // 弾は描いている円として扱う
+                // 高速な弾がすり抜けないよう、このフレームの円の中心の移動軌跡で判定する
+                // 敵も移動しているので、敵から見た相対的な軌跡を使う
+                let offset = bullet.center() - bullet.pos;
+                let start = (bullet.prev + offset - (enemy.pos - enemy.prev)).into();
+                let end = bullet.center().into();
+                let radius = bullet.radius;
+
+                // 弱点を先に判定し、外れたら本体を判定
+                let hit_zone = match enemy.weak_point() {
+                    Some(weak_point) if swept_circle_hits_rect(start, end, radius, weak_point) => {
+                        Some(HitZone::WeakPoint)
+                    }
+                    _ if swept_circle_hits_rect(start, end, radius, enemy.bounds()) => {
+                        Some(HitZone::Body)
+                    }
+                    _ => None,
+                };
+
+                if let Some(hit_zone) = hit_zone {
+                    // 貫通して複数に当たっても、命中数は弾1発につき1回だけ数える
+                    if !bullet.has_hit {
+                        bullet.has_hit = true;
+                        self.shots_hit += 1;
+                    }
+
+                    let multiplier = if hit_zone == HitZone::WeakPoint {
+                        self.crit_hits += 1;
+                        self.crit_effects.push(CritEffect::new(
+                            bullet.pos.x + bullet.radius,
+                            bullet.pos.y + bullet.radius,
+                        ));
+                        WEAK_POINT_MULTIPLIER
+                    } else {
+                        1
+                    };
+                    let damage = bullet.damage * multiplier;
 
-        window()
-            .unwrap()
-            .request_animation_frame(closure.as_ref().unchecked_ref())
-            .expect("requestAnimationFrame failed");
+                    // 体力が尽きた場合のみ撃破してスコアを加算
+                    let destroyed = enemy.take_damage(damage);
+                    if destroyed {
+                        enemy.dead = true;
+                        let awarded = enemy.score_value * self.combo.register_kill(&self.clock);
+                        self.score += awarded;
+                        self.score_popups.push(ScorePopup::new(
+                            enemy.pos.x + enemy.width / 2.0,
+                            enemy.pos.y + enemy.height / 2.0,
+                            awarded,
+                        ));
+                        self.kills += 1;
 
-        closure.forget(); // クロージャをメモリに保持させる
+                        // 一定確率でパワーアップを落とす
+                        if self.rng.next_f64() < POWER_UP_DROP_CHANCE {
+                            let index =
+                                (self.rng.next_f64() * PowerUpKind::ALL.len() as f64) as usize;
+                            let kind = PowerUpKind::ALL[index.min(PowerUpKind::ALL.len() - 1)];
+                            self.power_ups.push(PowerUp::new(
+                                enemy.pos.x + enemy.width / 2.0,
+                                enemy.pos.y + enemy.height / 2.0,
+                                kind,
+                            ));
+                        }
+
+                        // 火花を散らす
+                        spawn_particles(&mut self.particles, &mut self.rng, enemy);
+
+                        // 破片を残す
+                        if !self.reduced_motion {
+                            spawn_debris(&mut self.debris, &mut self.rng, enemy);
+                        }
+
+                        // 敵の種類に合った爆発音を再生
+                        match self.kill_sounds.pick(enemy.kind, &mut self.rng) {
+                            Some(clip) => self.audio.play_clip(clip),
+                            None => self.audio.play_explosion(),
+                        }
+                    }
+
+                    // 貫通弾は倒した敵を突き抜けて次の敵に当たる
+                    // 倒しきれなかった場合と、貫通回数を使い切った場合はそこで消える
+                    if destroyed && bullet.pierce_remaining > 0 {
+                        bullet.pierce_remaining -= 1;
+                        continue;
+                    }
+                    bullet.active = false;
+                    break;
+                }
+            }
+        }
     }
 
-    pub fn render_frame(&mut self, current_time: f64) {
-        // 初回フレームでlast_enemy_spawnを設定
-        if self.last_enemy_spawn == 0.0 {
-            self.last_enemy_spawn = current_time;
+    // レーザーと敵の当たり判定。光線に重なっている敵すべてに同じダメージを与える
+    fn collide_laser_with_enemies(&mut self) {
+        if !self.laser_firing || self.laser_damage < 1.0 {
+            return;
         }
+        let damage = self.laser_damage.floor();
+        self.laser_damage -= damage;
 
-        // フレーム間の経過時間を計算
-        let delta_time = current_time - self.last_frame_time;
-        self.last_frame_time = current_time;
+        let beam = self.laser_rects();
+        for index in self.enemies_near(beam) {
+            let enemy = &mut self.enemies[index];
+            let hit = !enemy.dead && hits_either(beam, enemy.bounds());
+            if !hit || !enemy.take_damage(damage as u32) {
+                continue;
+            }
+            enemy.dead = true;
+            self.reward_kill(index);
+        }
+    }
 
-        // 敵の生成
-        if current_time - self.last_enemy_spawn > self.enemy_spawn_interval {
-            self.spawn_enemy();
-            self.last_enemy_spawn = current_time;
+    // ミサイルと敵の当たり判定。いずれかの敵に触れたミサイルは爆発し、
+    // 爆風の範囲に入っている敵すべてにダメージを与える
+    fn collide_missiles_with_enemies(&mut self) {
+        let mut index = 0;
+        while index < self.missiles.len() {
+            let missile = &self.missiles[index];
+            let center = (missile.x, missile.y);
+            let hit = self
+                .enemies_near(((center.0, center.1, 0.0, 0.0), None))
+                .into_iter()
+                .map(|index| &self.enemies[index])
+                .any(|enemy| !enemy.dead && circle_overlaps_rect(center, 0.0, enemy.bounds()));
+            if !hit {
+                index += 1;
+                continue;
+            }
+            self.missiles.remove(index);
+            self.explode_missile(center);
         }
+    }
 
-        // キー入力に基づいてプレイヤーの移動
-        if self.keys_pressed.contains(&"ArrowLeft".to_string())
-            || self.keys_pressed.contains(&"a".to_string())
-        {
-            self.player.x -= self.player.speed;
-            if self.player.x < 0.0 {
-                self.player.x = 0.0;
+    // 爆風の範囲(敵の矩形の一部でも入っていれば範囲内)の敵にダメージを与える
+    fn explode_missile(&mut self, center: (f64, f64)) {
+        self.blasts.push(Blast::new(center.0, center.1, MISSILE_BLAST_RADIUS));
+        self.audio.play_explosion();
+        let radius = MISSILE_BLAST_RADIUS;
+        let blast = (center.0 - radius, center.1 - radius, radius * 2.0, radius * 2.0);
+        for index in self.enemies_near((blast, None)) {
+            let enemy = &mut self.enemies[index];
+            let in_range =
+                !enemy.dead && circle_overlaps_rect(center, MISSILE_BLAST_RADIUS, enemy.bounds());
+            if !in_range || !enemy.take_damage(MISSILE_DAMAGE) {
+                continue;
             }
+            enemy.dead = true;
+            self.reward_kill(index);
         }
+    }
 
-        if self.keys_pressed.contains(&"ArrowRight".to_string())
-            || self.keys_pressed.contains(&"d".to_string())
-        {
-            self.player.x += self.player.speed;
-            if self.player.x + self.player.width > 800.0 {
-                self.player.x = 800.0 - self.player.width;
+    // 弾以外の手段で倒した敵のスコアと演出
+    // 弾で倒したときと同じくスコアとコンボに数えるが、パワーアップは落とさない
+    fn reward_kill(&mut self, index: usize) {
+        let enemy = &self.enemies[index];
+        let awarded = enemy.score_value * self.combo.register_kill(&self.clock);
+        self.score += awarded;
+        self.kills += 1;
+        self.score_popups.push(ScorePopup::new(
+            enemy.pos.x + enemy.width / 2.0,
+            enemy.pos.y + enemy.height / 2.0,
+            awarded,
+        ));
+        spawn_particles(&mut self.particles, &mut self.rng, enemy);
+        if !self.reduced_motion {
+            spawn_debris(&mut self.debris, &mut self.rng, enemy);
+        }
+        match self.kill_sounds.pick(enemy.kind, &mut self.rng) {
+            Some(clip) => self.audio.play_clip(clip),
+            None => self.audio.play_explosion(),
+        }
+    }
+
+    // プレイヤーと敵の衝突判定
+    fn collide_enemies_with_player(&mut self) {
+        let player_rects = self.player_hurtboxes();
+        let mut shielded = self.shield.is_running(&self.clock);
+        let mut lives = self.lives;
+        let damage = self.level.damage_taken;
+        for index in self.enemies_near(player_rects) {
+            let enemy = &mut self.enemies[index];
+            if !enemy.dead && hits_either(player_rects, enemy.bounds()) {
+                enemy.dead = true;
+                absorb_hit(&mut shielded, &mut lives, damage);
             }
         }
+        self.apply_hits(shielded, lives);
+    }
 
-        if self.keys_pressed.contains(&"ArrowUp".to_string())
-            || self.keys_pressed.contains(&"w".to_string())
-        {
-            self.player.y -= self.player.speed;
-            if self.player.y < 0.0 {
-                self.player.y = 0.0;
+    // 敵の弾とプレイヤーの衝突判定
+    fn collide_enemy_bullets_with_player(&mut self) {
+        let player_rects = self.player_hurtboxes();
+        let mut shielded = self.shield.is_running(&self.clock);
+        let mut lives = self.lives;
+        let multiplier = self.level.damage_taken;
+        for bullet in self.enemy_bullets.iter_mut().filter(|bullet| bullet.active) {
+            let (main, wrapped) = player_rects;
+            let center = bullet.center().into();
+            let hit = bullet.owner == BulletOwner::Enemy
+                && std::iter::once(main)
+                    .chain(wrapped)
+                    .any(|rect| circle_overlaps_rect(center, bullet.radius, rect));
+            if hit {
+                bullet.active = false;
+                absorb_hit(&mut shielded, &mut lives, bullet.damage * multiplier);
             }
         }
+        self.apply_hits(shielded, lives);
+    }
 
-        if self.keys_pressed.contains(&"ArrowDown".to_string())
-            || self.keys_pressed.contains(&"s".to_string())
-        {
-            self.player.y += self.player.speed;
-            if self.player.y + self.player.height > 600.0 {
-                self.player.y = 600.0 - self.player.height;
+    // スコアが節目を越えるたびにライフを1つ増やす(上限を超えた分は増やさない)
+    // 1フレームで複数の節目を越えた場合はその数だけ増やす
+    fn award_extra_lives(&mut self) {
+        let mut awarded = false;
+        while self.score >= self.next_life_at {
+            self.next_life_at += self.level.extra_life_interval();
+            if self.lives < self.level.max_lives {
+                self.lives += 1;
+                awarded = true;
             }
         }
+        if awarded {
+            self.life_flash.start(&self.clock, LIFE_FLASH_DURATION);
+            self.audio.play_clip(EXTRA_LIFE_SOUND);
+        }
+    }
 
-        // 弾丸の位置を更新
-        self.bullets.iter_mut().for_each(|bullet| {
-            bullet.y -= bullet.speed;
-        });
+    // ボムを1つ使い、画面内の敵と敵の弾をすべて消す。ボムがなければ何もしない
+    // 倒した敵のスコアは半分(切り上げ)で、コンボには数えない
+    // ボスは一度に倒れないよう、一定のダメージを与えるだけにする
+    fn use_bomb(&mut self) {
+        if self.bombs == 0 {
+            return;
+        }
+        self.bombs -= 1;
+        let score = self.score;
 
-        // 弾丸が画面外に出た場合、弾丸を削除
-        self.bullets.retain(|bullet| bullet.y >= 0.0);
+        let height = self.height;
+        let mut index = 0;
+        while index < self.enemies.len() {
+            let enemy = &mut self.enemies[index];
+            let on_screen = enemy.pos.y + enemy.height >= 0.0 && enemy.pos.y <= height;
+            let damage = if enemy.kind == EnemyKind::Boss {
+                BOMB_BOSS_DAMAGE
+            } else {
+                enemy.hp
+            };
+            if !on_screen || !enemy.take_damage(damage) {
+                index += 1;
+                continue;
+            }
+            let enemy = self.enemies.remove(index);
+            let awarded = enemy.score_value.div_ceil(2);
+            self.score += awarded;
+            self.kills += 1;
+            self.score_popups.push(ScorePopup::new(
+                enemy.pos.x + enemy.width / 2.0,
+                enemy.pos.y + enemy.height / 2.0,
+                awarded,
+            ));
+            spawn_particles(&mut self.particles, &mut self.rng, &enemy);
+            if !self.reduced_motion {
+                spawn_debris(&mut self.debris, &mut self.rng, &enemy);
+            }
+        }
+        self.enemy_bullets.clear();
 
-        // 敵の位置を更新
-        self.update_enemies(delta_time);
+        self.audio.play_explosion();
+        if !self.reduced_motion {
+            self.bomb_flash.start(&self.clock, BOMB_FLASH_DURATION);
+        }
+        if self.score != score {
+            self.events.push(GameEvent::Score { score: self.score });
+            self.award_extra_lives();
+        }
+    }
 
-        // 衝突判定
-        self.check_collisions();
+    // 削ったボスの体力の段階。ボスがいなければNone
+    fn boss_phase(&self) -> Option<u32> {
+        let phases = self.level.bomb_meter.boss_phases;
+        self.enemies
+            .iter()
+            .find(|enemy| enemy.kind == EnemyKind::Boss)
+            .map(|boss| boss_phase(boss.hp, boss.max_hp, phases))
+    }
 
-        // Canvasをクリア
-        self.context.clear_rect(0.0, 0.0, 800.0, 600.0);
+    // 当たり判定で倒した数と、削ったボスの体力の段階に応じてボムのメーターを溜める
+    // 判定の前にいたボスがいなくなっていれば、倒したので最後の段階まで削ったことにする
+    // ボムで倒した分は溜めない(ボムでボムを溜め続けられないようにする)
+    fn charge_bomb_meter(&mut self, kills_before: u32, boss_phase_before: Option<u32>) {
+        let def = self.level.bomb_meter;
+        let phases = match (boss_phase_before, self.boss_phase()) {
+            (Some(before), Some(after)) => after.saturating_sub(before),
+            (Some(before), None) => def.boss_phases.saturating_sub(before),
+            (None, _) => 0,
+        };
+        let amount = (self.kills - kills_before) * def.per_kill + phases * def.per_boss_phase;
+        if amount == 0 {
+            return;
+        }
+        let room = MAX_BOMBS.saturating_sub(self.bombs);
+        let granted = self.bomb_meter.fill(amount, room, &self.clock);
+        if granted > 0 {
+            self.bombs += granted;
+            self.audio.play_clip(BOMB_READY_SOUND);
+        }
+    }
 
-        // 背景画像を描画
-        if let Err(e) = self.context.draw_image_with_html_image_element(
-            &self.background_image,
-            0.0,
-            0.0,
-        ) {
-            console_log!("Error drawing background: {:?}", e);
+    // 当たり判定の結果を反映する。shieldedがfalseになっていればシールドが被弾を防いだ
+    fn apply_hits(&mut self, shielded: bool, lives: u32) {
+        if !shielded && self.shield.is_running(&self.clock) {
+            self.shield.clear();
+            self.audio.play_clip(SHIELD_BREAK_SOUND);
         }
+        if lives < self.lives {
+            self.on_player_hit();
+        }
+        // ライフの減らないモードでも、被弾したときの揺れなどは起こす
+        if !self.mode.unlimited_lives() {
+            self.lives = lives;
+        }
+    }
 
-        // プレイヤーを描画
-        if let Err(e) = self.context.draw_image_with_html_image_element(
-            &self.player.image,
-            self.player.x,
-            self.player.y,
-        ) {
-            console_log!("Error drawing player: {:?}", e);
+    // 被弾したときの処理
+    fn on_player_hit(&mut self) {
+        self.hits_taken += 1;
+        self.combo.reset();
+        if self.level.bomb_meter.reset_on_death {
+            self.bomb_meter.reset();
         }
+        // 被弾すると武器が1段階弱くなる
+        self.weapon_level = self.weapon_level.saturating_sub(1).max(1);
+        self.spawn_guard.start(&self.clock, SPAWN_GUARD_DURATION);
+        self.start_shake();
+    }
 
-        // 弾丸を描画
-        for bullet in &self.bullets {
-            self.context.begin_path();
-            if let Err(e) = self.context.arc(
-                bullet.x + bullet.radius,
-                bullet.y + bullet.radius,
-                bullet.radius,
-                0.0,
-                PI * 2.0,
-            ) {
-                console_log!("Error drawing arc: {:?}", e);
-            }
-            self.context.set_fill_style(&JsValue::from_str(&bullet.color));
-            self.context.fill();
+    // 被弾したときに画面を揺らす
+    fn start_shake(&mut self) {
+        if self.reduced_motion {
+            return;
         }
+        self.shake.start(&self.clock, SHAKE_DURATION);
+        self.shake_magnitude = SHAKE_MAGNITUDE;
+    }
 
-        // 敵を描画
-        for enemy in &self.enemies {
-            if let Err(e) = self.context.draw_image_with_html_image_element(
-                &enemy.image,
-                enemy.x,
-                enemy.y,
-            ) {
-                console_log!("Error drawing enemy: {:?}", e);
+    // プレイヤーに触れたパワーアップを取得
+    fn collect_power_ups(&mut self) {
+        let mut collected = Vec::new();
+        let player_rects = self.player_rects();
+        self.power_ups.retain(|power_up| {
+            let hit = hits_either(
+                player_rects,
+                (power_up.x, power_up.y, power_up.width, power_up.height),
+            );
+            if hit {
+                collected.push(power_up.kind);
             }
+            !hit
+        });
+
+        for kind in collected {
+            self.apply_power_up(kind);
         }
+    }
 
-        // スコアを更新
-        self.update_ui();
+    pub fn update_power_ups(&mut self) {
+        let step = self.time_scale();
+        for power_up in &mut self.power_ups {
+            power_up.y += power_up.speed * step;
+        }
+
+        // 画面外に出たものを削除
+        let height = self.height;
+        self.power_ups.retain(|power_up| power_up.y <= height);
     }
 
-    pub fn update_ui(&self) {
-        // スコアをHTML要素に反映
-        let window = window().expect("no global `window` exists");
-        let document = window.document().expect("should have a document on window");
-
-        let score_element = document
-            .get_element_by_id("score")
-            .expect("should have score element");
-        score_element.set_inner_html(&self.score.to_string());
-
-        // ライフをHTML要素に反映
-        let lives_element = document
-            .get_element_by_id("lives")
-            .expect("should have lives element");
-        lives_element.set_inner_html(&self.lives.to_string());
-
-        // ゲームオーバー時の処理
-        let game_over_element = document.get_element_by_id("gameOver");
-        if self.state == GameState::GameOver {
-            if let Some(element) = game_over_element {
-                element
-                    .dyn_ref::<HtmlElement>()
-                    .unwrap()
-                    .style()
-                    .set_property("display", "block")
-                    .unwrap();
-            }
+    // プレイヤーの当たり判定の矩形(画像の大きさ)
+    // 左右ループ中に画面端をまたいでいる場合は反対側の矩形も返す
+    pub fn player_rects(&self) -> (Rect, Option<Rect>) {
+        self.with_wrapped_copy(self.player.bounds())
+    }
+
+    // プレイヤーの被弾判定の矩形。画面端をまたいでいる場合は反対側の矩形も返す
+    pub fn player_hurtboxes(&self) -> (Rect, Option<Rect>) {
+        self.with_wrapped_copy(self.player.hurtbox())
+    }
+
+    fn with_wrapped_copy(&self, rect: Rect) -> (Rect, Option<Rect>) {
+        let wrapped = if self.wrap_horizontal {
+            wrapped_copy(rect, self.width)
         } else {
-            // ゲームオーバーでない場合、非表示にする
-            if let Some(element) = game_over_element {
-                element
-                    .dyn_ref::<HtmlElement>()
-                    .unwrap()
-                    .style()
-                    .set_property("display", "none")
-                    .unwrap();
-            }
-        }
-    }
-
-    pub fn reset(&mut self) {
-        self.player.x = 300.0;
-        self.player.y = 550.0;
-        self.bullets.clear();
-        self.enemies.clear();
-        self.last_enemy_spawn = 0.0;
-        self.score = 0;
-        self.lives = 3; // ライフの初期化
-        self.state = GameState::Playing;
-        self.keys_pressed.clear();
+            None
+        };
+        (rect, wrapped)
+    }
+
+    // 場にいる敵の数(予告表示中の出現待ちを含む)
+    pub fn enemy_count(&self) -> usize {
+        self.enemies.len() + self.pending_spawns.len()
+    }
+
+    // 次の出現を編隊にするかどうかと、その形。roomは今出せる敵の数
+    // 編隊の人数分の余裕があるときだけ、数波ごとの最初の出現と、2波目以降のまれな抽選で編隊にする
+    fn roll_formation(&mut self, room: usize) -> Option<Formation> {
+        if room < FORMATION_SIZE {
+            return None;
+        }
+        let number = self.current_wave.number;
+        let due = self.wave_spawned == 0 && number.is_multiple_of(FORMATION_WAVE_INTERVAL);
+        if !due && (number < 2 || self.rng.next_f64() >= FORMATION_CHANCE) {
+            return None;
+        }
+        Some(if self.rng.next_f64() < 0.5 {
+            Formation::Row
+        } else {
+            Formation::Vee
+        })
+    }
+
+    // 自機の先端から追尾ミサイルを撃つ。再使用までの待ち時間中は撃てない
+    fn fire_missile(&mut self) {
+        if self.missile_cooldown.is_running(&self.clock) {
+            return;
+        }
+        let x = self.player.pos.x + self.player.width / 2.0;
+        self.missiles.push(Missile::new(x, self.player.pos.y));
+        self.missile_cooldown.start(&self.clock, MISSILE_COOLDOWN);
+        self.audio.play_shot();
+    }
 
-        // ゲームオーバー表示を非表示にする
-        let window = window().expect("no global `window` exists");
-        let document = window.document().expect("should have a document on window");
-        let game_over_element = document.get_element_by_id("gameOver");
-        if let Some(element) = game_over_element {
-            element
-                .dyn_ref::<HtmlElement>()
-                .unwrap()
-                .style()
-                .set_property("display", "none")
-                .unwrap();
+    // 各ミサイルを最も近い敵へ向けて進める。時間切れになったものは何も壊さずに消える
+    fn update_missiles(&mut self, delta_time: f64, step: f64) {
+        for missile in &mut self.missiles {
+            let target = nearest_enemy(&self.enemies, (missile.x, missile.y));
+            missile.update(target, delta_time, step);
         }
+        self.missiles.retain(|missile| !missile.is_expired());
+    }
+
+    // レーザーの照射範囲。自機の中央から場の上端までの縦長の矩形
+    // 自機が画面端をまたいでいる場合は反対側の分も返す
+    pub fn laser_rects(&self) -> (Rect, Option<Rect>) {
+        let x = self.player.pos.x + (self.player.width - LASER_WIDTH) / 2.0;
+        let rect = (x, 0.0, LASER_WIDTH, self.player.pos.y);
+        let wrapped = if self.wrap_horizontal {
+            wrapped_copy(rect, self.width)
+        } else {
+            None
+        };
+        (rect, wrapped)
+    }
+
+    // レーザーを照射中か。一時停止中やゲームオーバー後は照射しない
+    pub fn laser_active(&self) -> bool {
+        self.laser_firing && self.state == GameState::Playing && !self.paused
+    }
+
+    // キーを押している間はエネルギーを使って照射し、離している間は回復する
+    // 尽きた後は一定量まで回復するまで照射を始められない
+    fn update_laser(&mut self, delta_time: f64) {
+        let required = if self.laser_firing { 0.0 } else { LASER_MIN_ENERGY };
+        self.laser_firing =
+            self.is_pressed(InputAction::Laser) && self.laser_energy > required;
+        if self.laser_firing {
+            self.laser_energy = (self.laser_energy - delta_time / LASER_DRAIN_TIME).max(0.0);
+            self.laser_damage += LASER_DAMAGE_PER_SECOND * delta_time / 1000.0;
+        } else {
+            self.laser_energy = (self.laser_energy + delta_time / LASER_RECHARGE_TIME).min(1.0);
+            self.laser_damage = 0.0;
+        }
+    }
+
+    // 実時間に対するゲーム内時間の進む速さ
+    pub fn time_scale(&self) -> f64 {
+        self.assist_speed
+    }
+
+    // アシスト設定のゲーム速度を変更する。等速未満にしたプレイはアシスト扱いになる
+    pub fn set_game_speed(&mut self, speed: f64) {
+        let speed = speed.clamp(MIN_GAME_SPEED, MAX_GAME_SPEED);
+        self.assist_speed = speed;
+        self.latch_run_flags();
+    }
+
+    // 敵の出方やスコアの調整値を差し替える
+    // 進行中の波は同じ番号のまま作り直し、次のボスとライフは新しい間隔の次の節目にする
+    // 波に掛けている変化は、差し替えた調整値に掛け直す
+    pub fn set_level_config(&mut self, level: LevelConfig) {
+        let interval = level.boss_interval();
+        self.next_boss_score = (self.score / interval + 1) * interval;
+        let interval = level.extra_life_interval();
+        self.next_life_at = (self.score / interval + 1) * interval;
+        let modifier = self.wave_modifier.take().map(|active| active.modifier);
+        self.level = level;
+        self.current_wave = self.wave(self.current_wave.number);
+        self.wave_modifier = modifier.map(|modifier| {
+            ActiveModifier::apply(modifier, &mut self.level, &mut self.current_wave)
+        });
+    }
+
+    // number番目(1から)の波。敵の速さや出る間隔は難易度に合わせる
+    // 設定で出す間隔が指定されていれば、最初の波の間隔と下限をそれに合わせる
+    pub fn wave(&self, number: u32) -> Wave {
+        let mut waves = self.level.waves;
+        if let Some(interval) = self.config.spawn_interval {
+            waves.spawn_interval = interval;
+            waves.min_spawn_interval = waves.min_spawn_interval.min(interval);
+        }
+        self.difficulty.waves(&waves).wave(number)
+    }
+
+    pub fn set_volume(&mut self, volume: f64) {
+        self.master_volume = volume.clamp(0.0, 1.0);
+        self.apply_volume();
+    }
+
+    // 消音を切り替え、切り替えた後に消音中ならtrueを返す
+    pub fn toggle_mute(&mut self) -> bool {
+        self.muted = !self.muted;
+        self.apply_volume();
+        self.muted
+    }
+
+    pub fn set_music_volume(&mut self, volume: f64) {
+        self.music_volume = volume.clamp(0.0, 1.0);
+        self.apply_volume();
+    }
+
+    fn apply_volume(&self) {
+        let mute = if self.muted { 0.0 } else { 1.0 };
+        self.audio.set_volume(self.master_volume * mute);
+        self.audio.set_music_volume(self.music_volume * mute);
+    }
+
+    // プレイ中だけBGMを流す
+    // プレイはキー操作でしか始まらないので、自動再生の制限で止められることはない
+    fn sync_music(&mut self) {
+        let playing = self.state == GameState::Playing && !self.paused;
+        if playing != self.music_playing {
+            self.music_playing = playing;
+            self.audio.set_music_playing(playing);
+        }
+    }
+
+    // レーザーの音は照射している間だけ流す
+    fn sync_laser_sound(&mut self) {
+        let playing = self.laser_active();
+        if playing != self.laser_playing {
+            self.laser_playing = playing;
+            self.audio.set_laser_playing(playing);
+        }
+    }
+
+    pub fn apply_power_up(&mut self, kind: PowerUpKind) {
+        match kind {
+            PowerUpKind::ScoreBonus => {
+                self.score += SCORE_BONUS;
+                self.award_extra_lives();
+            }
+            PowerUpKind::SpeedBoost => {
+                self.speed_boost.start(&self.clock, SPEED_BOOST_DURATION);
+            }
+            PowerUpKind::Ricochet => {
+                self.ricochet.start(&self.clock, RICOCHET_DURATION);
+            }
+            PowerUpKind::Bomb => self.bombs = (self.bombs + 1).min(MAX_BOMBS),
+            PowerUpKind::Shield => self.shield.start(&self.clock, SHIELD_DURATION),
+            PowerUpKind::Pierce => self.pierce.start(&self.clock, PIERCE_DURATION),
+            PowerUpKind::WeaponUp => {
+                self.weapon_level = (self.weapon_level + 1).min(MAX_WEAPON_LEVEL);
+            }
+        }
+    }
+
+    // 効果を反映したプレイヤーの移動速度
+    pub fn player_speed(&self) -> f64 {
+        let speed = self.player.speed * self.difficulty.player_speed_scale();
+        if self.speed_boost.is_running(&self.clock) {
+            speed * SPEED_BOOST_MULTIPLIER
+        } else {
+            speed
+        }
+    }
+
+    pub fn render_frame(&mut self, current_time: f64) {
+        // 初回フレームは経過時間を0として扱う
+        if self.last_frame_time == 0.0 {
+            self.last_frame_time = current_time;
+        }
+
+        // フレーム間の経過時間を計算
+        // 処理が止まっていた後に一度に時間が進みすぎないよう上限を設ける
+        let frame_time = current_time - self.last_frame_time;
+        let real_delta = frame_time.min(MAX_FRAME_DELTA);
+        self.last_frame_time = current_time;
+        self.frame_stats.record(frame_time);
+
+        // 状態ごとに更新と描画を切り替える
+        match self.state {
+            GameState::Menu => {
+                self.expire_reset_stash(current_time);
+                self.draw_menu();
+            }
+            GameState::Stats => self.draw_stats(),
+            GameState::Playing => {
+                // 一時停止中は時間を進めない。カウントダウン中は場を止めたまま数える
+                if !self.paused {
+                    if self.countdown > 0.0 {
+                        self.countdown = (self.countdown - real_delta).max(0.0);
+                        self.expire_reset_stash(current_time);
+                        // 新しいプレイが動き出したらリセットは取り消せない
+                        if self.countdown == 0.0 {
+                            self.reset_stash = None;
+                        }
+                    } else {
+                        self.update(real_delta);
+                    }
+                }
+                self.draw();
+                if self.paused {
+                    self.draw_paused();
+                } else if self.countdown > 0.0 {
+                    self.draw_countdown();
+                }
+            }
+            GameState::LevelComplete => {
+                self.draw();
+                self.draw_level_complete();
+            }
+            GameState::Spectating => self.render_spectate(real_delta),
+            // 最後の場面の上にゲームオーバーの表示を重ねる
+            GameState::GameOver => {
+                self.draw();
+                self.draw_game_over();
+            }
+        }
+
+        if self.debug_overlay {
+            self.draw_debug_overlay();
+        }
+
+        self.sync_music();
+        self.sync_laser_sound();
+
+        // スコアを更新
+        self.update_ui();
+    }
+
+    // 実時間の経過量(ms)を受け取ってゲームを1フレーム進める
+    // DOMに触れないので、ブラウザ以外でも実行できる
+    pub fn update(&mut self, real_delta: f64) {
+        // ゲーム内時間を進める。以降の時間経過はすべてゲーム内時間で扱う
+        let delta_time = self.clock.advance(real_delta * self.time_scale());
+        // 経過時間の表示は実時間で数える。一時停止中はここに来ないので数えない
+        self.play_time += real_delta;
+        self.latch_run_flags();
+
+        // 敵の生成
+        self.update_waves();
+
+        // 背景を前進方向へ流す
+        self.background_offset_y =
+            (self.background_offset_y + BACKGROUND_SCROLL_SPEED * delta_time) % self.height;
+        self.starfield_offset_y =
+            (self.starfield_offset_y + STARFIELD_SCROLL_SPEED * delta_time) % self.height;
+
+        // 1フレームあたりの移動量はゲーム速度に合わせて拡縮する
+        let step = self.time_scale();
+
+        // キー入力に基づいてプレイヤーの移動
+        let speed = self.player_speed() * step;
+        let (dx, dy) = move_direction(
+            self.is_pressed(InputAction::MoveLeft),
+            self.is_pressed(InputAction::MoveRight),
+            self.is_pressed(InputAction::MoveUp),
+            self.is_pressed(InputAction::MoveDown),
+        );
+        self.player.pos += Vec2::new(dx, dy).scale(speed);
+
+        if self.wrap_horizontal {
+            // 端から出たら反対側の端から入る
+            if self.player.pos.x < 0.0 {
+                self.player.pos.x += self.width;
+            } else if self.player.pos.x >= self.width {
+                self.player.pos.x -= self.width;
+            }
+        } else {
+            self.player.pos.x = self.player.pos.x.clamp(0.0, self.width - self.player.width);
+        }
+        self.player.pos.y = self.player.pos.y.clamp(0.0, self.height - self.player.height);
+
+        // レーザーの照射とエネルギーを更新
+        self.update_laser(delta_time);
+
+        // 弾丸の位置を更新
+        self.bullets.iter_mut().for_each(|bullet| bullet.advance(step));
+
+        // 跳弾が有効な間は、まだ跳ね返っていない弾を画面上端で跳ね返す
+        if self.ricochet.is_running(&self.clock) {
+            for bullet in self.bullets.iter_mut() {
+                if bullet.pos.y < 0.0 && !bullet.bounced {
+                    bullet.bounce();
+                }
+            }
+        }
+
+        // 弾丸が画面外に出た場合、枠を空ける
+        // 何にも当たらずに出ていった弾は外したものとしてコンボを切る
+        let (width, height) = (self.width, self.height);
+        let mut missed = false;
+        for bullet in self.bullets.iter_mut() {
+            if bullet.is_outside(width, height) {
+                bullet.active = false;
+                missed |= !bullet.has_hit;
+            }
+        }
+        if missed {
+            self.combo.reset();
+        }
+        self.combo.update(&self.clock);
+
+        // 敵の弾の位置を更新し、画面外に出たものを削除
+        self.enemy_bullets.iter_mut().for_each(|bullet| bullet.advance(step));
+        self.enemy_bullets.retain(|bullet| !bullet.is_outside(width, height));
+
+        // クリティカルヒット演出を更新
+        for effect in &mut self.crit_effects {
+            effect.remaining -= delta_time;
+        }
+        self.crit_effects.retain(|effect| effect.remaining > 0.0);
+
+        // 獲得スコアの表示を更新
+        for popup in &mut self.score_popups {
+            popup.remaining -= delta_time;
+        }
+        self.score_popups.retain(|popup| popup.remaining > 0.0);
+
+        // 爆風を更新
+        for blast in &mut self.blasts {
+            blast.remaining -= delta_time;
+        }
+        self.blasts.retain(|blast| blast.remaining > 0.0);
+
+        // 火花を更新
+        for particle in &mut self.particles {
+            particle.update(delta_time);
+        }
+        self.particles.retain(|particle| particle.is_alive());
+
+        // 破片を更新
+        self.debris.update(delta_time, step, self.height);
+
+        // 出現待ちの敵を更新
+        self.update_pending_spawns(delta_time);
+
+        // 敵の位置を更新
+        self.update_enemies(delta_time);
+
+        // ミサイルを敵へ向けて進め、時間切れのものは自爆させる
+        self.update_missiles(delta_time, step);
+
+        // 衝突判定
+        self.check_collisions();
+
+        // 制限時間のあるモードは、時間切れでその時点のスコアを最終結果にする
+        if let Some(limit) = self.mode.time_limit() {
+            if self.state == GameState::Playing && self.play_time >= limit {
+                self.play_time = limit;
+                self.end_run();
+            }
+        }
+
+        // パワーアップの移動と取得
+        self.update_power_ups();
+
+        // 直近の場面を記録し、ゲームオーバーになったら再生を始める
+        self.replay.record(
+            self.clock.now(),
+            &self.player,
+            &self.enemies,
+            &self.bullets,
+            &self.enemy_bullets,
+        );
+        // 動きを抑える設定のときと画面のない実行では再生せず、そのまま結果画面にする
+        if self.state == GameState::GameOver && self.replays_final_moments() {
+            self.state = GameState::Spectating;
+            self.spectate_time = self.replay.start_time();
+        }
+    }
+
+    // 今後のレベル進行処理から呼び出し、レベルクリア画面に切り替える
+    #[allow(dead_code)]
+    pub fn complete_level(&mut self) {
+        if self.state == GameState::Playing {
+            self.state = GameState::LevelComplete;
+            self.keys_pressed.clear();
+            self.charge_start = None;
+        }
+    }
+
+    // 終わったプレイの結果を記録に加えて保存する
+    fn record_run(&mut self) {
+        self.history.push(RunSummary {
+            score: self.score,
+            kills: self.kills,
+            enemies_escaped: self.enemies_escaped,
+            shots_fired: self.shots_fired,
+            shots_hit: self.shots_hit,
+            duration_ms: self.play_time,
+            difficulty: self.difficulty,
+            flags: self.run_flags,
+            modifier_waves: self.modifier_waves,
+        });
+        if let Some(store) = &self.run_store {
+            store.save_runs(self.mode, self.difficulty, &self.history);
+        }
+    }
+
+    // ゲームオーバーになったプレイの結果。プレイ中やリセット後はNone
+    pub fn finished_run(&self) -> Option<&RunSummary> {
+        matches!(self.state, GameState::Spectating | GameState::GameOver)
+            .then(|| self.history.runs.last())
+            .flatten()
+    }
+
+    // ゲームオーバーになったプレイのまとめ。プレイ中やリセット後はNone
+    // 公正さに影響する設定を使ったプレイは最高スコアを更新しない
+    pub fn last_run_result(&self) -> Option<RunResult> {
+        let run = self.finished_run()?;
+        let new_best = run.flags.is_ranked() && run.score > self.previous_best;
+        Some(RunResult {
+            score: run.score,
+            high_score: if new_best { run.score } else { self.previous_best },
+            new_best,
+            best_combo: self.combo.best,
+            accuracy: run.accuracy(),
+            kills: run.kills,
+            duration_ms: run.duration_ms,
+        })
+    }
+
+    // タブが隠れたりウィンドウが選択されなくなったときに一時停止する
+    // キーを離したことは伝わらないので、押されているキーも忘れる
+    pub fn suspend(&mut self) {
+        if self.state == GameState::Playing {
+            self.paused = true;
+        }
+        self.keys_pressed.clear();
+        self.charge_start = None;
+    }
+
+    // 操作に割り当てるキーを変え、タイトル画面の説明を作り直す
+    pub fn rebind_key(&mut self, action: InputAction, key: &str) {
+        self.key_bindings.rebind(action, key);
+        self.key_hints = self.key_bindings.menu_hints();
+    }
+
+    // タイトル画面からプレイを始める
+    pub fn begin_run(&mut self) {
+        if self.state != GameState::Menu {
+            return;
+        }
+        // リセットの取り消しはカウントダウンが終わるまで受け付ける
+        self.state = GameState::Playing;
+        // 練習モードのプレイは自己ベストやランキングの対象にしない
+        self.run_flags.practice |= self.mode == GameMode::Practice;
+        self.keys_pressed.clear();
+        // タイトル画面にいた時間を最初のフレームで経過させない
+        self.last_frame_time = 0.0;
+        self.countdown = COUNTDOWN_STEP * COUNTDOWN_LABELS.len() as f64;
+    }
+
+    // プレイ中に一時停止を切り替え、切り替わったかどうかを返す
+    // 一時停止中はゲーム内時間が進まないので、再開時に時刻を合わせ直す必要はない
+    pub fn set_paused(&mut self, paused: bool) -> bool {
+        if self.state != GameState::Playing || self.paused == paused {
+            return false;
+        }
+        self.paused = paused;
+        self.keys_pressed.clear();
+        self.charge_start = None;
+        true
+    }
+
+    // 戻ってきたときは最初のフレームとして扱い、離れていた時間を経過させない
+    pub fn resume_timing(&mut self) {
+        self.last_frame_time = 0.0;
+    }
+
+    // ゲームオーバー直前の場面を再生するか
+    pub fn replays_final_moments(&self) -> bool {
+        !self.reduced_motion && !self.headless && !self.replay.is_empty()
+    }
+
+    pub fn finish_spectate(&mut self) {
+        self.state = GameState::GameOver;
+    }
+
+    pub fn update_ui(&self) {
+        self.renderer
+            .update_hud(self.score, self.lives, self.state == GameState::GameOver);
+    }
+
+    // now は現在時刻(requestAnimationFrame の時刻と同じ基準、ms)で、取り消しの期限に使う
+    // (最後のフレームの時刻は再開直後などに0へ戻されているため使わない)
+    pub fn reset(&mut self, now: f64) {
+        // 遊んでいる途中のプレイは、誤操作に備えてしばらく取り消せるように残す
+        let in_progress = matches!(self.state, GameState::Playing | GameState::LevelComplete);
+        // プレイごとに新しいシードを記録し、送信した記録を再現できるようにする
+        let seed = self.rng.next_u64();
+        self.reset_stash = if in_progress {
+            self.stash_run(now + UNDO_RESET_WINDOW)
+        } else {
+            None
+        };
+        self.start_fresh_run(seed);
+        // 次のプレイではBGMを最初から流す
+        self.audio.restart_music();
+        self.music_playing = false;
+        self.shake.clear();
+        // リセットは出来事として知らせない
+        self.events.clear();
+        self.latch_run_flags();
+        self.last_frame_time = 0.0;
+        self.state = GameState::Menu;
+        self.keys_pressed.clear();
+        self.paused = false;
+    }
+}
+
+// 1回の被弾を処理する。シールドがあればライフの代わりにシールドを消す
+fn absorb_hit(shielded: &mut bool, lives: &mut u32, damage: u32) {
+    if *shielded {
+        *shielded = false;
+    } else {
+        *lives = lives.saturating_sub(damage);
+    }
+}
+
+// 射撃キーを押していた時間(ms)から溜め撃ちの強さを求める。短ければ通常の弾(None)
+fn charge_power(held: f64) -> Option<f64> {
+    (held >= CHARGE_MIN_TIME)
+        .then(|| ((held - CHARGE_MIN_TIME) / (CHARGE_MAX_TIME - CHARGE_MIN_TIME)).min(1.0))
+}
+
+// 矩形 rect と、それを shift だけずらした矩形の両方を囲む矩形
+fn swept_bounds(rect: Rect, shift: Vec2) -> Rect {
+    rect_union(rect, (rect.0 + shift.x, rect.1 + shift.y, rect.2, rect.3))
+}
+
+// 矩形が、画面端をまたいだ反対側の分を含めた当たり判定のいずれかと重なっているかどうか
+fn hits_either(rects: (Rect, Option<Rect>), rect: Rect) -> bool {
+    let (main, wrapped) = rects;
+    rects_overlap(main, rect) || wrapped.is_some_and(|wrapped| rects_overlap(wrapped, rect))
+}
+
+// 撃破された敵の中心から10〜20個の火花を外向きに飛ばす
+fn spawn_particles(particles: &mut Vec<Particle>, rng: &mut Rng, enemy: &Enemy) {
+    let count = 10 + (rng.next_f64() * 11.0) as usize;
+    let center_x = enemy.pos.x + enemy.width / 2.0;
+    let center_y = enemy.pos.y + enemy.height / 2.0;
+    for _ in 0..count.min(MAX_PARTICLES - particles.len()) {
+        let angle = rng.next_f64() * std::f64::consts::PI * 2.0;
+        let speed = 0.05 + rng.next_f64() * 0.2;
+        let color = PARTICLE_COLORS[(rng.next_f64() * PARTICLE_COLORS.len() as f64) as usize];
+        particles.push(Particle {
+            x: center_x,
+            y: center_y,
+            vx: angle.cos() * speed,
+            vy: angle.sin() * speed,
+            life: PARTICLE_LIFETIME,
+            color,
+        });
+    }
+}
+
+// 撃破された敵の位置に2〜3個の破片を飛ばす
+fn spawn_debris(pool: &mut DebrisPool, rng: &mut Rng, enemy: &Enemy) {
+    let count = 2 + (rng.next_f64() * 2.0) as usize;
+    for _ in 0..count {
+        pool.spawn(Debris {
+            x: enemy.pos.x + enemy.width * (0.25 + rng.next_f64() * 0.5),
+            y: enemy.pos.y + enemy.height * (0.25 + rng.next_f64() * 0.5),
+            vx: (rng.next_f64() - 0.5) * 2.0,
+            vy: 0.5 + rng.next_f64() * 1.5,
+            angle: rng.next_f64() * std::f64::consts::PI * 2.0,
+            spin: (rng.next_f64() - 0.5) * 0.3,
+            size: 8.0 + rng.next_f64() * 8.0,
+            life: DEBRIS_LIFETIME,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::enemy::BOSS_HOLD_Y;
+    use crate::game::level::WaveModifierDef;
+    use crate::game::WaveModifier;
+    use crate::game::render::mock::{headless_game, MockAudio, MockRenderer, FIELD_SIZE};
+
+    // 1フレーム分の実時間(ms)
+    const FRAME: f64 = 1000.0 / 60.0;
+
+    // カウントダウンを飛ばしてプレイを始める
+    fn start(game: &mut Game) {
+        game.begin_run();
+        game.countdown = 0.0;
+    }
+
+    // 条件を満たすまでフレームを進める。満たさなければ失敗にする
+    fn run_until(game: &mut Game, frames: usize, done: impl Fn(&Game) -> bool) {
+        for _ in 0..frames {
+            if done(game) {
+                return;
+            }
+            game.update(FRAME);
+        }
+        assert!(done(game), "condition not met within {} frames", frames);
+    }
+
+    // 最初の敵を自機の真上へ動かす
+    fn place_enemy_above_player(game: &mut Game, distance: f64) {
+        let center_x = game.player.pos.x + game.player.width / 2.0;
+        let y = game.player.pos.y - distance;
+        let enemy = &mut game.enemies[0];
+        enemy.kind = EnemyKind::Small;
+        enemy.movement = MovementPattern::Straight;
+        enemy.spawn_x = center_x - enemy.width / 2.0;
+        enemy.pos = Vec2::new(enemy.spawn_x, y);
+        enemy.prev = enemy.pos;
+    }
+
+    // 止まった通常の敵を y = 100 の高さに横へ並べた場でプレイを始める
+    fn game_with_enemies(xs: &[f64]) -> (Rc<RefCell<Game>>, MockAudio) {
+        let (game, audio) = headless_game(3);
+        {
+            let mut game = game.borrow_mut();
+            start(&mut game);
+            game.enemies = xs
+                .iter()
+                .map(|&x| Enemy::for_test(EnemyKind::Small, x, 100.0))
+                .collect();
+        }
+        (game, audio)
+    }
+
+    // 敵の中央に自機の弾を置く
+    fn shoot_into(game: &mut Game, index: usize) {
+        let (x, y, width, height) = game.enemies[index].bounds();
+        let radius = PLAYER_BULLET_RADIUS;
+        let bullet = Bullet::new_player_shot(x + width / 2.0 - radius, y + height / 2.0 - radius);
+        assert!(game.bullets.fire(bullet));
+    }
+
+    // 自機の中央に敵の弾を置く
+    fn shoot_player(game: &mut Game) {
+        let center = game.player.center();
+        game.enemy_bullets.push(Bullet::new_enemy_shot(center.x - 4.0, center.y - 4.0));
+    }
+
+    fn enemy_xs(game: &Game) -> Vec<f64> {
+        game.enemies.iter().map(|enemy| enemy.pos.x).collect()
+    }
+
+    #[test]
+    fn collision_matrix_is_symmetric_and_declares_each_pair_once() {
+        for (row, &a) in CollisionGroup::ALL.iter().enumerate() {
+            assert_eq!(a as usize, row, "{:?} is out of order in ALL", a);
+            for (column, &b) in CollisionGroup::ALL.iter().enumerate() {
+                assert_eq!(collision_handler(a, b).is_some(), collision_handler(b, a).is_some());
+                if column < row {
+                    assert!(COLLISION_MATRIX[row][column].is_none(), "{:?}/{:?}", a, b);
+                }
+            }
+        }
+        use CollisionGroup::*;
+        assert!(collision_handler(Enemy, Laser).is_some());
+        assert!(collision_handler(Enemy, Missile).is_some());
+        assert!(collision_handler(PlayerBullet, Player).is_none());
+        assert!(collision_handler(EnemyBullet, Enemy).is_none());
+        assert!(collision_handler(PowerUp, Enemy).is_none());
+    }
+
+    #[test]
+    fn laser_and_missiles_find_enemies_through_the_grid() {
+        for brute_force in [false, true] {
+            // 自機の真上の敵と、離れた敵
+            let (game, _) = game_with_enemies(&[0.0, 400.0]);
+            let mut game = game.borrow_mut();
+            game.brute_force_collisions = brute_force;
+            place_enemy_above_player(&mut game, 200.0);
+            game.laser_firing = true;
+            game.laser_damage = 10.0;
+            game.check_collisions();
+            assert_eq!(enemy_xs(&game), vec![400.0], "brute force {}", brute_force);
+            game.laser_firing = false;
+
+            // 爆風は近くの敵だけに届く
+            game.enemies.push(Enemy::for_test(EnemyKind::Small, 200.0, 100.0));
+            let (x, y, width, height) = game.enemies[0].bounds();
+            game.missiles.push(Missile::new(x + width / 2.0, y + height / 2.0));
+            game.check_collisions();
+            assert!(game.missiles.is_empty());
+            assert_eq!(enemy_xs(&game), vec![200.0], "brute force {}", brute_force);
+            assert_eq!(game.kills, 2);
+        }
+    }
+
+    #[test]
+    fn disabled_pairs_produce_no_hit() {
+        let (game, _) = game_with_enemies(&[100.0]);
+        let mut game = game.borrow_mut();
+        let (x, y, width, height) = game.enemies[0].bounds();
+        let (center_x, center_y) = (x + width / 2.0, y + height / 2.0);
+        let hp = game.enemies[0].hp;
+        // 敵の弾を敵に、自機の弾を自機に、パワーアップを敵に重ねる
+        game.enemy_bullets.push(Bullet::new_enemy_shot(center_x - 4.0, center_y - 4.0));
+        let player = game.player.center();
+        let radius = PLAYER_BULLET_RADIUS;
+        assert!(game.bullets.fire(Bullet::new_player_shot(player.x - radius, player.y - radius)));
+        game.power_ups.push(PowerUp::new(center_x, center_y, PowerUpKind::Shield));
+        let lives = game.lives;
+
+        game.check_collisions();
+        assert_eq!((game.enemies.len(), game.enemies[0].hp), (1, hp));
+        assert!(game.enemy_bullets[0].active);
+        assert_eq!(game.bullets.iter().filter(|bullet| bullet.active).count(), 1);
+        assert_eq!(game.power_ups.len(), 1);
+        assert_eq!(game.lives, lives);
+    }
+
+    // 決めた時刻に押して離すキー(等速のフレーム数、画面が縦向きのときのキー、押したか)
+    const SCRIPT: [(usize, &str, bool); 7] = [
+        (0, "ArrowRight", true),
+        (10, " ", true),
+        (40, "ArrowRight", false),
+        (60, "ArrowUp", true),
+        (90, "ArrowUp", false),
+        (200, " ", false),
+        (220, "ArrowLeft", true),
+    ];
+
+    // 始めたプレイで SCRIPT のとおりにキーを押し、等速で frames フレーム分進める
+    // 時刻は等速のフレーム数で数え、遅くした分だけフレームを増やして同じゲーム内時間に押す
+    // 丸めの差が出ないよう、1フレームは2進数で割り切れる16msにする
+    // key は縦向きのときのキーを、同じ操作になる画面の向きのキーに置き換える
+    fn play_script(game: &mut Game, frames: usize, key: impl Fn(&str) -> &str) {
+        const FRAME: f64 = 16.0;
+        let stretch = (1.0 / game.assist_speed).round() as usize;
+        for frame in 0..frames * stretch {
+            for &(at, name, pressed) in &SCRIPT {
+                if at * stretch == frame {
+                    let name = key(name).to_string();
+                    match pressed {
+                        true => game.key_down(name, frame as f64 * FRAME),
+                        false => game.key_up(name),
+                    }
+                }
+            }
+            game.update(FRAME);
+        }
+    }
+
+    fn scripted_run(speed: f64, frames: usize) -> Game {
+        let (game, _) = headless_game(5);
+        let mut game = Rc::try_unwrap(game).ok().unwrap().into_inner();
+        start(&mut game);
+        game.set_game_speed(speed);
+        play_script(&mut game, frames, |key| key);
+        game
+    }
+
+    #[test]
+    fn half_speed_over_twice_the_frames_reaches_the_same_state() {
+        let full = scripted_run(MAX_GAME_SPEED, 300);
+        let half = scripted_run(0.5, 300);
+        assert_eq!(half.clock.now(), full.clock.now());
+        assert_eq!((half.score, half.kills, half.lives), (full.score, full.kills, full.lives));
+        assert!(full.kills > 0);
+        assert!((half.player.pos - full.player.pos).length() < 1e-6);
+        assert_eq!(half.enemies.len(), full.enemies.len());
+        for (slow, fast) in half.enemies.iter().zip(&full.enemies) {
+            assert!((slow.pos - fast.pos).length() < 1e-6, "{:?} {:?}", slow.pos, fast.pos);
+        }
+        assert!(half.run_flags.assist_speed && !full.run_flags.assist_speed);
+    }
+
+    // 場の大きさが FIELD_SIZE になる画面で、orientation の向きにして SCRIPT を進める
+    // key は縦向きのときのキーを、同じ操作になるその向きの画面のキーに置き換える
+    fn oriented_run(orientation: Orientation, key: impl Fn(&str) -> &str) -> Game {
+        let (width, height) = orientation.field_size(FIELD_SIZE);
+        let renderer = Box::new(MockRenderer::default());
+        let audio = Box::new(MockAudio::default());
+        let game = Game::new(renderer, audio, 7, width, height);
+        let mut game = Rc::try_unwrap(game).ok().unwrap().into_inner();
+        game.headless = true;
+        // 向きを変えるとリセットされ、同じ手順で次のシードを引く
+        game.set_orientation(orientation, 0.0);
+        assert_eq!((game.width, game.height), FIELD_SIZE);
+        start(&mut game);
+        play_script(&mut game, 300, key);
+        game
+    }
+
+    #[test]
+    fn the_same_script_plays_out_the_same_in_both_orientations() {
+        let bottom_up = oriented_run(Orientation::BottomUp, |key| key);
+        // 横向きでは画面の右が場の奥、画面の上が場の左になる
+        let left_to_right = oriented_run(Orientation::LeftToRight, |key| match key {
+            "ArrowUp" => "ArrowRight",
+            "ArrowDown" => "ArrowLeft",
+            "ArrowLeft" => "ArrowUp",
+            "ArrowRight" => "ArrowDown",
+            key => key,
+        });
+
+        let outcome = |game: &Game| (game.score, game.kills, game.lives, game.enemies.len());
+        assert_eq!(outcome(&left_to_right), outcome(&bottom_up));
+        assert!(bottom_up.kills > 0);
+        assert_eq!(left_to_right.player.pos, bottom_up.player.pos);
+        assert_eq!(enemy_xs(&left_to_right), enemy_xs(&bottom_up));
+
+        // 画面上では場の奥(上)が右に、場の左が画面の上に来る
+        let Vec2 { x, y } = bottom_up.player.pos;
+        let (_, field_height) = FIELD_SIZE;
+        assert_eq!(left_to_right.basis().point((x, y)), (field_height - y, x));
+        assert_eq!(bottom_up.basis().point((x, y)), (x, y));
+    }
+
+    #[test]
+    fn pausing_halfway_through_a_buff_keeps_its_remaining_time() {
+        let (game, _) = headless_game(1);
+        let mut game = game.borrow_mut();
+        start(&mut game);
+        game.shield = GameTimer::started(&game.clock, SHIELD_DURATION);
+        game.pierce = GameTimer::started(&game.clock, PIERCE_DURATION);
+        let mut now = 1000.0;
+        game.render_frame(now);
+        while game.shield.remaining(&game.clock) > SHIELD_DURATION / 2.0 {
+            // 敵や弾に当たってシールドが消えないよう、場を空けたまま進める
+            game.enemies.clear();
+            game.enemy_bullets.clear();
+            now += 16.0;
+            game.render_frame(now);
+        }
+        let remaining = |game: &Game| {
+            (game.shield.remaining(&game.clock), game.pierce.remaining(&game.clock))
+        };
+        let before = remaining(&game);
+
+        // 一時停止している間に実時間で30秒たつ
+        assert!(game.set_paused(true));
+        for _ in 0..30 {
+            now += 1000.0;
+            game.render_frame(now);
+        }
+        assert!(game.set_paused(false));
+        assert_eq!(remaining(&game), before);
+
+        // 再開後は1フレーム分だけ減る
+        now += 16.0;
+        game.render_frame(now);
+        assert_eq!(remaining(&game), (before.0 - 16.0, before.1 - 16.0));
+    }
+
+    #[test]
+    fn a_telegraphed_spawn_appears_on_the_frame_its_delay_runs_out() {
+        let (game, _) = headless_game(1);
+        let mut game = game.borrow_mut();
+        start(&mut game);
+        // 波からは出さず、置いた1体だけを見る
+        game.wave_break = GameTimer::started(&game.clock, f64::MAX);
+        let marker = Enemy::for_test(EnemyKind::Small, 123.0, 0.0);
+        game.pending_spawns.push(PendingSpawn::new(marker));
+        let spawned = |game: &Game| game.enemies.iter().any(|enemy| enemy.spawn_x == 123.0);
+        let mut now = 1000.0;
+        game.render_frame(now);
+
+        // 600msの予告は16msのフレームで37フレーム目まで残り、38フレーム目に敵になる
+        let frame = |game: &mut Game, now: &mut f64| {
+            *now += 16.0;
+            game.render_frame(*now);
+        };
+        for _ in 0..20 {
+            frame(&mut game, &mut now);
+        }
+
+        // 一時停止の間は予告の残り時間が減らない
+        let remaining = game.pending_spawns[0].remaining;
+        game.set_paused(true);
+        for _ in 0..100 {
+            frame(&mut game, &mut now);
+        }
+        game.set_paused(false);
+        assert_eq!(game.pending_spawns[0].remaining, remaining);
+
+        for _ in 20..37 {
+            frame(&mut game, &mut now);
+        }
+        assert_eq!(game.pending_spawns.len(), 1);
+        assert!(!spawned(&game));
+        frame(&mut game, &mut now);
+        assert!(game.pending_spawns.is_empty());
+        assert!(spawned(&game));
+    }
+
+    #[test]
+    fn removes_every_destroyed_enemy_and_spent_bullet_in_one_pass() {
+        let (game, _) = game_with_enemies(&[0.0, 100.0, 200.0, 300.0]);
+        let mut game = game.borrow_mut();
+        shoot_into(&mut game, 0);
+        shoot_into(&mut game, 2);
+        shoot_player(&mut game);
+        game.enemy_bullets.push(Bullet::new_enemy_shot(400.0, 10.0));
+        let lives = game.lives;
+
+        game.check_collisions();
+        // 残った敵は順番を保つ
+        assert_eq!(enemy_xs(&game), vec![100.0, 300.0]);
+        assert_eq!(game.kills, 2);
+        assert_eq!(game.enemy_bullets.len(), 1);
+        assert_eq!(game.enemy_bullets[0].pos.x, 400.0);
+        assert_eq!(game.lives, lives - 1);
+        assert!(game.enemies.iter().all(|enemy| !enemy.dead));
+    }
+
+    #[test]
+    fn two_bullets_on_one_enemy_score_a_single_kill() {
+        let (game, audio) = game_with_enemies(&[200.0]);
+        let mut game = game.borrow_mut();
+        shoot_into(&mut game, 0);
+        shoot_into(&mut game, 0);
+
+        game.check_collisions();
+        assert!(game.enemies.is_empty());
+        assert_eq!(game.kills, 1);
+        assert_eq!(game.score, 10);
+        assert_eq!(game.shots_hit, 1);
+        assert_eq!(game.score_popups.len(), 1);
+        // 2発目は倒れた敵を素通りして飛び続ける
+        let remaining: Vec<&Bullet> = game.bullets.iter().collect();
+        assert_eq!(remaining.len(), 1);
+        assert!(!remaining[0].has_hit);
+        // 撃破の音も1回だけ鳴る
+        assert_eq!(audio.played.borrow().len(), 1);
+    }
+
+    #[test]
+    fn kills_and_player_collisions_in_one_frame_remove_the_right_enemies() {
+        let (game, _) = game_with_enemies(&[0.0, 100.0, 200.0]);
+        let mut game = game.borrow_mut();
+        // 最後の敵を自機に重ね、先頭の敵を撃つ
+        let player = game.player.pos;
+        game.enemies[2].pos = player;
+        game.enemies[2].prev = player;
+        shoot_into(&mut game, 0);
+        let lives = game.lives;
+
+        game.check_collisions();
+        assert_eq!(enemy_xs(&game), vec![100.0]);
+        assert_eq!(game.enemies[0].hp, game.enemies[0].max_hp);
+        assert_eq!(game.kills, 1);
+        assert_eq!(game.lives, lives - 1);
+
+        // 取り除いた後の番号で判定し直しても、残った敵には何も起きない
+        game.check_collisions();
+        assert_eq!(enemy_xs(&game), vec![100.0]);
+        assert_eq!((game.kills, game.lives), (1, lives - 1));
+    }
+
+    #[test]
+    fn bullets_hit_with_their_drawn_circle_not_the_bounding_square() {
+        let (game, _) = game_with_enemies(&[200.0]);
+        let mut game = game.borrow_mut();
+        // 外接する正方形の角だけが敵の左上の角に1px入り込んでいる
+        let corner = Bullet::new_player_shot(200.0 - 9.0, 100.0 - 9.0);
+        assert!(rects_overlap(corner.bounds(), game.enemies[0].bounds()));
+        game.bullets.fire(corner);
+        game.check_collisions();
+        assert_eq!(game.kills, 0);
+
+        // 円の縁が敵の左辺に届いていれば当たる
+        game.bullets = BulletPool::new();
+        game.bullets.fire(Bullet::new_player_shot(200.0 - 10.0, 120.0));
+        game.check_collisions();
+        assert_eq!(game.kills, 1);
+    }
+
+    #[test]
+    fn grazing_the_sprite_edge_does_not_cost_a_life() {
+        let (game, _) = game_with_enemies(&[]);
+        let mut game = game.borrow_mut();
+        let lives = game.lives;
+        // 画像の左端には触れているが、被弾判定の外
+        let (x, y, _, height) = game.player.bounds();
+        game.enemy_bullets.push(Bullet::new_enemy_shot(x - 4.0, y + height / 2.0 - 4.0));
+        game.check_collisions();
+        assert_eq!(game.lives, lives);
+        assert_eq!(game.enemy_bullets.len(), 1);
+
+        // 被弾判定の中なら当たる
+        shoot_player(&mut game);
+        game.check_collisions();
+        assert_eq!(game.lives, lives - 1);
+    }
+
+    #[test]
+    fn cleared_waves_take_a_break_before_the_next_one() {
+        let (game, _) = game_with_enemies(&[]);
+        let mut game = game.borrow_mut();
+        // 最初の波の前の休憩を終え、出し切った敵をすべて倒した
+        game.wave_break.clear();
+        game.wave_spawned = game.current_wave.enemy_count;
+        game.update(FRAME);
+        assert_eq!(game.current_wave.number, 2);
+        assert_eq!(game.wave_spawned, 0);
+        assert!(game.wave_break.is_running(&game.clock));
+
+        // 休憩中は敵を出さない
+        let break_frames = (WAVE_BREAK_DURATION / FRAME) as usize;
+        for _ in 0..break_frames - 1 {
+            game.update(FRAME);
+            assert!(game.pending_spawns.is_empty() && game.enemies.is_empty());
+        }
+        run_until(&mut game, 10, |game| !game.pending_spawns.is_empty());
+        assert_eq!(game.current_wave.number, 2);
+    }
+
+    #[test]
+    fn spawns_wait_for_room_under_the_enemy_cap() {
+        let (game, _) = game_with_enemies(&[]);
+        let mut game = game.borrow_mut();
+        game.wave_break.clear();
+        game.level.max_enemies = 5;
+        game.current_wave.enemy_count = 40;
+        game.next_boss_score = u32::MAX;
+
+        // 出現の間隔を無視して出し続けても、上限を超えない
+        for _ in 0..100 {
+            game.spawn_timer.clear();
+            game.update_waves();
+            assert!(game.enemy_count() <= 5);
+        }
+        assert_eq!(game.enemy_count(), 5);
+        assert_eq!(game.wave_spawned, 5);
+
+        // 空いた分だけ出し、その分の出現は捨てずに数える
+        game.pending_spawns.clear();
+        game.enemies.clear();
+        for _ in 0..100 {
+            game.spawn_timer.clear();
+            game.update_waves();
+            assert!(game.enemy_count() <= 5);
+        }
+        assert_eq!(game.wave_spawned, 10);
+        assert_eq!(game.current_wave.number, 1);
+    }
+
+    #[test]
+    fn player_wraps_across_the_side_edges_only_when_enabled() {
+        let (game, _) = game_with_enemies(&[]);
+        let mut game = game.borrow_mut();
+        game.key_down("ArrowLeft".to_string(), 0.0);
+        game.player.pos.x = 1.0;
+        game.update(FRAME);
+        assert_eq!(game.player.pos.x, 0.0);
+
+        game.wrap_horizontal = true;
+        game.update(FRAME);
+        assert!(game.player.pos.x > game.width - game.player.width);
+        assert!(game.player.pos.x < game.width);
+        // 端をまたいでいる間は反対側にも当たり判定がある
+        let (_, wrapped) = game.player_rects();
+        let (x, _, width, _) = wrapped.unwrap();
+        assert!(x < 0.0 && x + width > 0.0);
+    }
+
+    #[test]
+    fn bullets_hit_the_part_of_the_player_shown_on_the_other_side() {
+        let (game, _) = game_with_enemies(&[]);
+        let mut game = game.borrow_mut();
+        game.wrap_horizontal = true;
+        game.player.pos.x = game.width - game.player.width / 2.0;
+        let (_, y, _, height) = game.player.hurtbox();
+        game.enemy_bullets.push(Bullet::new_enemy_shot(0.0, y + height / 2.0 - 4.0));
+        let lives = game.lives;
+
+        game.check_collisions();
+        assert_eq!(game.lives, lives - 1);
+    }
+
+    #[test]
+    fn weak_point_hits_multiply_damage_and_count_as_crits() {
+        let (game, _) = game_with_enemies(&[]);
+        let mut game = game.borrow_mut();
+        game.enemies.push(Enemy::for_test(EnemyKind::Armored, 200.0, 100.0));
+        let radius = PLAYER_BULLET_RADIUS;
+        let fire_at = |game: &mut Game, (x, y): (f64, f64)| {
+            game.bullets = BulletPool::new();
+            game.bullets.fire(Bullet::new_player_shot(x - radius, y - radius));
+        };
+
+        // 本体の上の方に当たると1ダメージ
+        fire_at(&mut game, (225.0, 105.0));
+        game.check_collisions();
+        assert_eq!(game.enemies[0].hp, 2);
+        assert_eq!(game.crit_hits, 0);
+
+        // 弱点に当たると倍率分のダメージで一撃
+        let (x, y, width, height) = game.enemies[0].weak_point().unwrap();
+        fire_at(&mut game, (x + width / 2.0, y + height / 2.0));
+        game.check_collisions();
+        assert!(game.enemies.is_empty());
+        assert_eq!(game.crit_hits, 1);
+        assert_eq!(game.crit_effects.len(), 1);
+    }
+
+    // ボスの出るスコアに達し、通常の敵が1体いる場
+    fn game_reaching_boss() -> (Rc<RefCell<Game>>, MockAudio) {
+        let (game, audio) = game_with_enemies(&[100.0]);
+        {
+            let mut game = game.borrow_mut();
+            game.wave_break.clear();
+            game.score = game.next_boss_score;
+        }
+        (game, audio)
+    }
+
+    #[test]
+    fn boss_intro_warns_clears_the_field_and_eases_the_boss_in() {
+        let (game, audio) = game_reaching_boss();
+        let mut game = game.borrow_mut();
+        let game = &mut *game;
+        game.spawn_timer.start(&game.clock, 800.0);
+        game.pending_spawns.push(PendingSpawn::new(Enemy::for_test(EnemyKind::Small, 300.0, 0.0)));
+        let lives = game.lives;
+
+        game.update(FRAME);
+        let intro = game.boss_intro.unwrap();
+        assert_eq!(intro.phase, BossIntroPhase::Warning);
+        assert_eq!(intro.spawn_remaining, 800.0 - FRAME);
+        assert!(game.pending_spawns.is_empty());
+        assert_eq!(audio.count(BOSS_WARNING_SOUND), 1);
+
+        // 警告中は通常の敵を出さず、抜けていった敵でライフを減らさない
+        game.enemies[0].pos.y = game.height - 1.0;
+        game.enemies[0].speed = 5.0;
+        run_until(game, 10, |game| game.enemies.is_empty());
+        assert_eq!(game.lives, lives);
+        assert_eq!(game.escape_flash.remaining(&game.clock), 0.0);
+
+        // 警告が終わると、ボスが画面の外から攻撃せずに定位置まで降りてくる
+        let entering = |game: &Game| game.boss_intro.map(|intro| intro.phase);
+        run_until(game, 200, |game| entering(game) == Some(BossIntroPhase::Entering));
+        assert!(game.pending_spawns.is_empty());
+        let mut last_y = f64::MIN;
+        for _ in 0..200 {
+            if game.boss_intro.is_none() {
+                break;
+            }
+            assert_eq!(game.enemies.len(), 1);
+            assert_eq!(game.enemies[0].kind, EnemyKind::Boss);
+            assert!(game.enemies[0].pos.y >= last_y);
+            assert!(game.enemy_bullets.is_empty());
+            last_y = game.enemies[0].pos.y;
+            game.update(FRAME);
+        }
+        assert!(game.boss_intro.is_none());
+        assert_eq!(game.enemies[0].pos.y, BOSS_HOLD_Y);
+        // 止めておいた出現間隔の続きから数える
+        assert!((game.spawn_timer.remaining(&game.clock) - (800.0 - FRAME)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn killing_the_boss_on_its_way_in_ends_the_intro() {
+        let (game, _) = game_reaching_boss();
+        let mut game = game.borrow_mut();
+        game.update(FRAME);
+        run_until(&mut game, 200, |game| game.boss_active());
+        game.enemies.clear();
+        game.update(FRAME);
+        assert!(game.boss_intro.is_none());
+    }
+
+    #[test]
+    fn reset_and_game_over_abort_the_boss_intro() {
+        let (game, _) = game_reaching_boss();
+        let mut game = game.borrow_mut();
+        game.update(FRAME);
+        assert!(game.boss_intro.is_some());
+        game.reset(0.0);
+        assert!(game.boss_intro.is_none());
+        // 取り消せば演出の途中から続ける
+        game.undo_reset();
+        assert!(game.boss_intro.is_some());
+
+        game.countdown = 0.0;
+        game.lives = 1;
+        shoot_player(&mut game);
+        game.update(FRAME);
+        assert_ne!(game.state, GameState::Playing);
+        assert!(game.boss_intro.is_none());
+    }
+
+    #[test]
+    fn kills_fill_the_bomb_meter_up_to_the_bomb_cap() {
+        let (game, audio) = game_with_enemies(&[0.0, 100.0, 200.0]);
+        let mut game = game.borrow_mut();
+        game.bombs = MAX_BOMBS - 1;
+        game.bomb_meter.percent = 97;
+        shoot_into(&mut game, 0);
+        shoot_into(&mut game, 1);
+        game.check_collisions();
+        // 2体で4%溜まり、あふれた1%は持ち越す
+        assert_eq!(game.bombs, MAX_BOMBS);
+        assert_eq!(game.bomb_meter.percent, 1);
+        assert_eq!(audio.count(BOMB_READY_SOUND), 1);
+
+        // ボムが上限の間は満タンで止まり、ボムは増えない
+        game.bomb_meter.percent = 99;
+        shoot_into(&mut game, 0);
+        game.check_collisions();
+        assert_eq!(game.bombs, MAX_BOMBS);
+        assert_eq!(game.bomb_meter.percent, 100);
+        assert_eq!(audio.count(BOMB_READY_SOUND), 1);
+    }
+
+    #[test]
+    fn each_boss_phase_fills_the_bomb_meter() {
+        let (game, _) = game_with_enemies(&[]);
+        let mut game = game.borrow_mut();
+        let mut boss = Enemy::for_test(EnemyKind::Boss, 180.0, 100.0);
+        (boss.hp, boss.max_hp) = (40, 40);
+        game.enemies.push(boss);
+
+        shoot_into(&mut game, 0);
+        game.check_collisions();
+        assert_eq!(game.bomb_meter.percent, 0);
+
+        // 体力の4分の1を削りきると1段階
+        game.enemies[0].hp = 31;
+        shoot_into(&mut game, 0);
+        game.check_collisions();
+        assert_eq!(game.bomb_meter.percent, 10);
+
+        // 倒すと最後の段階と撃破の分
+        game.enemies[0].hp = 1;
+        shoot_into(&mut game, 0);
+        game.check_collisions();
+        assert!(game.enemies.is_empty());
+        assert_eq!(game.bomb_meter.percent, 22);
+    }
+
+    #[test]
+    fn bomb_meter_survives_waves_and_empties_on_a_hit_when_configured() {
+        let (game, _) = game_with_enemies(&[]);
+        let mut game = game.borrow_mut();
+        game.bomb_meter.percent = 40;
+        game.wave_break.clear();
+        game.wave_spawned = game.current_wave.enemy_count;
+        game.update(FRAME);
+        assert_eq!(game.current_wave.number, 2);
+        assert_eq!(game.bomb_meter.percent, 40);
+
+        game.level.bomb_meter.reset_on_death = false;
+        shoot_player(&mut game);
+        game.check_collisions();
+        assert_eq!(game.bomb_meter.percent, 40);
+
+        game.level.bomb_meter.reset_on_death = true;
+        shoot_player(&mut game);
+        game.check_collisions();
+        assert_eq!(game.bomb_meter.percent, 0);
+
+        // リセットで空になり、取り消すと戻る
+        game.bomb_meter.percent = 30;
+        game.reset(0.0);
+        assert_eq!(game.bomb_meter.percent, 0);
+        game.undo_reset();
+        assert_eq!(game.bomb_meter.percent, 30);
+    }
+
+    // 2波目から必ず modifier を掛ける設定
+    fn always(modifier: WaveModifier) -> WaveModifierDef {
+        let mut def = WaveModifierDef {
+            chance: 1.0,
+            first_wave: 2,
+            speed: 0,
+            swarm: 0,
+            darkness: 0,
+            fragile: 0,
+        };
+        match modifier {
+            WaveModifier::Speed => def.speed = 1,
+            WaveModifier::Swarm => def.swarm = 1,
+            WaveModifier::Darkness => def.darkness = 1,
+            WaveModifier::Fragile => def.fragile = 1,
+        }
+        def
+    }
+
+    // 進行中の波を片付けて次の波へ進める
+    fn finish_wave(game: &mut Game) {
+        game.wave_break.clear();
+        game.enemies.clear();
+        game.pending_spawns.clear();
+        game.wave_spawned = game.current_wave.enemy_count;
+        game.update(FRAME);
+    }
+
+    #[test]
+    fn wave_modifiers_revert_at_wave_end_even_after_losing_a_life() {
+        for modifier in WaveModifier::ALL {
+            let (game, _) = game_with_enemies(&[]);
+            let mut game = game.borrow_mut();
+            game.level.wave_modifiers = always(modifier);
+            let mut base = game.level.clone();
+
+            finish_wave(&mut game);
+            assert_eq!(game.current_wave.number, 2);
+            assert_eq!(game.wave_modifier.as_ref().map(|active| active.modifier), Some(modifier));
+            assert!((&game.level, game.current_wave) != (&base, game.wave(2)));
+            assert_eq!(game.modifier_waves, 1);
+
+            let lives = game.lives;
+            shoot_player(&mut game);
+            game.check_collisions();
+            assert!(game.lives < lives);
+
+            // 次の波には掛けない
+            game.level.wave_modifiers.chance = 0.0;
+            base.wave_modifiers.chance = 0.0;
+            finish_wave(&mut game);
+            assert_eq!(game.current_wave.number, 3);
+            assert!(game.wave_modifier.is_none());
+            assert_eq!(game.level, base, "{:?}", modifier);
+            assert_eq!(game.current_wave, game.wave(3), "{:?}", modifier);
+        }
+    }
+
+    #[test]
+    fn game_over_mid_wave_reverts_the_modifier() {
+        let (game, _) = game_with_enemies(&[]);
+        let mut game = game.borrow_mut();
+        game.level.wave_modifiers = always(WaveModifier::Fragile);
+        let base = game.level.clone();
+        finish_wave(&mut game);
+
+        // もろい波では1回の被弾で2つ失う
+        game.lives = 2;
+        shoot_player(&mut game);
+        game.check_collisions();
+        assert_eq!(game.state, GameState::GameOver);
+        assert!(game.wave_modifier.is_none());
+        assert_eq!(game.level, base);
+        assert_eq!(game.current_wave, game.wave(2));
+        assert_eq!(game.history.runs.last().map(|run| run.modifier_waves), Some(1));
+    }
+
+    #[test]
+    fn fragile_waves_triple_the_score_of_enemies_spawned_in_them() {
+        let (game, _) = game_with_enemies(&[]);
+        let mut game = game.borrow_mut();
+        game.level.wave_modifiers = always(WaveModifier::Fragile);
+        finish_wave(&mut game);
+        game.spawn_enemy();
+        let enemy = &game.pending_spawns[0].enemy;
+        let base = LevelConfig::default().enemy(enemy.kind).score;
+        assert_eq!(enemy.score_value, (base + Enemy::speed_bonus(enemy.speed)) * 3);
+    }
+
+    #[test]
+    fn reset_sets_the_modifier_aside_and_undo_puts_it_back() {
+        let (game, _) = game_with_enemies(&[]);
+        let mut game = game.borrow_mut();
+        game.level.wave_modifiers = always(WaveModifier::Swarm);
+        let base = game.level.clone();
+        finish_wave(&mut game);
+        let modified = (game.level.clone(), game.current_wave);
+
+        game.reset(0.0);
+        assert!(game.wave_modifier.is_none());
+        assert_eq!(game.level, base);
+        assert_eq!(game.modifier_waves, 0);
+
+        game.undo_reset();
+        assert_eq!(
+            game.wave_modifier.as_ref().map(|active| active.modifier),
+            Some(WaveModifier::Swarm)
+        );
+        assert_eq!((game.level.clone(), game.current_wave), modified);
+        assert_eq!(game.modifier_waves, 1);
+    }
+
+    #[test]
+    fn kills_request_the_clip_listed_for_the_enemy_kind() {
+        let (game, audio) = game_with_enemies(&[0.0, 100.0, 200.0]);
+        let mut game = game.borrow_mut();
+        game.kill_sounds.kamikaze = vec!["crashSound".to_string()];
+        game.enemies[1].kind = EnemyKind::Kamikaze;
+        game.enemies[2].kind = EnemyKind::Boss;
+        game.enemies[2].hp = 1;
+        for index in 0..3 {
+            shoot_into(&mut game, index);
+        }
+        game.check_collisions();
+        assert!(game.enemies.is_empty());
+        let played = audio.played.borrow();
+        assert!(played.iter().any(|clip| game.kill_sounds.small.contains(clip)));
+        assert_eq!(audio.count("crashSound"), 1);
+        // 候補のないボスは通常の爆発音
+        assert_eq!(audio.count("explosion"), 1);
+    }
+
+    #[test]
+    fn final_moments_replay_only_on_screen_and_without_reduced_motion() {
+        for (headless, reduced_motion, expected) in [
+            (false, false, GameState::Spectating),
+            (false, true, GameState::GameOver),
+            (true, false, GameState::GameOver),
+        ] {
+            let (game, _) = game_with_enemies(&[]);
+            let mut game = game.borrow_mut();
+            game.headless = headless;
+            game.reduced_motion = reduced_motion;
+            game.lives = 1;
+            game.update(FRAME);
+            shoot_player(&mut game);
+            game.update(FRAME);
+            assert_eq!(game.state, expected, "headless {}, reduced {}", headless, reduced_motion);
+        }
+
+        // 再生中に動きを抑える設定にすると、次の描画で結果画面に移る
+        let (game, _) = game_with_enemies(&[]);
+        let mut game = game.borrow_mut();
+        game.headless = false;
+        game.lives = 1;
+        game.update(FRAME);
+        shoot_player(&mut game);
+        game.update(FRAME);
+        assert_eq!(game.state, GameState::Spectating);
+        game.reduced_motion = true;
+        game.render_spectate(FRAME);
+        assert_eq!(game.state, GameState::GameOver);
+    }
+
+    #[test]
+    fn headless_run_spawns_kills_and_ends() {
+        let (game, audio) = headless_game(7);
+        let mut game = game.borrow_mut();
+        start(&mut game);
+
+        // 敵が出てくる
+        run_until(&mut game, 600, |game| !game.enemies.is_empty());
+
+        // 真上の敵を撃ち落とす
+        place_enemy_above_player(&mut game, 200.0);
+        for _ in 0..120 {
+            if game.kills > 0 {
+                break;
+            }
+            game.fire_bullet(None);
+            game.update(FRAME);
+        }
+        assert!(game.kills > 0);
+        assert!(game.score > 0);
+        assert!(audio.count("shot") > 0);
+        assert!(game.events.contains(&GameEvent::Score { score: game.score }));
+
+        // 最後のライフで敵に体当たりされてゲームオーバーになる
+        game.lives = 1;
+        run_until(&mut game, 600, |game| !game.enemies.is_empty());
+        place_enemy_above_player(&mut game, 0.0);
+        game.update(FRAME);
+        assert_eq!(game.lives, 0);
+        assert_eq!(game.state, GameState::GameOver);
+        assert_eq!(game.history.runs.len(), 1);
+        assert!(game
+            .events
+            .iter()
+            .any(|event| matches!(event, GameEvent::GameOver { .. })));
     }
 }
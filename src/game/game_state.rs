@@ -1,5 +1,9 @@
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum GameState {
+    Menu, // タイトル画面(Enterで開始)
+    Stats, // これまでのプレイの記録(タイトル画面からSで開く)
     Playing,
+    LevelComplete, // レベルクリア後、Enterで次へ進む
+    Spectating, // ゲームオーバー直前の場面を再生中
     GameOver,
 }
@@ -0,0 +1,141 @@
+// 対戦相手の自機を半透明で表示するための位置の記録と補間
+
+// 表示を遅らせる時間(ms)。送信間隔(100ms)分遅らせて、常に2つの受信位置の間を補間する
+pub const GHOST_INTERPOLATION_DELAY: f64 = 100.0;
+// 受信が遅れたときに予測で進める時間の上限(ms)
+pub const GHOST_MAX_EXTRAPOLATION: f64 = 200.0;
+// この時間受信がなければ表示を消す(ms)
+pub const GHOST_TIMEOUT: f64 = 2000.0;
+
+#[derive(Clone, Copy)]
+struct GhostSample {
+    sent_at: f64, // 相手側の時刻
+    x: f64,
+    y: f64,
+}
+
+pub struct GhostBuffer {
+    previous: Option<GhostSample>,
+    latest: Option<GhostSample>,
+    clock_offset: Option<f64>, // 自分の時刻 - 相手の時刻 の最小値(通信の揺らぎを除く)
+    last_received: f64,        // 最後に受信した自分側の時刻
+}
+
+impl GhostBuffer {
+    pub fn new() -> Self {
+        GhostBuffer {
+            previous: None,
+            latest: None,
+            clock_offset: None,
+            last_received: 0.0,
+        }
+    }
+
+    // 位置を受信した。順番が入れ替わって届いた古い位置は捨てる
+    pub fn push(&mut self, sent_at: f64, x: f64, y: f64, received_at: f64) {
+        if self.latest.is_some_and(|latest| sent_at <= latest.sent_at) {
+            return;
+        }
+        let offset = received_at - sent_at;
+        self.clock_offset = Some(self.clock_offset.map_or(offset, |o| o.min(offset)));
+        self.previous = self.latest;
+        self.latest = Some(GhostSample { sent_at, x, y });
+        self.last_received = received_at;
+    }
+
+    // 自分側の時刻 now に表示する位置。しばらく受信がなければNone
+    pub fn position_at(&self, now: f64) -> Option<(f64, f64)> {
+        let latest = self.latest?;
+        if now - self.last_received > GHOST_TIMEOUT {
+            return None;
+        }
+        let Some(previous) = self.previous else {
+            return Some((latest.x, latest.y));
+        };
+
+        // 相手側の時刻に直して、少し過去の位置を求める
+        let target = now - self.clock_offset? - GHOST_INTERPOLATION_DELAY;
+        let span = latest.sent_at - previous.sent_at;
+        if span <= 0.0 {
+            return Some((latest.x, latest.y));
+        }
+        // 最新の位置より先は予測で進めるが、進めすぎないよう制限する
+        let target = target.min(latest.sent_at + GHOST_MAX_EXTRAPOLATION);
+        let t = ((target - previous.sent_at) / span).max(0.0);
+        let t = ease(t);
+        Some((
+            previous.x + (latest.x - previous.x) * t,
+            previous.y + (latest.y - previous.y) * t,
+        ))
+    }
+}
+
+// 2点の間は滑らかに加減速し、最新の位置を越えた予測部分はそのまま直線で進める
+fn ease(t: f64) -> f64 {
+    if t <= 1.0 {
+        t * t * (3.0 - 2.0 * t)
+    } else {
+        t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 相手の時刻 0ms と 100ms の位置を、自分の時刻 1000ms と 1100ms に受信した
+    fn two_samples() -> GhostBuffer {
+        let mut ghost = GhostBuffer::new();
+        ghost.push(0.0, 0.0, 0.0, 1000.0);
+        ghost.push(100.0, 100.0, 50.0, 1100.0);
+        ghost
+    }
+
+    #[test]
+    fn shows_nothing_until_received_and_a_single_sample_as_is() {
+        let mut ghost = GhostBuffer::new();
+        assert_eq!(ghost.position_at(0.0), None);
+        ghost.push(0.0, 10.0, 20.0, 500.0);
+        assert_eq!(ghost.position_at(600.0), Some((10.0, 20.0)));
+    }
+
+    #[test]
+    fn interpolates_a_delay_behind_the_latest_sample() {
+        let ghost = two_samples();
+        assert_eq!(ghost.position_at(1100.0), Some((0.0, 0.0)));
+        // ちょうど中間は加減速しても中間
+        assert_eq!(ghost.position_at(1150.0), Some((50.0, 25.0)));
+        assert_eq!(ghost.position_at(1200.0), Some((100.0, 50.0)));
+    }
+
+    #[test]
+    fn extrapolates_only_up_to_the_limit() {
+        let ghost = two_samples();
+        assert_eq!(ghost.position_at(1300.0), Some((200.0, 100.0)));
+        let limit = 1.0 + GHOST_MAX_EXTRAPOLATION / 100.0;
+        assert_eq!(ghost.position_at(1900.0), Some((100.0 * limit, 50.0 * limit)));
+    }
+
+    #[test]
+    fn drops_samples_that_arrive_out_of_order() {
+        let mut ghost = two_samples();
+        ghost.push(50.0, 999.0, 999.0, 1120.0);
+        ghost.push(100.0, 999.0, 999.0, 1120.0);
+        assert_eq!(ghost.position_at(1200.0), Some((100.0, 50.0)));
+    }
+
+    #[test]
+    fn late_deliveries_do_not_shift_the_clock_offset() {
+        let mut ghost = two_samples();
+        // 通信が詰まって遅れて届いても、最小の時差を使い続ける
+        ghost.push(200.0, 200.0, 100.0, 1500.0);
+        assert_eq!(ghost.position_at(1250.0), Some((150.0, 75.0)));
+    }
+
+    #[test]
+    fn disappears_after_the_timeout() {
+        let ghost = two_samples();
+        assert!(ghost.position_at(1100.0 + GHOST_TIMEOUT).is_some());
+        assert_eq!(ghost.position_at(1101.0 + GHOST_TIMEOUT), None);
+    }
+}
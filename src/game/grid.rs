@@ -0,0 +1,140 @@
+use crate::game::collision::Rect;
+
+// 当たり判定の候補を絞るための格子の1マスの大きさ(px)
+pub const GRID_CELL_SIZE: f64 = 100.0;
+
+// 場を一定の大きさのマスに分け、マスごとに重なっている物体の番号を持つ
+// 毎フレーム作り直し、近くにいる物体だけを当たり判定の候補にする
+// 場の外にはみ出した部分は端のマスに入れる
+pub struct SpatialGrid {
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>, // マスごとの物体の番号(行優先)
+}
+
+impl SpatialGrid {
+    pub fn new(field_width: f64, field_height: f64) -> Self {
+        let columns = ((field_width / GRID_CELL_SIZE).ceil() as usize).max(1);
+        let rows = ((field_height / GRID_CELL_SIZE).ceil() as usize).max(1);
+        SpatialGrid {
+            columns,
+            rows,
+            cells: vec![Vec::new(); columns * rows],
+        }
+    }
+
+    // 矩形が重なるマスの範囲(列と行、両端を含む)
+    fn cell_range(&self, (x, y, width, height): Rect) -> ((usize, usize), (usize, usize)) {
+        let (columns, rows) = (self.columns, self.rows);
+        (
+            (cell_index(x, columns), cell_index(x + width, columns)),
+            (cell_index(y, rows), cell_index(y + height, rows)),
+        )
+    }
+
+    // 入っている物体をすべて取り除き、rects の順に番号を振って入れ直す
+    pub fn rebuild(&mut self, rects: impl Iterator<Item = Rect>) {
+        self.cells.iter_mut().for_each(Vec::clear);
+        for (index, rect) in rects.enumerate() {
+            let ((left, right), (top, bottom)) = self.cell_range(rect);
+            for row in top..=bottom {
+                for column in left..=right {
+                    self.cells[row * self.columns + column].push(index);
+                }
+            }
+        }
+    }
+
+    // 矩形と同じマスにいる物体の番号を小さい順に重複なく candidates に入れる
+    pub fn query(&self, rect: Rect, candidates: &mut Vec<usize>) {
+        candidates.clear();
+        let ((left, right), (top, bottom)) = self.cell_range(rect);
+        for row in top..=bottom {
+            for column in left..=right {
+                candidates.extend_from_slice(&self.cells[row * self.columns + column]);
+            }
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+    }
+}
+
+// 座標 v を含むマスの番号。範囲の外は端のマスにする
+fn cell_index(v: f64, count: usize) -> usize {
+    ((v / GRID_CELL_SIZE).floor().max(0.0) as usize).min(count - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::collision::rects_overlap;
+    use crate::game::Rng;
+
+    fn query(grid: &SpatialGrid, rect: Rect) -> Vec<usize> {
+        let mut candidates = vec![99];
+        grid.query(rect, &mut candidates);
+        candidates
+    }
+
+    #[test]
+    fn finds_objects_in_the_same_cells_once() {
+        let mut grid = SpatialGrid::new(400.0, 300.0);
+        // 1番は4マスにまたがる
+        let rects = [
+            (10.0, 10.0, 20.0, 20.0),
+            (90.0, 90.0, 20.0, 20.0),
+            (350.0, 250.0, 10.0, 10.0),
+        ];
+        grid.rebuild(rects.into_iter());
+        assert_eq!(query(&grid, (0.0, 0.0, 150.0, 150.0)), vec![0, 1]);
+        assert_eq!(query(&grid, (105.0, 105.0, 1.0, 1.0)), vec![1]);
+        assert_eq!(query(&grid, (200.0, 0.0, 10.0, 10.0)), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn objects_outside_the_field_go_to_the_edge_cells() {
+        let mut grid = SpatialGrid::new(200.0, 200.0);
+        grid.rebuild([(-80.0, -60.0, 30.0, 30.0), (250.0, 500.0, 10.0, 10.0)].into_iter());
+        assert_eq!(query(&grid, (0.0, 0.0, 1.0, 1.0)), vec![0]);
+        assert_eq!(query(&grid, (-500.0, -500.0, 1.0, 1.0)), vec![0]);
+        assert_eq!(query(&grid, (199.0, 199.0, 1.0, 1.0)), vec![1]);
+    }
+
+    #[test]
+    fn rebuild_forgets_the_previous_frame() {
+        let mut grid = SpatialGrid::new(200.0, 200.0);
+        grid.rebuild([(10.0, 10.0, 10.0, 10.0)].into_iter());
+        grid.rebuild([(150.0, 150.0, 10.0, 10.0)].into_iter());
+        assert_eq!(query(&grid, (10.0, 10.0, 10.0, 10.0)), Vec::<usize>::new());
+        assert_eq!(query(&grid, (150.0, 150.0, 10.0, 10.0)), vec![0]);
+    }
+
+    // 重なっている組み合わせは必ず候補に入る(全組み合わせの判定と結果が変わらない)
+    #[test]
+    fn never_misses_an_overlap() {
+        let mut rng = Rng::new(42);
+        let random_rect = |rng: &mut Rng| {
+            (
+                rng.next_f64() * 700.0 - 50.0,
+                rng.next_f64() * 900.0 - 50.0,
+                rng.next_f64() * 150.0,
+                rng.next_f64() * 150.0,
+            )
+        };
+        let mut grid = SpatialGrid::new(600.0, 800.0);
+        let mut candidates = Vec::new();
+        for _ in 0..50 {
+            let rects: Vec<Rect> = (0..40).map(|_| random_rect(&mut rng)).collect();
+            grid.rebuild(rects.iter().copied());
+            for _ in 0..20 {
+                let probe = random_rect(&mut rng);
+                grid.query(probe, &mut candidates);
+                for (index, rect) in rects.iter().enumerate() {
+                    if rects_overlap(probe, *rect) {
+                        assert!(candidates.contains(&index));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+// キー入力をゲーム内の操作に置き換える
+
+// ゲーム内の操作
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InputAction {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Fire,
+    Bomb,
+    Laser,
+    Missile,
+    Pause,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 9] = [
+        InputAction::MoveLeft,
+        InputAction::MoveRight,
+        InputAction::MoveUp,
+        InputAction::MoveDown,
+        InputAction::Fire,
+        InputAction::Bomb,
+        InputAction::Laser,
+        InputAction::Missile,
+        InputAction::Pause,
+    ];
+
+    // 設定画面とやり取りするときの名前
+    pub fn name(self) -> &'static str {
+        match self {
+            InputAction::MoveLeft => "MoveLeft",
+            InputAction::MoveRight => "MoveRight",
+            InputAction::MoveUp => "MoveUp",
+            InputAction::MoveDown => "MoveDown",
+            InputAction::Fire => "Fire",
+            InputAction::Bomb => "Bomb",
+            InputAction::Laser => "Laser",
+            InputAction::Missile => "Missile",
+            InputAction::Pause => "Pause",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<InputAction> {
+        InputAction::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+// Shiftの有無で変わる1文字のキーを小文字にそろえる
+// "A" で押して "a" で離された場合も同じキーとして扱うため
+pub fn normalize_key(key: String) -> String {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_uppercase() => c.to_lowercase().collect(),
+        _ => key,
+    }
+}
+
+// 画面に出すキーの名前(矢印キーは記号、空白はSpaceにする)
+pub fn key_label(key: &str) -> &str {
+    match key {
+        " " => "Space",
+        "ArrowLeft" => "←",
+        "ArrowRight" => "→",
+        "ArrowUp" => "↑",
+        "ArrowDown" => "↓",
+        key => key,
+    }
+}
+
+// キー(KeyboardEvent.key)から操作への対応表
+// 1つの操作に複数のキーを割り当てられる
+pub struct KeyBindings {
+    pub bindings: Vec<(String, InputAction)>,
+}
+
+impl KeyBindings {
+    pub fn action_for(&self, key: &str) -> Option<InputAction> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| bound == key)
+            .map(|&(_, action)| action)
+    }
+
+    // 操作に割り当てるキーを1つに置き換える。そのキーが別の操作に使われていれば外す
+    pub fn rebind(&mut self, action: InputAction, key: &str) {
+        let key = normalize_key(key.to_string());
+        self.bindings
+            .retain(|(bound, bound_action)| *bound_action != action && *bound != key);
+        self.bindings.push((key, action));
+    }
+
+    // 操作に割り当てられているキーの一覧
+    pub fn keys_for(&self, action: InputAction) -> impl Iterator<Item = &str> {
+        self.bindings
+            .iter()
+            .filter(move |(_, bound_action)| *bound_action == action)
+            .map(|(key, _)| key.as_str())
+    }
+
+    // タイトル画面に出す操作の説明。操作ごとに最初に割り当てたキーを出す
+    pub fn menu_hints(&self) -> [String; 2] {
+        let key = |action| self.keys_for(action).next().map_or("-", key_label);
+        [
+            format!(
+                "Move {}{}{}{}   Fire {} (hold to charge)",
+                key(InputAction::MoveUp),
+                key(InputAction::MoveLeft),
+                key(InputAction::MoveDown),
+                key(InputAction::MoveRight),
+                key(InputAction::Fire)
+            ),
+            format!(
+                "Bomb {}   Laser {}   Missile {}   Pause {}",
+                key(InputAction::Bomb),
+                key(InputAction::Laser),
+                key(InputAction::Missile),
+                key(InputAction::Pause)
+            ),
+        ]
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let defaults = [
+            ("ArrowLeft", InputAction::MoveLeft),
+            ("a", InputAction::MoveLeft),
+            ("ArrowRight", InputAction::MoveRight),
+            ("d", InputAction::MoveRight),
+            ("ArrowUp", InputAction::MoveUp),
+            ("w", InputAction::MoveUp),
+            ("ArrowDown", InputAction::MoveDown),
+            ("s", InputAction::MoveDown),
+            (" ", InputAction::Fire),
+            ("Space", InputAction::Fire),
+            ("b", InputAction::Bomb),
+            ("l", InputAction::Laser),
+            ("f", InputAction::Missile),
+            ("p", InputAction::Pause),
+            ("Escape", InputAction::Pause),
+        ];
+        KeyBindings {
+            bindings: defaults
+                .iter()
+                .map(|&(key, action)| (key.to_string(), action))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Game, Orientation, MAX_GAME_SPEED};
+
+// プレイの公正さに影響する設定が使われたかの記録
+// 一度立った印はプレイ中に設定を戻しても下ろさない(リセットでのみ消える)
+#[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RunFlags {
+    pub assist_speed: bool,     // アシスト速度(等速未満)
+    pub practice: bool,         // ライフの減らない練習モード(プレイの開始時に立てる)
+    pub custom_config: bool,    // ページから渡された設定で自機やライフ、敵の間隔を変えた
+    pub gameplay_toggles: bool, // 左右のループや画面の向きなど、遊び方を変える切り替え
+}
+
+// ビット列での位置。1番は以前の時間倍率の印が使っていたので空けておく
+const ASSIST_SPEED_BIT: u8 = 1;
+const PRACTICE_BIT: u8 = 1 << 2;
+const CUSTOM_CONFIG_BIT: u8 = 1 << 3;
+const GAMEPLAY_TOGGLES_BIT: u8 = 1 << 4;
+
+impl RunFlags {
+    // ランキングや自己ベストの対象になるプレイかどうか
+    pub fn is_ranked(&self) -> bool {
+        self.names().next().is_none()
+    }
+
+    // 立っている印の名前
+    pub fn names(&self) -> impl Iterator<Item = &'static str> {
+        [
+            (self.assist_speed, "assist_speed"),
+            (self.practice, "practice"),
+            (self.custom_config, "custom_config"),
+            (self.gameplay_toggles, "gameplay_toggles"),
+        ]
+        .into_iter()
+        .filter(|&(set, _)| set)
+        .map(|(_, name)| name)
+    }
+
+    // 記録やダイジェストに含めるためのビット列
+    pub fn to_bits(self) -> u8 {
+        [
+            (self.assist_speed, ASSIST_SPEED_BIT),
+            (self.practice, PRACTICE_BIT),
+            (self.custom_config, CUSTOM_CONFIG_BIT),
+            (self.gameplay_toggles, GAMEPLAY_TOGGLES_BIT),
+        ]
+        .into_iter()
+        .filter(|&(set, _)| set)
+        .fold(0, |bits, (_, bit)| bits | bit)
+    }
+
+    // to_bits() で作ったビット列から戻す
+    pub fn from_bits(bits: u8) -> Self {
+        RunFlags {
+            assist_speed: bits & ASSIST_SPEED_BIT != 0,
+            practice: bits & PRACTICE_BIT != 0,
+            custom_config: bits & CUSTOM_CONFIG_BIT != 0,
+            gameplay_toggles: bits & GAMEPLAY_TOGGLES_BIT != 0,
+        }
+    }
+}
+
+impl Game {
+    // 今の設定を見て印を立てる。設定を変える入口と毎フレームの更新で呼ぶ
+    pub fn latch_run_flags(&mut self) {
+        let flags = &mut self.run_flags;
+        flags.assist_speed |= self.assist_speed < MAX_GAME_SPEED;
+        flags.custom_config |= self.config.changes_gameplay();
+        flags.gameplay_toggles |= self.wrap_horizontal || self.orientation != Orientation::BottomUp;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::render::mock::headless_game;
+    use crate::game::GameConfig;
+
+    #[test]
+    fn flags_stay_set_after_the_setting_is_turned_back_off() {
+        let (game, _) = headless_game(1);
+        let mut game = game.borrow_mut();
+        game.begin_run();
+        assert!(game.run_flags.is_ranked());
+
+        game.set_wrap_horizontal(true);
+        game.set_wrap_horizontal(false);
+        game.set_game_speed(0.5);
+        game.set_game_speed(1.0);
+        game.update(16.0);
+        assert!(game.run_flags.gameplay_toggles);
+        assert!(game.run_flags.assist_speed);
+        assert!(!game.run_flags.is_ranked());
+    }
+
+    #[test]
+    fn a_gameplay_config_flags_the_run_but_the_canvas_id_does_not() {
+        let (game, _) = headless_game(1);
+        let mut game = game.borrow_mut();
+        game.set_config(GameConfig {
+            canvas_id: "embedded".to_string(),
+            ..GameConfig::default()
+        });
+        assert!(game.run_flags.is_ranked());
+        game.set_config(GameConfig {
+            starting_lives: Some(99),
+            ..GameConfig::default()
+        });
+        assert!(game.run_flags.custom_config);
+    }
+
+    #[test]
+    fn reset_clears_the_flags_of_settings_no_longer_in_use() {
+        let (game, _) = headless_game(1);
+        let mut game = game.borrow_mut();
+        game.begin_run();
+        game.set_wrap_horizontal(true);
+        game.set_wrap_horizontal(false);
+        game.reset(0.0);
+        assert_eq!(game.run_flags, RunFlags::default());
+
+        // 使い続けている設定は次のプレイにも印を付ける
+        game.set_orientation(Orientation::LeftToRight, 0.0);
+        assert!(game.run_flags.gameplay_toggles);
+    }
+
+    #[test]
+    fn undo_restores_the_flags_from_before_the_reset() {
+        let (game, _) = headless_game(1);
+        let mut game = game.borrow_mut();
+        game.begin_run();
+        game.set_wrap_horizontal(true);
+        game.set_wrap_horizontal(false);
+        game.reset(0.0);
+        assert!(game.run_flags.is_ranked());
+        game.undo_reset();
+        assert!(game.run_flags.gameplay_toggles);
+    }
+
+    #[test]
+    fn bits_round_trip() {
+        for bits in 0..32u8 {
+            let flags = RunFlags::from_bits(bits);
+            assert_eq!(RunFlags::from_bits(flags.to_bits()), flags);
+        }
+        let all = RunFlags {
+            assist_speed: true,
+            practice: true,
+            custom_config: true,
+            gameplay_toggles: true,
+        };
+        assert_eq!(all.to_bits(), 0b11101);
+        assert_eq!(RunFlags::from_bits(all.to_bits()), all);
+    }
+}
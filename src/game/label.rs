@@ -0,0 +1,100 @@
+// 数値を含む表示用の文字列を、buffer を使い回して作る(毎フレームの確保を避ける)
+
+// 数値を10進で buffer の末尾に足す。width 桁に満たなければ先頭を0で埋める
+pub fn push_number(buffer: &mut String, value: u64, width: usize) {
+    let mut digits = [0u8; 20];
+    let mut len = 0;
+    let mut rest = value;
+    loop {
+        digits[len] = b'0' + (rest % 10) as u8;
+        len += 1;
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+    }
+
+    for _ in len..width {
+        buffer.push('0');
+    }
+    for &digit in digits[..len].iter().rev() {
+        buffer.push(digit as char);
+    }
+}
+
+// 数値を10進の文字列として buffer に書き込む
+pub fn format_number(value: u32, buffer: &mut String) -> &str {
+    buffer.clear();
+    push_number(buffer, value as u64, 1);
+    buffer
+}
+
+// "SCORE 120" のように見出し、数値、後ろに付ける文字を並べて buffer に書き込む
+pub fn format_label<'a>(buffer: &'a mut String, prefix: &str, value: u32, suffix: &str) -> &'a str {
+    buffer.clear();
+    buffer.push_str(prefix);
+    push_number(buffer, value as u64, 1);
+    buffer.push_str(suffix);
+    buffer
+}
+
+// 経過時間を "mm:ss" の形で buffer に書き込む(1時間を超えても分で数え続ける)
+pub fn format_clock(ms: f64, buffer: &mut String) -> &str {
+    let seconds = (ms / 1000.0) as u64;
+    buffer.clear();
+    push_number(buffer, seconds / 60, 2);
+    buffer.push(':');
+    push_number(buffer, seconds % 60, 2);
+    buffer
+}
+
+// 倍率を "ASSIST x0.75" のように小数2桁で buffer に書き込む
+pub fn format_ratio<'a>(buffer: &'a mut String, prefix: &str, ratio: f64) -> &'a str {
+    let hundredths = (ratio.max(0.0) * 100.0).round() as u64;
+    buffer.clear();
+    buffer.push_str(prefix);
+    push_number(buffer, hundredths / 100, 1);
+    buffer.push('.');
+    push_number(buffer, hundredths % 100, 2);
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_numbers_into_the_same_buffer() {
+        let mut buffer = String::new();
+        assert_eq!(format_number(0, &mut buffer), "0");
+        assert_eq!(format_number(u32::MAX, &mut buffer), "4294967295");
+        assert_eq!(format_label(&mut buffer, "SCORE ", 120, ""), "SCORE 120");
+        assert_eq!(format_label(&mut buffer, "MISSILE ", 3, "s"), "MISSILE 3s");
+    }
+
+    #[test]
+    fn formats_clock_with_padded_minutes_past_an_hour() {
+        let mut buffer = String::new();
+        assert_eq!(format_clock(0.0, &mut buffer), "00:00");
+        assert_eq!(format_clock(65_999.0, &mut buffer), "01:05");
+        assert_eq!(format_clock(3_725_000.0, &mut buffer), "62:05");
+    }
+
+    #[test]
+    fn formats_ratio_with_two_decimals() {
+        let mut buffer = String::new();
+        assert_eq!(format_ratio(&mut buffer, "ASSIST x", 0.75), "ASSIST x0.75");
+        assert_eq!(format_ratio(&mut buffer, "ASSIST x", 0.5), "ASSIST x0.50");
+        assert_eq!(format_ratio(&mut buffer, "x", 1.0), "x1.00");
+    }
+
+    #[test]
+    fn reuses_the_buffer_allocation() {
+        let mut buffer = String::with_capacity(32);
+        let capacity = buffer.capacity();
+        for score in 0..1000 {
+            format_label(&mut buffer, "SCORE ", score, "");
+        }
+        assert_eq!(buffer.capacity(), capacity);
+    }
+}
@@ -0,0 +1,250 @@
+use serde::Deserialize;
+
+use crate::game::modifier::WaveModifier;
+use crate::game::{EnemyKind, Wave};
+
+// 敵の出方やスコアの調整値。assets/levels.json から読み込み、
+// 書かれていない項目は組み込みの値を使う
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[serde(default)]
+pub struct LevelConfig {
+    pub small: EnemyDef,
+    pub armored: EnemyDef,
+    pub boss: EnemyDef,
+    pub kamikaze: EnemyDef,
+    pub armored_chance: f64, // 通常の敵の代わりに装甲付きの敵を出す確率
+    pub kamikaze_chance: f64, // 通常の敵の代わりに特攻する敵を出す確率
+    pub kamikaze_min_score: u32, // このスコアを超えるまでは特攻する敵を出さない
+    pub boss_interval: u32, // ボスが現れるスコアの間隔
+    pub extra_life_interval: u32, // ライフが1増えるスコアの間隔
+    pub max_lives: u32, // ライフの上限(これ以上は増えない)
+    pub escape_costs_life: bool, // 画面下まで抜けた敵1体につきライフを1減らすか
+    pub max_enemies: usize, // 同時に場に出せる敵の数(出現待ちを含む)。超える分は空くまで待たせる
+    pub damage_taken: u32, // 被弾1回で失うライフ
+    pub score_multiplier: u32, // 撃破時のスコアに掛ける倍率
+    pub fog: bool, // 場を霧で覆い、自機の周りしか見えなくするか
+    pub waves: WaveDef,
+    pub bomb_meter: BombMeterDef,
+    pub wave_modifiers: WaveModifierDef,
+}
+
+// 敵の種類ごとの値
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(default)]
+pub struct EnemyDef {
+    pub hp: u32,
+    pub score: u32, // 撃破時に得られるスコア
+    pub fire_chance: f64, // 一定間隔で弾を撃つ敵になる確率
+}
+
+// ボムのメーターの溜まり方
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(default)]
+pub struct BombMeterDef {
+    pub per_kill: u32, // 1体倒すごとに溜まる量(%)
+    pub per_boss_phase: u32, // ボスの体力を1段階削るごとに溜まる量(%)
+    pub boss_phases: u32, // ボスの体力を区切る段階の数
+    pub reset_on_death: bool, // 被弾してライフを失ったら空にするか
+}
+
+// 波に掛ける変化の選び方。speed から fragile はそれぞれの変化が選ばれる重み
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(default)]
+pub struct WaveModifierDef {
+    pub chance: f64, // 波に変化を掛ける確率
+    pub first_wave: u32, // 変化を掛け始める波の番号
+    pub speed: u32,
+    pub swarm: u32,
+    pub darkness: u32,
+    pub fragile: u32,
+}
+
+// 波の作り方。番号が進むごとに数と速さを増やし、間隔を縮める
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(default)]
+pub struct WaveDef {
+    pub first_count: u32, // 最初の波の敵の数
+    pub count_step: u32, // 1波ごとに増やす数
+    pub min_speed: f64, // 最初の波の速さの範囲(1ステップあたりのpx)
+    pub max_speed: f64,
+    pub speed_step: f64, // 1波ごとに速さの範囲をずらす量
+    pub speed_cap: f64, // 速さの上限
+    pub spawn_interval: f64, // 最初の波で敵を出す間隔(ms)
+    pub interval_decay: f64, // 1波ごとに間隔に掛ける倍率
+    pub min_spawn_interval: f64, // 間隔の下限(ms)
+}
+
+impl Default for LevelConfig {
+    fn default() -> Self {
+        LevelConfig {
+            small: EnemyDef {
+                hp: 1,
+                score: 1,
+                fire_chance: 0.3,
+            },
+            armored: EnemyDef {
+                hp: 3,
+                score: 3,
+                fire_chance: 0.0,
+            },
+            boss: EnemyDef {
+                hp: 40,
+                score: 20,
+                fire_chance: 0.0,
+            },
+            kamikaze: EnemyDef {
+                hp: 1,
+                score: 2,
+                fire_chance: 0.0,
+            },
+            armored_chance: 0.2,
+            kamikaze_chance: 0.2,
+            kamikaze_min_score: 15,
+            boss_interval: 25,
+            extra_life_interval: 50,
+            max_lives: 5,
+            escape_costs_life: true,
+            max_enemies: 30,
+            damage_taken: 1,
+            score_multiplier: 1,
+            fog: false,
+            waves: WaveDef::default(),
+            bomb_meter: BombMeterDef::default(),
+            wave_modifiers: WaveModifierDef::default(),
+        }
+    }
+}
+
+impl Default for EnemyDef {
+    fn default() -> Self {
+        LevelConfig::default().small
+    }
+}
+
+impl Default for BombMeterDef {
+    fn default() -> Self {
+        BombMeterDef {
+            per_kill: 2,
+            per_boss_phase: 10,
+            boss_phases: 4,
+            reset_on_death: true,
+        }
+    }
+}
+
+impl Default for WaveModifierDef {
+    fn default() -> Self {
+        WaveModifierDef {
+            chance: 0.25,
+            first_wave: 3,
+            speed: 3,
+            swarm: 3,
+            darkness: 2,
+            fragile: 1,
+        }
+    }
+}
+
+impl Default for WaveDef {
+    fn default() -> Self {
+        WaveDef {
+            first_count: 6,
+            count_step: 2,
+            min_speed: 2.0,
+            max_speed: 5.0,
+            speed_step: 0.25,
+            speed_cap: 9.0,
+            spawn_interval: 1800.0,
+            interval_decay: 0.92,
+            min_spawn_interval: 400.0,
+        }
+    }
+}
+
+impl LevelConfig {
+    // JSONの文字列から読み込む
+    pub fn parse(text: &str) -> Result<LevelConfig, String> {
+        serde_json::from_str(text).map_err(|e| e.to_string())
+    }
+
+    pub fn enemy(&self, kind: EnemyKind) -> &EnemyDef {
+        match kind {
+            EnemyKind::Small => &self.small,
+            EnemyKind::Armored => &self.armored,
+            EnemyKind::Boss => &self.boss,
+            EnemyKind::Kamikaze => &self.kamikaze,
+        }
+    }
+
+    // 0点のたびにボスが出続けないよう、間隔は1以上にする
+    pub fn boss_interval(&self) -> u32 {
+        self.boss_interval.max(1)
+    }
+
+    pub fn extra_life_interval(&self) -> u32 {
+        self.extra_life_interval.max(1)
+    }
+}
+
+impl WaveModifierDef {
+    pub fn weight(&self, modifier: WaveModifier) -> u32 {
+        match modifier {
+            WaveModifier::Speed => self.speed,
+            WaveModifier::Swarm => self.swarm,
+            WaveModifier::Darkness => self.darkness,
+            WaveModifier::Fragile => self.fragile,
+        }
+    }
+}
+
+impl WaveDef {
+    // number番目(1から)の波
+    pub fn wave(&self, number: u32) -> Wave {
+        let level = number.saturating_sub(1);
+        let shift = level as f64 * self.speed_step;
+        Wave {
+            number,
+            enemy_count: self.first_count.saturating_add(self.count_step.saturating_mul(level)),
+            speed_range: (
+                (self.min_speed + shift).min(self.speed_cap),
+                (self.max_speed + shift).min(self.speed_cap),
+            ),
+            spawn_interval: (self.spawn_interval * self.interval_decay.powi(level as i32))
+                .max(self.min_spawn_interval),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waves_grow_faster_and_denser_up_to_their_limits() {
+        let waves = WaveDef::default();
+        let first = waves.wave(1);
+        assert_eq!(first.enemy_count, waves.first_count);
+        assert_eq!(first.speed_range, (waves.min_speed, waves.max_speed));
+        assert_eq!(first.spawn_interval, waves.spawn_interval);
+
+        let third = waves.wave(3);
+        assert_eq!(third.enemy_count, waves.first_count + waves.count_step * 2);
+        assert_eq!(third.speed_range.0, waves.min_speed + waves.speed_step * 2.0);
+        assert!(third.spawn_interval < first.spawn_interval);
+
+        // ずっと先の波でも上限と下限で止まる
+        let late = waves.wave(1000);
+        assert_eq!(late.speed_range, (waves.speed_cap, waves.speed_cap));
+        assert_eq!(late.spawn_interval, waves.min_spawn_interval);
+    }
+
+    #[test]
+    fn parses_partial_level_files() {
+        let level = LevelConfig::parse(r#"{"max_enemies": 5, "waves": {"first_count": 2}}"#)
+            .unwrap();
+        assert_eq!(level.max_enemies, 5);
+        assert_eq!(level.waves.first_count, 2);
+        assert_eq!(level.waves.count_step, WaveDef::default().count_step);
+        assert!(LevelConfig::parse("{").is_err());
+    }
+}
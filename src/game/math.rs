@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use std::ops::{Add, AddAssign, Sub};
+
+// 2次元のベクトル。位置(ゲーム内座標)と速度(1ステップあたりの移動量)に使う
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Vec2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Vec2 {
+    pub const fn new(x: f64, y: f64) -> Self {
+        Vec2 { x, y }
+    }
+
+    pub fn scale(self, factor: f64) -> Vec2 {
+        Vec2::new(self.x * factor, self.y * factor)
+    }
+
+    pub fn length(self) -> f64 {
+        self.x.hypot(self.y)
+    }
+
+    // 同じ向きで長さ1のベクトル。長さ0ならそのまま返す
+    pub fn normalize(self) -> Vec2 {
+        let length = self.length();
+        if length > 0.0 {
+            Vec2::new(self.x / length, self.y / length)
+        } else {
+            self
+        }
+    }
+
+    // self から other へ t(0.0〜1.0)の割合だけ進んだ位置
+    pub fn lerp(self, other: Vec2, t: f64) -> Vec2 {
+        Vec2::new(self.x + (other.x - self.x) * t, self.y + (other.y - self.y) * t)
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl AddAssign for Vec2 {
+    fn add_assign(&mut self, other: Vec2) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+// 座標を (x, y) の組で受け渡す関数(当たり判定や描画)とのやり取り用
+impl From<(f64, f64)> for Vec2 {
+    fn from((x, y): (f64, f64)) -> Vec2 {
+        Vec2::new(x, y)
+    }
+}
+
+impl From<Vec2> for (f64, f64) {
+    fn from(v: Vec2) -> (f64, f64) {
+        (v.x, v.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        let a = Vec2::new(1.0, 2.0);
+        let b = Vec2::new(3.0, -4.0);
+        assert_eq!(a + b, Vec2::new(4.0, -2.0));
+        assert_eq!(a - b, Vec2::new(-2.0, 6.0));
+        assert_eq!(b.scale(0.5), Vec2::new(1.5, -2.0));
+        let mut c = a;
+        c += b;
+        assert_eq!(c, a + b);
+    }
+
+    #[test]
+    fn length_and_normalize() {
+        let v = Vec2::new(3.0, 4.0);
+        assert_eq!(v.length(), 5.0);
+        assert_eq!(v.normalize(), Vec2::new(0.6, 0.8));
+        // 長さ0のベクトルはそのまま返す(NaNにしない)
+        assert_eq!(Vec2::default().normalize(), Vec2::default());
+    }
+
+    #[test]
+    fn lerp_between_points() {
+        let a = Vec2::new(0.0, 10.0);
+        let b = Vec2::new(10.0, 20.0);
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 1.0), b);
+        assert_eq!(a.lerp(b, 0.25), Vec2::new(2.5, 12.5));
+    }
+
+    #[test]
+    fn converts_to_and_from_tuples() {
+        let v: Vec2 = (1.5, -2.5).into();
+        assert_eq!(v, Vec2::new(1.5, -2.5));
+        let t: (f64, f64) = v.into();
+        assert_eq!(t, (1.5, -2.5));
+    }
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::game::Enemy;
+
+// 次のミサイルを撃てるようになるまでの時間(ms)
+pub const MISSILE_COOLDOWN: f64 = 10000.0;
+// 速さ(1ステップあたりのpx)
+const MISSILE_SPEED: f64 = 5.0;
+// 向きを変えられる速さ(ラジアン/ms)
+const MISSILE_TURN_RATE: f64 = 0.004;
+// 何にも当たらなかった場合に自爆するまでの時間(ms)
+const MISSILE_LIFETIME: f64 = 3000.0;
+// 爆発の半径(px)と、範囲内の敵に与えるダメージ
+pub const MISSILE_BLAST_RADIUS: f64 = 80.0;
+pub const MISSILE_DAMAGE: u32 = 3;
+// 機体の長さ(px)
+const MISSILE_LENGTH: f64 = 14.0;
+
+// 最も近い敵へ向きを変えながら進み、敵に触れると爆発するミサイル
+#[derive(Serialize, Deserialize)]
+pub struct Missile {
+    pub x: f64, // 先端ではなく機体の中心
+    pub y: f64,
+    pub heading: f64, // 進む向き(ラジアン、x軸から時計回り)
+    pub remaining: f64, // 自爆までの残り時間(ms)
+}
+
+impl Missile {
+    // 中心座標を指定して、真上向きに生成
+    pub fn new(x: f64, y: f64) -> Self {
+        Missile {
+            x,
+            y,
+            heading: -FRAC_PI_2,
+            remaining: MISSILE_LIFETIME,
+        }
+    }
+
+    // 目標があればそちらへ向きを変え、1ステップ分進める。delta_timeはゲーム内時間(ms)
+    pub fn update(&mut self, target: Option<(f64, f64)>, delta_time: f64, step: f64) {
+        if let Some(target) = target {
+            self.heading =
+                steer(self.heading, (self.x, self.y), target, MISSILE_TURN_RATE * delta_time);
+        }
+        self.x += self.heading.cos() * MISSILE_SPEED * step;
+        self.y += self.heading.sin() * MISSILE_SPEED * step;
+        self.remaining -= delta_time;
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining <= 0.0
+    }
+
+    // 進む向きを指す三角形の頂点(先端、左後ろ、右後ろ)
+    pub fn corners(&self) -> [(f64, f64); 3] {
+        let (sin, cos) = self.heading.sin_cos();
+        let half = MISSILE_LENGTH / 2.0;
+        let wing = MISSILE_LENGTH / 3.0;
+        [
+            (self.x + cos * half, self.y + sin * half),
+            (self.x - cos * half - sin * wing, self.y - sin * half + cos * wing),
+            (self.x - cos * half + sin * wing, self.y - sin * half - cos * wing),
+        ]
+    }
+}
+
+// 現在の向きから目標への向きへ、最大でmax_turn(ラジアン)だけ回した向き
+pub fn steer(heading: f64, from: (f64, f64), to: (f64, f64), max_turn: f64) -> f64 {
+    let desired = (to.1 - from.1).atan2(to.0 - from.0);
+    // 回る量は -PI〜PI に収め、近い側へ回す
+    let turn = (desired - heading + PI).rem_euclid(TAU) - PI;
+    heading + turn.clamp(-max_turn, max_turn)
+}
+
+// 位置から最も近い敵の中心
+pub fn nearest_enemy(enemies: &[Enemy], (x, y): (f64, f64)) -> Option<(f64, f64)> {
+    enemies
+        .iter()
+        .map(|enemy| (enemy.pos.x + enemy.width / 2.0, enemy.pos.y + enemy.height / 2.0))
+        .min_by(|a, b| {
+            let da = (a.0 - x).hypot(a.1 - y);
+            let db = (b.0 - x).hypot(b.1 - y);
+            da.total_cmp(&db)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::EnemyKind;
+
+    #[test]
+    fn steer_turns_toward_the_target_by_at_most_max_turn() {
+        // 真上向きから真右の目標へは最大0.1ラジアンだけ回る
+        let heading = steer(-FRAC_PI_2, (0.0, 0.0), (100.0, 0.0), 0.1);
+        assert!((heading - (-FRAC_PI_2 + 0.1)).abs() < 1e-9);
+        // 十分に回れるなら目標の向きにそろう
+        let heading = steer(-FRAC_PI_2, (0.0, 0.0), (100.0, 0.0), 10.0);
+        assert!(heading.abs() < 1e-9);
+    }
+
+    #[test]
+    fn steer_takes_the_shorter_way_around() {
+        // 向き3.0から左下の目標(-2.9付近)へは、負の側へ大回りせず正の側へ回る
+        let heading = steer(3.0, (0.0, 0.0), (-100.0, -25.0), 0.05);
+        assert!((heading - 3.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn nearest_enemy_picks_the_closest_center() {
+        assert_eq!(nearest_enemy(&[], (0.0, 0.0)), None);
+        let far = Enemy::for_test(EnemyKind::Small, 300.0, 0.0);
+        let near = Enemy::for_test(EnemyKind::Small, 40.0, 20.0);
+        let center = (near.pos.x + near.width / 2.0, near.pos.y + near.height / 2.0);
+        assert_eq!(nearest_enemy(&[far, near], (0.0, 0.0)), Some(center));
+    }
+
+    #[test]
+    fn flies_straight_up_without_a_target_and_expires() {
+        let mut missile = Missile::new(100.0, 500.0);
+        missile.update(None, 1000.0, 1.0);
+        assert!((missile.x - 100.0).abs() < 1e-9);
+        assert!((missile.y - (500.0 - MISSILE_SPEED)).abs() < 1e-9);
+        assert!(!missile.is_expired());
+        missile.update(None, MISSILE_LIFETIME - 1000.0, 1.0);
+        assert!(missile.is_expired());
+    }
+}
@@ -2,11 +2,86 @@ mod player;
 mod bullet;
 mod enemy;
 mod game_state;
+mod spawn;
+mod replay;
+mod power_up;
+mod effect;
+mod pattern;
+mod collision;
+mod timer;
+mod render;
+mod draw;
+mod debris;
+mod rng;
+mod submission;
+mod input;
+mod orientation;
+mod particles;
+mod sound;
+mod ghost;
+mod integrity;
+mod undo;
+mod stats;
+mod event;
+mod wave;
+mod level;
+mod combo;
+mod weapon;
+mod missile;
+mod formation;
+mod mode;
+mod difficulty;
+mod config;
+mod frame_stats;
+mod math;
+mod grid;
+mod label;
+mod bomb_meter;
+mod modifier;
 
-pub use player::Player;
-pub use bullet::Bullet;
-pub use enemy::Enemy;
+pub use player::{move_direction, Player};
+pub use bullet::{Bullet, BulletOwner, BulletPool};
+pub use enemy::{Enemy, MovementPattern, BOSS_SIZE};
 pub use game_state::GameState;
+pub use spawn::PendingSpawn;
+pub use replay::{ReplayBuffer, Snapshot};
+pub use power_up::{PowerUp, PowerUpKind};
+pub use effect::{Blast, CritEffect, ScorePopup};
+pub use pattern::{BulletPattern, PatternRunner};
+pub use timer::{GameClock, GameTimer};
+pub use render::{AudioSink, Renderer, Sprite, TextAlign, TextStyle};
+// ブラウザで動かすテストから使う
+#[cfg(all(test, target_arch = "wasm32"))]
+pub use render::mock;
+pub use debris::{Debris, DebrisPool};
+pub use rng::Rng;
+pub use submission::ScoreSubmission;
+pub use input::{normalize_key, InputAction, KeyBindings};
+pub use orientation::{Basis, Orientation};
+pub use particles::Particle;
+pub use sound::{
+    EnemyKind, KillSounds, BOMB_READY_SOUND, BOSS_WARNING_SOUND, ESCAPE_SOUND, EXTRA_LIFE_SOUND,
+    SHIELD_BREAK_SOUND,
+};
+pub use ghost::GhostBuffer;
+pub use integrity::RunFlags;
+pub use undo::RunStash;
+pub use stats::{RunHistory, RunResult, RunStore, RunSummary};
+pub use event::GameEvent;
+pub use wave::Wave;
+pub use level::LevelConfig;
+pub use combo::Combo;
+pub use missile::Missile;
+pub use mode::GameMode;
+pub use difficulty::Difficulty;
+pub use config::GameConfig;
+pub use frame_stats::FrameStats;
+pub use math::Vec2;
+pub use grid::SpatialGrid;
+pub use label::format_number;
+pub use bomb_meter::BombMeter;
+pub use modifier::WaveModifier;
 
+#[allow(clippy::module_inception)]
 mod game;
-pub use game::Game;
+pub use game::{Game, MAX_GAME_SPEED, MIN_GAME_SPEED};
@@ -0,0 +1,57 @@
+// スコアアタックの制限時間(一時停止中を除いた実時間、ms)
+pub const SCORE_ATTACK_TIME_LIMIT: f64 = 90000.0;
+
+// 遊び方。タイトル画面で選び、記録はモードごとに分けて保存する
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameMode {
+    Endless,     // ライフが尽きるまで続ける
+    ScoreAttack, // ライフは減らず、制限時間内のスコアを競う
+    Practice,    // ライフは減らず終わりもない。被弾した回数を数える
+}
+
+impl GameMode {
+    // タイトル画面で選ぶ順
+    pub const ALL: [GameMode; 3] = [GameMode::Endless, GameMode::ScoreAttack, GameMode::Practice];
+
+    pub fn from_name(name: &str) -> Option<GameMode> {
+        GameMode::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    // start_game_with_mode で指定する名前(保存する記録のキーにも使う)
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Endless => "endless",
+            GameMode::ScoreAttack => "score_attack",
+            GameMode::Practice => "practice",
+        }
+    }
+
+    // タイトル画面の表示
+    pub fn label(self) -> &'static str {
+        match self {
+            GameMode::Endless => "ENDLESS",
+            GameMode::ScoreAttack => "SCORE ATTACK",
+            GameMode::Practice => "PRACTICE",
+        }
+    }
+
+    // 選択肢を step 個ずらしたモード(端では反対側へ回る)
+    pub fn cycle(self, step: isize) -> GameMode {
+        let count = GameMode::ALL.len() as isize;
+        let index = GameMode::ALL.iter().position(|mode| *mode == self).unwrap_or(0) as isize;
+        GameMode::ALL[(index + step).rem_euclid(count) as usize]
+    }
+
+    // 制限時間(ms)。時間切れのないモードはNone
+    pub fn time_limit(self) -> Option<f64> {
+        match self {
+            GameMode::ScoreAttack => Some(SCORE_ATTACK_TIME_LIMIT),
+            GameMode::Endless | GameMode::Practice => None,
+        }
+    }
+
+    // 被弾や敵に抜けられてもライフが減らないか
+    pub fn unlimited_lives(self) -> bool {
+        matches!(self, GameMode::ScoreAttack | GameMode::Practice)
+    }
+}
@@ -0,0 +1,249 @@
+// 1回の波だけ遊び方を変える変化。波の始まりに調整値へ重ね、波の終わりに外す
+// 変化ごとに調整値へ重ねる処理と、重ねた値を元に戻す処理を組にして登録する
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::level::{EnemyDef, WaveModifierDef};
+use crate::game::{LevelConfig, Rng, Wave};
+
+// スピードの波で敵の速さに掛ける倍率と、撃破1体ごとに足すスコア
+pub const SPEED_WAVE_MULTIPLIER: f64 = 1.4;
+pub const SPEED_WAVE_KILL_BONUS: u32 = 1;
+// 群れの波で出す敵の数に掛ける倍率(出す間隔は同じ倍率で縮める)
+pub const SWARM_WAVE_MULTIPLIER: u32 = 2;
+// もろい波で被弾時に失うライフとスコアに掛ける倍率
+pub const FRAGILE_DAMAGE_MULTIPLIER: u32 = 2;
+pub const FRAGILE_SCORE_MULTIPLIER: u32 = 3;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum WaveModifier {
+    Speed,    // 敵が速く、撃破ごとのスコアが増える
+    Swarm,    // 敵の数が倍で、体力が半分
+    Darkness, // 場が霧に覆われる
+    Fragile,  // 被弾時に失うライフが倍で、スコアが3倍
+}
+
+// 変化を重ねる前の調整値。外すときはここから書き戻し、倍率の丸めを残さない
+#[derive(Clone, PartialEq, Debug)]
+pub struct ModifierBase {
+    pub level: LevelConfig,
+    pub wave: Wave,
+}
+
+// 登録する変化の内容
+// apply で調整値に重ね、revert で apply が変えた値だけを重ねる前の値に戻す
+pub struct ModifierEntry {
+    pub name: &'static str, // 休憩中に波の番号の下に出す名前
+    pub icon: &'static str, // 波の間HUDに出す印
+    pub color: &'static str, // 名前と印の色
+    pub apply: fn(&mut LevelConfig, &mut Wave),
+    pub revert: fn(&mut LevelConfig, &mut Wave, &ModifierBase),
+}
+
+// 変化の登録表(WaveModifier の並びと同じ順)
+pub const WAVE_MODIFIERS: [ModifierEntry; 4] = [
+    ModifierEntry {
+        name: "SPEED WAVE",
+        icon: "»",
+        color: "deepskyblue",
+        apply: |level, wave| {
+            let (min_speed, max_speed) = wave.speed_range;
+            wave.speed_range =
+                (min_speed * SPEED_WAVE_MULTIPLIER, max_speed * SPEED_WAVE_MULTIPLIER);
+            for def in enemy_defs(level) {
+                def.score += SPEED_WAVE_KILL_BONUS;
+            }
+        },
+        revert: |level, wave, base| {
+            wave.speed_range = base.wave.speed_range;
+            for (def, original) in enemy_defs(level).into_iter().zip(enemy_values(&base.level)) {
+                def.score = original.score;
+            }
+        },
+    },
+    ModifierEntry {
+        name: "SWARM WAVE",
+        icon: "∴",
+        color: "lime",
+        apply: |level, wave| {
+            wave.enemy_count = wave.enemy_count.saturating_mul(SWARM_WAVE_MULTIPLIER);
+            wave.spawn_interval /= SWARM_WAVE_MULTIPLIER as f64;
+            // ボスの体力はそのままにする
+            for def in [&mut level.small, &mut level.armored, &mut level.kamikaze] {
+                def.hp = def.hp.div_ceil(2);
+            }
+        },
+        revert: |level, wave, base| {
+            wave.enemy_count = base.wave.enemy_count;
+            wave.spawn_interval = base.wave.spawn_interval;
+            for (def, original) in enemy_defs(level).into_iter().zip(enemy_values(&base.level)) {
+                def.hp = original.hp;
+            }
+        },
+    },
+    ModifierEntry {
+        name: "DARKNESS WAVE",
+        icon: "◐",
+        color: "mediumpurple",
+        apply: |level, _| level.fog = true,
+        revert: |level, _, base| level.fog = base.level.fog,
+    },
+    ModifierEntry {
+        name: "FRAGILE WAVE",
+        icon: "!",
+        color: "red",
+        apply: |level, _| {
+            level.damage_taken = level.damage_taken.saturating_mul(FRAGILE_DAMAGE_MULTIPLIER);
+            level.score_multiplier =
+                level.score_multiplier.saturating_mul(FRAGILE_SCORE_MULTIPLIER);
+        },
+        revert: |level, _, base| {
+            level.damage_taken = base.level.damage_taken;
+            level.score_multiplier = base.level.score_multiplier;
+        },
+    },
+];
+
+impl WaveModifier {
+    pub const ALL: [WaveModifier; 4] = [
+        WaveModifier::Speed,
+        WaveModifier::Swarm,
+        WaveModifier::Darkness,
+        WaveModifier::Fragile,
+    ];
+
+    pub fn entry(self) -> &'static ModifierEntry {
+        &WAVE_MODIFIERS[self as usize]
+    }
+}
+
+// 進行中の波に掛けている変化と、掛ける前の調整値
+#[derive(Clone, PartialEq, Debug)]
+pub struct ActiveModifier {
+    pub modifier: WaveModifier,
+    base: ModifierBase,
+}
+
+impl ActiveModifier {
+    // 変化を調整値に重ねる
+    pub fn apply(modifier: WaveModifier, level: &mut LevelConfig, wave: &mut Wave) -> Self {
+        let base = ModifierBase {
+            level: level.clone(),
+            wave: *wave,
+        };
+        (modifier.entry().apply)(level, wave);
+        ActiveModifier { modifier, base }
+    }
+
+    // 重ねた値を重ねる前の値に戻す
+    pub fn revert(self, level: &mut LevelConfig, wave: &mut Wave) {
+        (self.modifier.entry().revert)(level, wave, &self.base);
+    }
+}
+
+// number番目の波に掛ける変化を、重み付きの表から選ぶ。掛けない波はNone
+pub fn roll_modifier(rng: &mut Rng, def: &WaveModifierDef, number: u32) -> Option<WaveModifier> {
+    if number < def.first_wave || rng.next_f64() >= def.chance {
+        return None;
+    }
+    let total: u32 = WaveModifier::ALL.iter().map(|&modifier| def.weight(modifier)).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut roll = (rng.next_f64() * total as f64) as u32;
+    for modifier in WaveModifier::ALL {
+        let weight = def.weight(modifier);
+        if roll < weight {
+            return Some(modifier);
+        }
+        roll -= weight;
+    }
+    None
+}
+
+// 種類ごとの値(LevelConfig::enemy と同じ並び)
+fn enemy_defs(level: &mut LevelConfig) -> [&mut EnemyDef; 4] {
+    [&mut level.small, &mut level.armored, &mut level.boss, &mut level.kamikaze]
+}
+
+fn enemy_values(level: &LevelConfig) -> [EnemyDef; 4] {
+    [level.small, level.armored, level.boss, level.kamikaze]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 倍率を掛けると丸めが出る値にしておく
+    fn tuning() -> (LevelConfig, Wave) {
+        let mut level = LevelConfig::default();
+        level.armored.hp = 3;
+        level.kamikaze.hp = 5;
+        let wave = Wave {
+            number: 4,
+            enemy_count: 7,
+            speed_range: (2.3, 5.7),
+            spawn_interval: 1333.3,
+        };
+        (level, wave)
+    }
+
+    #[test]
+    fn every_modifier_changes_the_tuning_and_reverts_it_exactly() {
+        for modifier in WaveModifier::ALL {
+            let (mut level, mut wave) = tuning();
+            let active = ActiveModifier::apply(modifier, &mut level, &mut wave);
+            assert!((level.clone(), wave) != tuning(), "{:?} changed nothing", modifier);
+            active.revert(&mut level, &mut wave);
+            assert_eq!((level, wave), tuning(), "{:?} did not revert", modifier);
+        }
+    }
+
+    #[test]
+    fn modifiers_overlay_the_described_values() {
+        let (mut level, mut wave) = tuning();
+        ActiveModifier::apply(WaveModifier::Speed, &mut level, &mut wave);
+        assert!((wave.speed_range.0 - 2.3 * 1.4).abs() < 1e-9);
+        assert_eq!(level.small.score, LevelConfig::default().small.score + 1);
+
+        let (mut level, mut wave) = tuning();
+        ActiveModifier::apply(WaveModifier::Swarm, &mut level, &mut wave);
+        assert_eq!(wave.enemy_count, 14);
+        assert_eq!((level.small.hp, level.armored.hp, level.kamikaze.hp), (1, 2, 3));
+        assert_eq!(level.boss.hp, LevelConfig::default().boss.hp);
+
+        let (mut level, mut wave) = tuning();
+        ActiveModifier::apply(WaveModifier::Fragile, &mut level, &mut wave);
+        assert_eq!((level.damage_taken, level.score_multiplier), (2, 3));
+    }
+
+    #[test]
+    fn rolls_follow_the_weighted_table() {
+        let mut def = WaveModifierDef {
+            chance: 1.0,
+            first_wave: 3,
+            speed: 0,
+            swarm: 3,
+            darkness: 0,
+            fragile: 1,
+        };
+        let mut rng = Rng::new(7);
+        assert_eq!(roll_modifier(&mut rng, &def, 2), None);
+        let rolls: Vec<_> = (0..400).filter_map(|_| roll_modifier(&mut rng, &def, 3)).collect();
+        assert_eq!(rolls.len(), 400);
+        let swarms = rolls.iter().filter(|&&m| m == WaveModifier::Swarm).count();
+        assert!((250..350).contains(&swarms), "{} swarm waves", swarms);
+        assert!(rolls.iter().all(|&m| matches!(m, WaveModifier::Swarm | WaveModifier::Fragile)));
+
+        // 同じシードなら同じ波に同じ変化が掛かる
+        let pick = |seed| roll_modifier(&mut Rng::new(seed), &def, 5);
+        assert_eq!(pick(11), pick(11));
+
+        def.chance = 0.0;
+        assert_eq!(roll_modifier(&mut rng, &def, 9), None);
+        def.chance = 1.0;
+        def.swarm = 0;
+        def.fragile = 0;
+        assert_eq!(roll_modifier(&mut rng, &def, 9), None);
+    }
+}
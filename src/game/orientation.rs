@@ -0,0 +1,141 @@
+use crate::game::collision::Rect;
+use crate::game::InputAction;
+
+// 画面の向き。ゲームの処理は常に「自機が下にいて上へ撃つ」座標で行い、
+// 描画と入力のときだけ画面の向きに変換する
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Orientation {
+    BottomUp,    // 自機が下、敵は上から来る
+    LeftToRight, // 自機が左、敵は右から来る
+}
+
+impl Orientation {
+    pub fn from_name(name: &str) -> Option<Orientation> {
+        match name {
+            "BottomUp" => Some(Orientation::BottomUp),
+            "LeftToRight" => Some(Orientation::LeftToRight),
+            _ => None,
+        }
+    }
+
+    // 画面の大きさからゲーム内の場の大きさを求める(逆変換も同じ計算になる)
+    pub fn field_size(self, (width, height): (f64, f64)) -> (f64, f64) {
+        match self {
+            Orientation::BottomUp => (width, height),
+            Orientation::LeftToRight => (height, width),
+        }
+    }
+
+    // 場の大きさに対応する座標変換
+    pub fn basis(self, (_, field_height): (f64, f64)) -> Basis {
+        match self {
+            Orientation::BottomUp => Basis {
+                origin: (0.0, 0.0),
+                lateral: (1.0, 0.0),
+                depth: (0.0, 1.0),
+            },
+            // 場の下端(自機側)が画面の左端、場の上端が画面の右端になる
+            Orientation::LeftToRight => Basis {
+                origin: (field_height, 0.0),
+                lateral: (0.0, 1.0),
+                depth: (-1.0, 0.0),
+            },
+        }
+    }
+
+    // 画面上の向きで押された操作を、ゲーム内の向きの操作に置き換える
+    pub fn local_action(self, action: InputAction) -> InputAction {
+        match (self, action) {
+            (Orientation::LeftToRight, InputAction::MoveUp) => InputAction::MoveLeft,
+            (Orientation::LeftToRight, InputAction::MoveDown) => InputAction::MoveRight,
+            (Orientation::LeftToRight, InputAction::MoveRight) => InputAction::MoveUp,
+            (Orientation::LeftToRight, InputAction::MoveLeft) => InputAction::MoveDown,
+            (_, action) => action,
+        }
+    }
+}
+
+// ゲーム内座標から画面座標への変換
+// 画面座標 = origin + x * lateral + y * depth
+#[derive(Clone, Copy)]
+pub struct Basis {
+    pub origin: (f64, f64),
+    pub lateral: (f64, f64), // ゲーム内のx方向が向く画面上の向き
+    pub depth: (f64, f64),   // ゲーム内のy方向(自機から見て手前)が向く画面上の向き
+}
+
+impl Basis {
+    pub fn point(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (
+            self.origin.0 + x * self.lateral.0 + y * self.depth.0,
+            self.origin.1 + x * self.lateral.1 + y * self.depth.1,
+        )
+    }
+
+    // 画面座標からゲーム内座標への逆変換(lateral と depth は直交する単位ベクトル)
+    pub fn local_point(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let (dx, dy) = (x - self.origin.0, y - self.origin.1);
+        (
+            dx * self.lateral.0 + dy * self.lateral.1,
+            dx * self.depth.0 + dy * self.depth.1,
+        )
+    }
+
+    // 矩形は左上と右下の点を変換し、画面上で左上が最小になるよう並べ直す
+    pub fn rect(&self, (x, y, width, height): Rect) -> Rect {
+        let a = self.point((x, y));
+        let b = self.point((x + width, y + height));
+        (a.0.min(b.0), a.1.min(b.1), (a.0 - b.0).abs(), (a.1 - b.1).abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: (f64, f64) = (640.0, 480.0);
+
+    #[test]
+    fn bottom_up_is_the_identity() {
+        let basis = Orientation::BottomUp.basis(Orientation::BottomUp.field_size(SCREEN));
+        assert_eq!(basis.point((12.0, 34.0)), (12.0, 34.0));
+        assert_eq!(basis.rect((1.0, 2.0, 3.0, 4.0)), (1.0, 2.0, 3.0, 4.0));
+        assert_eq!(Orientation::BottomUp.local_action(InputAction::MoveUp), InputAction::MoveUp);
+    }
+
+    #[test]
+    fn left_to_right_puts_the_player_side_on_the_left() {
+        let orientation = Orientation::LeftToRight;
+        let field = orientation.field_size(SCREEN);
+        assert_eq!(field, (480.0, 640.0));
+        let basis = orientation.basis(field);
+        // 場の下端(自機側)は画面の左端、上端は右端
+        assert_eq!(basis.point((0.0, field.1)), (0.0, 0.0));
+        assert_eq!(basis.point((field.0, 0.0)), (640.0, 480.0));
+        // 画面上でも左上が最小になる矩形にする
+        assert_eq!(basis.rect((10.0, 100.0, 50.0, 20.0)), (520.0, 10.0, 20.0, 50.0));
+    }
+
+    #[test]
+    fn local_point_reverses_point() {
+        for orientation in [Orientation::BottomUp, Orientation::LeftToRight] {
+            let basis = orientation.basis(orientation.field_size(SCREEN));
+            for point in [(0.0, 0.0), (123.0, 45.0), (480.0, 640.0)] {
+                assert_eq!(basis.local_point(basis.point(point)), point);
+            }
+        }
+    }
+
+    #[test]
+    fn screen_directions_map_to_field_directions() {
+        // 画面の右(敵の来る側)へ進むと場の上へ進む
+        let orientation = Orientation::LeftToRight;
+        assert_eq!(orientation.local_action(InputAction::MoveRight), InputAction::MoveUp);
+        assert_eq!(orientation.local_action(InputAction::MoveLeft), InputAction::MoveDown);
+        assert_eq!(orientation.local_action(InputAction::MoveUp), InputAction::MoveLeft);
+        assert_eq!(orientation.local_action(InputAction::MoveDown), InputAction::MoveRight);
+        assert_eq!(orientation.local_action(InputAction::Fire), InputAction::Fire);
+        assert_eq!(Orientation::from_name("LeftToRight"), Some(orientation));
+        assert_eq!(Orientation::from_name("Sideways"), None);
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::render::{color_name, ColorName};
+
+// 敵の撃破時に飛び散る火花
+pub const PARTICLE_LIFETIME: f64 = 500.0;
+// 同時に存在できる火花の上限(一度に何体も倒したときの描画負荷を抑える)
+pub const MAX_PARTICLES: usize = 200;
+// 火花の色
+pub const PARTICLE_COLORS: [&str; 3] = ["orange", "yellow", "red"];
+
+#[derive(Serialize, Deserialize)]
+pub struct Particle {
+    pub x: f64,
+    pub y: f64,
+    pub vx: f64, // 速度(px/ms)
+    pub vy: f64,
+    pub life: f64, // 残り時間(ms)
+    #[serde(with = "color_name")]
+    pub color: ColorName,
+}
+
+impl Particle {
+    // 経過時間に合わせて移動し、寿命を減らす
+    pub fn update(&mut self, delta_time: f64) {
+        self.x += self.vx * delta_time;
+        self.y += self.vy * delta_time;
+        self.life -= delta_time;
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.life > 0.0
+    }
+
+    // 不透明度(寿命に合わせて薄くなる)
+    pub fn alpha(&self) -> f64 {
+        (self.life / PARTICLE_LIFETIME).clamp(0.0, 1.0)
+    }
+}
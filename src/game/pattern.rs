@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+
+use std::f64::consts::PI;
+
+use crate::game::Bullet;
+
+// 敵の弾幕パターン
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum BulletPattern {
+    // 全方位に等間隔で発射
+    Ring { count: u32, speed: f64 },
+    // 角度をずらしながら連続で発射(rateは1秒あたりの発射数)
+    Spiral { rate: f64, angular_step: f64, speed: f64 },
+    // プレイヤーを狙って扇状に発射(spreadは両端の間の角度)
+    AimedFan { count: u32, spread: f64, speed: f64 },
+}
+
+// 全方位に等間隔に並んだ角度を返す
+pub fn ring_angles(count: u32, offset: f64) -> impl Iterator<Item = f64> {
+    let step = PI * 2.0 / count.max(1) as f64;
+    (0..count).map(move |i| offset + step * i as f64)
+}
+
+// 中心の角度を軸に左右対称に広がる角度を返す
+pub fn fan_angles(center: f64, count: u32, spread: f64) -> impl Iterator<Item = f64> {
+    let step = if count > 1 {
+        spread / (count - 1) as f64
+    } else {
+        0.0
+    };
+    let start = center - step * (count.saturating_sub(1)) as f64 / 2.0;
+    (0..count).map(move |i| start + step * i as f64)
+}
+
+// パターンを時間経過に応じて実行する
+// 経過時間を積算して発射するのでフレームレートに依存しない
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PatternRunner {
+    pub pattern: BulletPattern,
+    pub interval: f64, // Ring / AimedFan の発射間隔(ms)
+    accumulated: f64,
+    angle: f64, // Spiral の現在の角度
+}
+
+impl PatternRunner {
+    pub fn new(pattern: BulletPattern, interval: f64) -> Self {
+        PatternRunner {
+            pattern,
+            interval,
+            accumulated: 0.0,
+            angle: PI / 2.0,
+        }
+    }
+
+    // 1回の発射の間隔(ms)
+    fn emit_interval(&self) -> f64 {
+        match self.pattern {
+            BulletPattern::Spiral { rate, .. } => 1000.0 / rate.max(0.001),
+            _ => self.interval,
+        }
+    }
+
+    // 経過時間を進め、発射すべき弾をoutに追加する
+    pub fn update(
+        &mut self,
+        delta_time: f64,
+        origin: (f64, f64),
+        target: (f64, f64),
+        out: &mut Vec<Bullet>,
+    ) {
+        self.accumulated += delta_time;
+        let interval = self.emit_interval();
+        while self.accumulated >= interval {
+            self.accumulated -= interval;
+            self.emit(origin, target, out);
+        }
+    }
+
+    fn emit(&mut self, origin: (f64, f64), target: (f64, f64), out: &mut Vec<Bullet>) {
+        let (x, y) = origin;
+        match self.pattern {
+            BulletPattern::Ring { count, speed } => {
+                for angle in ring_angles(count, PI / 2.0) {
+                    out.push(Bullet::enemy_shot_with_angle(x, y, angle, speed));
+                }
+            }
+            BulletPattern::Spiral {
+                angular_step,
+                speed,
+                ..
+            } => {
+                out.push(Bullet::enemy_shot_with_angle(x, y, self.angle, speed));
+                self.angle = (self.angle + angular_step) % (PI * 2.0);
+            }
+            BulletPattern::AimedFan {
+                count,
+                spread,
+                speed,
+            } => {
+                let center = (target.1 - y).atan2(target.0 - x);
+                for angle in fan_angles(center, count, spread) {
+                    out.push(Bullet::enemy_shot_with_angle(x, y, angle, speed));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    // 弾の進む向き(ラジアン)
+    fn heading(bullet: &Bullet) -> f64 {
+        bullet.vel.y.atan2(bullet.vel.x)
+    }
+
+    #[test]
+    fn ring_angles_are_evenly_spaced() {
+        let angles: Vec<f64> = ring_angles(4, 0.0).collect();
+        assert_eq!(angles.len(), 4);
+        for (i, angle) in angles.iter().enumerate() {
+            assert!(close(*angle, PI / 2.0 * i as f64));
+        }
+        assert_eq!(ring_angles(0, 0.0).count(), 0);
+    }
+
+    #[test]
+    fn fan_angles_are_symmetric_around_the_center() {
+        let angles: Vec<f64> = fan_angles(1.0, 3, 0.6).collect();
+        assert!(close(angles[0], 0.7) && close(angles[1], 1.0) && close(angles[2], 1.3));
+        // 1発なら中心だけ
+        assert_eq!(fan_angles(1.0, 1, 0.6).collect::<Vec<_>>(), vec![1.0]);
+    }
+
+    #[test]
+    fn fires_by_accumulated_time_independent_of_frame_rate() {
+        let pattern = BulletPattern::Ring { count: 8, speed: 2.0 };
+        let mut coarse = PatternRunner::new(pattern, 1000.0);
+        let mut fine = PatternRunner::new(pattern, 1000.0);
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        coarse.update(2500.0, (0.0, 0.0), (0.0, 100.0), &mut a);
+        for _ in 0..150 {
+            fine.update(2500.0 / 150.0, (0.0, 0.0), (0.0, 100.0), &mut b);
+        }
+        assert_eq!(a.len(), 16);
+        assert_eq!(b.len(), 16);
+    }
+
+    #[test]
+    fn spiral_turns_a_step_per_shot_at_its_rate() {
+        let pattern = BulletPattern::Spiral {
+            rate: 4.0,
+            angular_step: 0.5,
+            speed: 2.0,
+        };
+        let mut runner = PatternRunner::new(pattern, 0.0);
+        let mut out = Vec::new();
+        runner.update(1000.0, (0.0, 0.0), (0.0, 0.0), &mut out);
+        assert_eq!(out.len(), 4);
+        for (i, bullet) in out.iter().enumerate() {
+            assert!(close(heading(bullet), PI / 2.0 + 0.5 * i as f64));
+        }
+    }
+
+    #[test]
+    fn aimed_fan_centers_on_the_target() {
+        let pattern = BulletPattern::AimedFan {
+            count: 3,
+            spread: 0.6,
+            speed: 3.0,
+        };
+        let mut runner = PatternRunner::new(pattern, 500.0);
+        let mut out = Vec::new();
+        runner.update(499.0, (100.0, 100.0), (200.0, 100.0), &mut out);
+        assert!(out.is_empty());
+        runner.update(1.0, (100.0, 100.0), (200.0, 100.0), &mut out);
+        let headings: Vec<f64> = out.iter().map(heading).collect();
+        assert!(close(headings[0], -0.3) && close(headings[1], 0.0) && close(headings[2], 0.3));
+        assert!(out.iter().all(|bullet| close(bullet.center().x, 100.0)));
+    }
+}
@@ -1,14 +1,77 @@
-use web_sys::HtmlImageElement;
+use serde::{Deserialize, Serialize};
 
+use crate::game::collision::{Collidable, Rect};
+use crate::game::Vec2;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Player {
-    pub x: f64,
-    pub y: f64,
+    pub pos: Vec2, // 左上の位置
     pub width: f64,
     pub height: f64,
     pub speed: f64,
-    pub image: HtmlImageElement, // プレイヤーの画像
+    pub hurtbox_size: (f64, f64), // 被弾判定の幅と高さ(自機の中央に置く)
 }
 
 impl Player {
-    // 必要に応じてメソッドを追加
+    // 中心の位置
+    pub fn center(&self) -> Vec2 {
+        self.pos + Vec2::new(self.width / 2.0, self.height / 2.0)
+    }
+
+    // 敵や敵の弾に当たったかを調べる矩形
+    // 画像の透明な縁に触れただけで被弾しないよう、画像より小さい矩形を中央に置く
+    // 移動できる範囲や描画、アイテムを拾う判定には画像の大きさ(bounds)を使う
+    pub fn hurtbox(&self) -> Rect {
+        let (width, height) = self.hurtbox_size;
+        (
+            self.pos.x + (self.width - width) / 2.0,
+            self.pos.y + (self.height - height) / 2.0,
+            width,
+            height,
+        )
+    }
+}
+
+impl Collidable for Player {
+    fn bounds(&self) -> Rect {
+        (self.pos.x, self.pos.y, self.width, self.height)
+    }
+}
+
+// 押されている方向から移動の向きを求める(長さ1、止まっていれば0)
+// 斜めに動くときも速さが変わらないよう、両方の軸が動くときは長さをそろえる
+pub fn move_direction(left: bool, right: bool, up: bool, down: bool) -> (f64, f64) {
+    let axis = |negative: bool, positive: bool| positive as i8 as f64 - negative as i8 as f64;
+    let (dx, dy) = (axis(left, right), axis(up, down));
+    if dx != 0.0 && dy != 0.0 {
+        (dx * std::f64::consts::FRAC_1_SQRT_2, dy * std::f64::consts::FRAC_1_SQRT_2)
+    } else {
+        (dx, dy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn length((dx, dy): (f64, f64)) -> f64 {
+        dx.hypot(dy)
+    }
+
+    #[test]
+    fn straight_and_diagonal_moves_have_the_same_speed() {
+        assert_eq!(move_direction(false, true, false, false), (1.0, 0.0));
+        assert_eq!(move_direction(false, false, true, false), (0.0, -1.0));
+        let diagonal = move_direction(true, false, false, true);
+        assert!(diagonal.0 < 0.0 && diagonal.1 > 0.0);
+        assert!((length(diagonal) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn opposite_keys_cancel_out() {
+        assert_eq!(move_direction(true, true, false, false), (0.0, 0.0));
+        assert_eq!(move_direction(false, false, false, false), (0.0, 0.0));
+        // 縦だけ打ち消し合う場合は横に等速で動く
+        assert_eq!(move_direction(false, true, true, true), (1.0, 0.0));
+    }
 }
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+// パワーアップの種類
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PowerUpKind {
+    ScoreBonus, // スコアを加算
+    SpeedBoost, // 一定時間移動速度を上げる
+    Ricochet,   // 一定時間、画面上端で弾が跳ね返る
+    Bomb,       // ボムを1つ補充
+    Shield,     // 一定時間、被弾を1回だけ防ぐ
+    Pierce,     // 一定時間、弾が倒した敵を突き抜ける
+    WeaponUp,   // 武器を1段階強化する
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 7] = [
+        PowerUpKind::ScoreBonus,
+        PowerUpKind::SpeedBoost,
+        PowerUpKind::Ricochet,
+        PowerUpKind::Bomb,
+        PowerUpKind::Shield,
+        PowerUpKind::Pierce,
+        PowerUpKind::WeaponUp,
+    ];
+
+    // 表示色
+    pub fn color(&self) -> &'static str {
+        match self {
+            PowerUpKind::ScoreBonus => "gold",
+            PowerUpKind::SpeedBoost => "deepskyblue",
+            PowerUpKind::Ricochet => "lime",
+            PowerUpKind::Bomb => "orangered",
+            PowerUpKind::Shield => "cyan",
+            PowerUpKind::Pierce => "orange",
+            PowerUpKind::WeaponUp => "violet",
+        }
+    }
+
+    // アイコンに表示する文字
+    pub fn label(&self) -> &'static str {
+        match self {
+            PowerUpKind::ScoreBonus => "$",
+            PowerUpKind::SpeedBoost => "S",
+            PowerUpKind::Ricochet => "R",
+            PowerUpKind::Bomb => "B",
+            PowerUpKind::Shield => "O",
+            PowerUpKind::Pierce => "P",
+            PowerUpKind::WeaponUp => "W",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PowerUp {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub speed: f64,
+    pub kind: PowerUpKind,
+}
+
+impl PowerUp {
+    // 中心座標を指定して生成
+    pub fn new(center_x: f64, center_y: f64, kind: PowerUpKind) -> Self {
+        let size = 20.0;
+        PowerUp {
+            x: center_x - size / 2.0,
+            y: center_y - size / 2.0,
+            width: size,
+            height: size,
+            speed: 2.0,
+            kind,
+        }
+    }
+}
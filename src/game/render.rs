@@ -0,0 +1,341 @@
+// 描画する画像の種類
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Sprite {
+    Player,
+    Enemy(usize), // 敵の見た目の種類(画像の番号)。ない番号は最初の画像で描画する
+    Boss, // 画像がなければ通常の敵の画像をボスの大きさに広げて描画する
+    Background,
+    Starfield, // 背景の上に重ねてゆっくり流れる星(画像がなければ描画しない)
+}
+
+impl Sprite {
+    // 画像を読み込めなかったときに、画像の代わりに矩形を塗る色
+    pub fn placeholder_color(self) -> &'static str {
+        match self {
+            Sprite::Player => "deepskyblue",
+            Sprite::Enemy(_) | Sprite::Boss => "crimson",
+            Sprite::Background | Sprite::Starfield => "#0b0b1e",
+        }
+    }
+}
+
+// 弾や火花の色の名前
+// (別名にしているのは、保存から戻すときに文字列を借用しようとさせないため)
+pub type ColorName = &'static str;
+
+// 弾や火花の色を、リセット前のプレイの保存に書き出して戻すための変換
+// 色は &'static str で持つので、戻すときは使っている色の一覧から同じ名前を探す
+pub mod color_name {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    // 弾と火花に使う色(新しい色を使うときはここに加える)
+    pub const SAVED_COLORS: [&str; 5] = ["red", "magenta", "yellow", "violet", "orange"];
+
+    pub fn serialize<S>(color: &&'static str, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(color)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<&'static str, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        SAVED_COLORS
+            .into_iter()
+            .find(|color| *color == name)
+            .ok_or_else(|| D::Error::custom(format!("unknown color: {}", name)))
+    }
+}
+
+// 文字列の横方向の揃え位置
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+// 文字列の見た目
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TextStyle {
+    pub color: &'static str,
+    pub bold: bool,
+    pub outline: Option<(&'static str, f64)>, // 縁取りの色と太さ(塗りの前に描く)
+    pub shadow: Option<(&'static str, f64)>,  // 影の色と右下へのずらし量
+}
+
+impl TextStyle {
+    // 通常の文字
+    pub const PLAIN: TextStyle = TextStyle {
+        color: "white",
+        bold: false,
+        outline: None,
+        shadow: None,
+    };
+    // 画面中央に出す見出し
+    pub const BANNER: TextStyle = TextStyle {
+        color: "white",
+        bold: true,
+        outline: Some(("black", 4.0)),
+        shadow: Some(("rgba(0, 0, 0, 0.6)", 3.0)),
+    };
+    // プレイ中に重ねる表示(敵や弾の上でも読めるよう縁取る)
+    pub const HUD: TextStyle = TextStyle {
+        color: "white",
+        bold: true,
+        outline: Some(("black", 3.0)),
+        shadow: None,
+    };
+
+    pub const fn with_color(self, color: &'static str) -> TextStyle {
+        TextStyle { color, ..self }
+    }
+
+    pub const fn bold(self) -> TextStyle {
+        TextStyle { bold: true, ..self }
+    }
+}
+
+// 描画先。ゲームの処理からCanvasなどのDOMを切り離すために使う
+// 座標はすべてゲーム内の座標で、文字列は縦方向の中央を基準に描画する
+pub trait Renderer {
+    fn clear(&self, width: f64, height: f64);
+    fn draw_sprite(&self, sprite: Sprite, x: f64, y: f64);
+    // 画像を描画できるか(読み込みに失敗した画像は描画できない)
+    fn has_sprite(&self, sprite: Sprite) -> bool;
+    fn draw_circle(&self, x: f64, y: f64, radius: f64, color: &str);
+    fn stroke_circle(&self, x: f64, y: f64, radius: f64, color: &str, line_width: f64);
+    // 円弧の線。angles は開始と終了の角度(ラジアン、x軸から時計回り)
+    fn stroke_arc(
+        &self,
+        x: f64,
+        y: f64,
+        radius: f64,
+        angles: (f64, f64),
+        color: &str,
+        line_width: f64,
+    );
+    fn fill_polygon(&self, points: &[(f64, f64)], color: &str);
+    fn stroke_polygon(&self, points: &[(f64, f64)], color: &str, line_width: f64);
+    // 敵の画像の種類の数(1以上)
+    fn enemy_variants(&self) -> usize;
+    // size はピクセル単位の文字の大きさ
+    fn draw_text(
+        &self,
+        text: &str,
+        x: f64,
+        y: f64,
+        size: f64,
+        align: TextAlign,
+        style: &TextStyle,
+    );
+    // 以降の描画をずらす。pop_offsetで元に戻す(ずれは積み重ならない)
+    fn push_offset(&self, dx: f64, dy: f64);
+    fn pop_offset(&self);
+    // 以降の描画の不透明度
+    fn set_alpha(&self, alpha: f64);
+    // ページ側のスコアやライフの表示を更新
+    fn update_hud(&self, score: u32, lives: u32, game_over: bool);
+}
+
+// 効果音の再生先
+pub trait AudioSink {
+    fn play_shot(&self);
+    fn play_explosion(&self);
+    // 名前を指定して効果音を鳴らす。見つからない場合は爆発音で代用する
+    fn play_clip(&self, name: &str);
+    // 以降に鳴らす効果音の音量(0.0〜1.0、消音中は0.0)
+    fn set_volume(&self, volume: f64);
+    // BGMの再生と一時停止(止めた位置から再開する)
+    fn set_music_playing(&self, playing: bool);
+    // BGMを止めて先頭に戻す
+    fn restart_music(&self);
+    // BGMの音量(効果音とは別に設定する)
+    fn set_music_volume(&self, volume: f64);
+    // レーザーのループ音の再生と停止
+    fn set_laser_playing(&self, playing: bool);
+    // 停止時に音を止め、使っていた資源を解放する
+    fn shutdown(&self);
+}
+
+// ブラウザなしでゲームを動かすテスト用の描画先と再生先
+#[cfg(test)]
+pub mod mock {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::{AudioSink, Renderer, Sprite, TextAlign, TextStyle};
+    use crate::game::Game;
+
+    // テストで使う場の大きさ
+    pub const FIELD_SIZE: (f64, f64) = (480.0, 640.0);
+
+    // 描いた内容を記録する描画先と、鳴らした音を記録する再生先で、画面のないゲームを作る
+    pub fn headless_game(seed: u64) -> (Rc<RefCell<Game>>, MockAudio) {
+        let (game, _, audio) = recorded_game(seed);
+        (game, audio)
+    }
+
+    // 描いた内容も確かめるときに使う
+    pub fn recorded_game(seed: u64) -> (Rc<RefCell<Game>>, MockRenderer, MockAudio) {
+        let audio = MockAudio::default();
+        let renderer = MockRenderer::default();
+        let (width, height) = FIELD_SIZE;
+        let game = Game::new(
+            Box::new(renderer.clone()),
+            Box::new(audio.clone()),
+            seed,
+            width,
+            height,
+        );
+        game.borrow_mut().headless = true;
+        (game, renderer, audio)
+    }
+
+    // 記録した描画の呼び出し
+    #[derive(Clone, PartialEq, Debug)]
+    pub enum DrawCall {
+        Clear,
+        Sprite { sprite: Sprite, x: f64, y: f64 },
+        Circle { x: f64, y: f64, radius: f64, color: String },
+        StrokeCircle { x: f64, y: f64, radius: f64, color: String },
+        StrokeArc { x: f64, y: f64, radius: f64, color: String },
+        FillPolygon { points: Vec<(f64, f64)>, color: String },
+        StrokePolygon { points: Vec<(f64, f64)>, color: String },
+        Text { text: String, x: f64, y: f64, size: f64, align: TextAlign },
+        PushOffset { dx: f64, dy: f64 },
+        PopOffset,
+        Alpha(f64),
+    }
+
+    // 描画の呼び出しを順に記録する描画先。ページ側の表示に送った値も覚える
+    // 複製はゲームに渡したものと記録を共有する
+    #[derive(Clone, Default)]
+    pub struct MockRenderer {
+        pub calls: Rc<RefCell<Vec<DrawCall>>>,
+        pub hud: Rc<RefCell<Option<(u32, u32, bool)>>>,
+    }
+
+    impl MockRenderer {
+        fn record(&self, call: DrawCall) {
+            self.calls.borrow_mut().push(call);
+        }
+
+        // 描いた文字列
+        pub fn texts(&self) -> Vec<String> {
+            self.calls
+                .borrow()
+                .iter()
+                .filter_map(|call| match call {
+                    DrawCall::Text { text, .. } => Some(text.clone()),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        pub fn clear_calls(&self) {
+            self.calls.borrow_mut().clear();
+        }
+    }
+
+    impl Renderer for MockRenderer {
+        fn clear(&self, _width: f64, _height: f64) {
+            self.record(DrawCall::Clear);
+        }
+        fn draw_sprite(&self, sprite: Sprite, x: f64, y: f64) {
+            self.record(DrawCall::Sprite { sprite, x, y });
+        }
+        fn has_sprite(&self, _sprite: Sprite) -> bool {
+            true
+        }
+        fn draw_circle(&self, x: f64, y: f64, radius: f64, color: &str) {
+            let color = color.to_string();
+            self.record(DrawCall::Circle { x, y, radius, color });
+        }
+        fn stroke_circle(&self, x: f64, y: f64, radius: f64, color: &str, _width: f64) {
+            let color = color.to_string();
+            self.record(DrawCall::StrokeCircle { x, y, radius, color });
+        }
+        fn stroke_arc(
+            &self,
+            x: f64,
+            y: f64,
+            radius: f64,
+            _angles: (f64, f64),
+            color: &str,
+            _line_width: f64,
+        ) {
+            let color = color.to_string();
+            self.record(DrawCall::StrokeArc { x, y, radius, color });
+        }
+        fn fill_polygon(&self, points: &[(f64, f64)], color: &str) {
+            let (points, color) = (points.to_vec(), color.to_string());
+            self.record(DrawCall::FillPolygon { points, color });
+        }
+        fn stroke_polygon(&self, points: &[(f64, f64)], color: &str, _line_width: f64) {
+            let (points, color) = (points.to_vec(), color.to_string());
+            self.record(DrawCall::StrokePolygon { points, color });
+        }
+        fn enemy_variants(&self) -> usize {
+            1
+        }
+        fn draw_text(
+            &self,
+            text: &str,
+            x: f64,
+            y: f64,
+            size: f64,
+            align: TextAlign,
+            _style: &TextStyle,
+        ) {
+            let text = text.to_string();
+            self.record(DrawCall::Text { text, x, y, size, align });
+        }
+        fn push_offset(&self, dx: f64, dy: f64) {
+            self.record(DrawCall::PushOffset { dx, dy });
+        }
+        fn pop_offset(&self) {
+            self.record(DrawCall::PopOffset);
+        }
+        fn set_alpha(&self, alpha: f64) {
+            self.record(DrawCall::Alpha(alpha));
+        }
+        fn update_hud(&self, score: u32, lives: u32, game_over: bool) {
+            *self.hud.borrow_mut() = Some((score, lives, game_over));
+        }
+    }
+
+    // 鳴らした効果音の名前を順に記録する再生先
+    #[derive(Clone, Default)]
+    pub struct MockAudio {
+        pub played: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl MockAudio {
+        pub fn count(&self, name: &str) -> usize {
+            self.played.borrow().iter().filter(|played| *played == name).count()
+        }
+    }
+
+    impl AudioSink for MockAudio {
+        fn play_shot(&self) {
+            self.played.borrow_mut().push("shot".to_string());
+        }
+        fn play_explosion(&self) {
+            self.played.borrow_mut().push("explosion".to_string());
+        }
+        fn play_clip(&self, name: &str) {
+            self.played.borrow_mut().push(name.to_string());
+        }
+        fn set_volume(&self, _volume: f64) {}
+        fn set_music_playing(&self, _playing: bool) {}
+        fn restart_music(&self) {}
+        fn set_music_volume(&self, _volume: f64) {}
+        fn set_laser_playing(&self, _playing: bool) {}
+        fn shutdown(&self) {}
+    }
+}
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Bullet, BulletPool, Enemy, Player};
+
+// ゲームオーバー時に再生する時間(ms)
+pub const REPLAY_DURATION: f64 = 5000.0;
+// 再生速度(スローモーション)
+pub const REPLAY_SPEED: f64 = 0.5;
+// 記録する間隔(ゲーム内時間、ms)。フレームレートによらず同じ間隔で記録する
+const SAMPLE_INTERVAL: f64 = 25.0;
+// 保持するスナップショット数の上限
+// 1区間に1つしか記録しないので、両端の区間の分を足せば再生する時間を必ず覆える
+const CAPACITY: usize = (REPLAY_DURATION / SAMPLE_INTERVAL) as usize + 2;
+
+// ある時点の各エンティティの位置
+#[derive(Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub time: f64,
+    pub player_x: f64,
+    pub player_y: f64,
+    pub enemies: Vec<(f64, f64, bool, usize)>, // x, y, 被弾点滅中か, 見た目の種類
+    pub bullets: Vec<(f64, f64, f64)>, // x, y, 半径
+    pub enemy_bullets: Vec<(f64, f64, f64)>, // x, y, 半径
+}
+
+// 直近のスナップショットを保持するリングバッファ
+// 各スナップショットのVecは使い回し、記録のたびに確保し直さない
+#[derive(Serialize, Deserialize)]
+pub struct ReplayBuffer {
+    snapshots: Vec<Snapshot>,
+    head: usize, // 次に書き込む位置
+    len: usize,
+    next_sample_at: f64, // 次に記録する時刻(ゲーム内時間、ms)
+}
+
+impl ReplayBuffer {
+    pub fn new() -> Self {
+        ReplayBuffer {
+            snapshots: (0..CAPACITY).map(|_| Snapshot::default()).collect(),
+            head: 0,
+            len: 0,
+            next_sample_at: 0.0,
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        time: f64,
+        player: &Player,
+        enemies: &[Enemy],
+        bullets: &BulletPool,
+        enemy_bullets: &[Bullet],
+    ) {
+        // SAMPLE_INTERVAL ごとの区間で最初のフレームだけ記録する
+        if time < self.next_sample_at {
+            return;
+        }
+        self.next_sample_at = ((time / SAMPLE_INTERVAL).floor() + 1.0) * SAMPLE_INTERVAL;
+
+        let snapshot = &mut self.snapshots[self.head];
+        snapshot.time = time;
+        snapshot.player_x = player.pos.x;
+        snapshot.player_y = player.pos.y;
+        snapshot.enemies.clear();
+        snapshot
+            .enemies
+            .extend(enemies.iter().map(|e| (e.pos.x, e.pos.y, e.hit_flash > 0.0, e.sprite)));
+        snapshot.bullets.clear();
+        snapshot
+            .bullets
+            .extend(bullets.iter().map(|b| (b.pos.x, b.pos.y, b.radius)));
+        snapshot.enemy_bullets.clear();
+        snapshot
+            .enemy_bullets
+            .extend(enemy_bullets.iter().map(|b| (b.pos.x, b.pos.y, b.radius)));
+
+        self.head = (self.head + 1) % CAPACITY;
+        self.len = (self.len + 1).min(CAPACITY);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // 古い順にi番目のスナップショット
+    fn get(&self, i: usize) -> &Snapshot {
+        let oldest = (self.head + CAPACITY - self.len) % CAPACITY;
+        &self.snapshots[(oldest + i) % CAPACITY]
+    }
+
+    // 再生を開始する時刻(最新の記録からREPLAY_DURATION以内で最も古いもの)
+    pub fn start_time(&self) -> f64 {
+        let end = self.end_time();
+        (0..self.len)
+            .map(|i| self.get(i).time)
+            .find(|&t| end - t <= REPLAY_DURATION)
+            .unwrap_or(end)
+    }
+
+    pub fn end_time(&self) -> f64 {
+        if self.len == 0 {
+            return 0.0;
+        }
+        self.get(self.len - 1).time
+    }
+
+    // 指定時刻に表示するスナップショット
+    pub fn snapshot_at(&self, time: f64) -> Option<&Snapshot> {
+        (0..self.len)
+            .map(|i| self.get(i))
+            .take_while(|s| s.time <= time)
+            .last()
+            .or_else(|| (self.len > 0).then(|| self.get(0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::render::mock::headless_game;
+
+    // frame_ms ごとに seconds 秒分を記録したバッファ
+    fn recorded(frame_ms: f64, seconds: f64) -> ReplayBuffer {
+        let (game, _) = headless_game(1);
+        let game = game.borrow();
+        let mut replay = ReplayBuffer::new();
+        let mut time = 0.0;
+        while time < seconds * 1000.0 {
+            replay.record(time, &game.player, &game.enemies, &game.bullets, &game.enemy_bullets);
+            time += frame_ms;
+        }
+        replay
+    }
+
+    #[test]
+    fn the_window_covers_the_same_game_time_at_any_frame_rate() {
+        for frame_ms in [1000.0 / 144.0, 1000.0 / 60.0, 1000.0 / 30.0] {
+            let replay = recorded(frame_ms, 8.0);
+            // 記録の間隔(区間と1フレーム)より短くはならない
+            let window = replay.end_time() - replay.start_time();
+            let gap = SAMPLE_INTERVAL + frame_ms;
+            assert!(
+                window <= REPLAY_DURATION && REPLAY_DURATION - window < gap,
+                "{} ms frames kept {} ms",
+                frame_ms,
+                window
+            );
+        }
+    }
+
+    #[test]
+    fn samples_are_taken_once_per_interval() {
+        // 高いフレームレートでも区間あたり1つしか記録しない
+        let replay = recorded(1000.0 / 144.0, 1.0);
+        assert_eq!(replay.len, (1000.0 / SAMPLE_INTERVAL) as usize);
+        // 区間より長いフレームでは毎フレーム記録する
+        let replay = recorded(50.0, 1.0);
+        assert_eq!(replay.len, 20);
+    }
+}
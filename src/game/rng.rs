@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+// 再現可能な乱数生成器(xorshift64*)
+// 同じシードと同じ入力であれば同じ敵の出現を再現できる
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // 状態が0だと常に0を返すため、シードを混ぜてから使う
+        let state = seed ^ 0x9E37_79B9_7F4A_7C15;
+        Rng {
+            state: if state == 0 { 0x9E37_79B9_7F4A_7C15 } else { state },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // 0.0以上1.0未満の乱数(Math.randomの代わり)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_the_same_sequence() {
+        let mut a = Rng::new(12345);
+        let mut b = Rng::new(12345);
+        let mut c = Rng::new(12346);
+        let first: Vec<u64> = (0..100).map(|_| a.next_u64()).collect();
+        let second: Vec<u64> = (0..100).map(|_| b.next_u64()).collect();
+        let other: Vec<u64> = (0..100).map(|_| c.next_u64()).collect();
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn clones_continue_the_same_sequence() {
+        let mut rng = Rng::new(7);
+        rng.next_u64();
+        let mut copy = rng.clone();
+        assert_eq!(rng.next_u64(), copy.next_u64());
+    }
+
+    #[test]
+    fn seed_that_cancels_the_mix_does_not_get_stuck_at_zero() {
+        let mut rng = Rng::new(0x9E37_79B9_7F4A_7C15);
+        assert!((0..10).any(|_| rng.next_u64() != 0));
+    }
+
+    #[test]
+    fn floats_stay_in_the_unit_range_and_spread_out() {
+        let mut rng = Rng::new(1);
+        let values: Vec<f64> = (0..10_000).map(|_| rng.next_f64()).collect();
+        assert!(values.iter().all(|&v| (0.0..1.0).contains(&v)));
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        assert!((mean - 0.5).abs() < 0.02);
+        // 10等分したどの区間にもおおよそ均等に入る
+        for bucket in 0..10 {
+            let count = values.iter().filter(|&&v| (v * 10.0) as usize == bucket).count();
+            assert!((800..1200).contains(&count));
+        }
+    }
+}
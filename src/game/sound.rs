@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::Rng;
+
+// 効果音を選び分けるための敵の種類
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum EnemyKind {
+    Small,   // 通常の敵(軽い破裂音)
+    Armored, // 装甲付きの敵(重い爆発音)
+    Boss,    // スコアの節目に現れる大型の敵(通常の爆発音)
+    Kamikaze, // 途中から自機めがけて突っ込んでくる敵(通常の爆発音)
+}
+
+// 撃破時の効果音の候補を1種類あたりこの数まで並べられる
+pub const MAX_KILL_SOUND_VARIANTS: usize = 3;
+
+// 撃破時の効果音の一覧。種類ごとに候補を並べ、鳴らすたびに乱数で1つ選ぶ
+// 名前はページ上のaudio要素のidで、候補のない種類や見つからない名前は通常の爆発音を鳴らす
+// 中身はアセットの一覧(assets/manifest.json)から読み込み、書かれていない種類は候補なしにする
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KillSounds {
+    #[serde(default)]
+    pub small: Vec<String>,
+    #[serde(default)]
+    pub armored: Vec<String>,
+    #[serde(default)]
+    pub boss: Vec<String>,
+    #[serde(default)]
+    pub kamikaze: Vec<String>,
+}
+
+impl Default for KillSounds {
+    fn default() -> Self {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        KillSounds {
+            small: names(&["popSound1", "popSound2", "popSound3"]),
+            armored: names(&["boomSound1", "boomSound2"]),
+            boss: Vec::new(),
+            kamikaze: Vec::new(),
+        }
+    }
+}
+
+impl KillSounds {
+    pub fn clips(&self, kind: EnemyKind) -> &[String] {
+        match kind {
+            EnemyKind::Small => &self.small,
+            EnemyKind::Armored => &self.armored,
+            EnemyKind::Boss => &self.boss,
+            EnemyKind::Kamikaze => &self.kamikaze,
+        }
+    }
+
+    // すべての種類の候補の名前(重複はそのまま)
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        [&self.small, &self.armored, &self.boss, &self.kamikaze]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    // 候補の数と名前を確かめる。問題があれば理由を返す
+    pub fn validate(&self) -> Result<(), String> {
+        let kinds = [
+            ("small", &self.small),
+            ("armored", &self.armored),
+            ("boss", &self.boss),
+            ("kamikaze", &self.kamikaze),
+        ];
+        for (kind, clips) in kinds {
+            if clips.len() > MAX_KILL_SOUND_VARIANTS {
+                return Err(format!(
+                    "kill_sounds.{} has {} variants (at most {})",
+                    kind,
+                    clips.len(),
+                    MAX_KILL_SOUND_VARIANTS
+                ));
+            }
+            if clips.iter().any(|clip| clip.trim().is_empty()) {
+                return Err(format!("kill_sounds.{} has an empty clip name", kind));
+            }
+        }
+        Ok(())
+    }
+
+    // 撃破した敵の種類に合った効果音を選ぶ。候補のない種類はNone
+    pub fn pick(&self, kind: EnemyKind, rng: &mut Rng) -> Option<&str> {
+        let clips = self.clips(kind);
+        if clips.is_empty() {
+            return None;
+        }
+        let index = (rng.next_f64() * clips.len() as f64) as usize;
+        Some(&clips[index.min(clips.len() - 1)])
+    }
+}
+
+// スコアの節目でライフが増えたときの効果音
+pub const EXTRA_LIFE_SOUND: &str = "oneUpSound";
+
+// シールドが被弾を防いで消えたときの効果音
+pub const SHIELD_BREAK_SOUND: &str = "shieldSound";
+
+// 敵に画面下まで抜けられてライフが減ったときの警告音
+pub const ESCAPE_SOUND: &str = "escapeSound";
+
+// ボスの登場前に「WARNING」を出すときの警報
+pub const BOSS_WARNING_SOUND: &str = "alarmSound";
+
+// ボムのメーターが満タンになってボムが増えたときの効果音
+pub const BOMB_READY_SOUND: &str = "chimeSound";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_every_variant_of_the_kind() {
+        let sounds = KillSounds::default();
+        let mut rng = Rng::new(5);
+        let mut picked: Vec<&str> =
+            (0..100).filter_map(|_| sounds.pick(EnemyKind::Small, &mut rng)).collect();
+        picked.sort_unstable();
+        picked.dedup();
+        assert_eq!(picked, vec!["popSound1", "popSound2", "popSound3"]);
+        assert_eq!(sounds.pick(EnemyKind::Boss, &mut rng), None);
+    }
+
+    #[test]
+    fn rejects_too_many_variants_and_empty_names() {
+        let mut sounds = KillSounds::default();
+        assert_eq!(sounds.validate(), Ok(()));
+        sounds.boss = vec!["a".into(), "b".into(), "c".into(), "d".into()];
+        assert!(sounds.validate().unwrap_err().contains("kill_sounds.boss"));
+        sounds.boss = vec![" ".into()];
+        assert!(sounds.validate().is_err());
+    }
+}
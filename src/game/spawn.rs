@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Enemy, Rng};
+
+// 敵が出現するまでの予告時間(ms)
+pub const SPAWN_TELEGRAPH_DURATION: f64 = 600.0;
+// 被弾後に自機の真上へ敵を出しにくくする時間(ms)と、避ける列の幅(px)
+pub const SPAWN_GUARD_DURATION: f64 = 4000.0;
+pub const SPAWN_GUARD_WIDTH: f64 = 120.0;
+// 避ける列に重なった位置を引き直す回数。使い切ったらそのまま出現させる
+const SPAWN_GUARD_RETRIES: usize = 5;
+
+// 敵の出現位置のx座標を選ぶ
+// guard_x がある場合は、そこを中心とする列に重ならない位置を優先する
+// 引き直しも同じ乱数から行うので、シードが同じなら結果も同じになる
+pub fn pick_spawn_x(
+    rng: &mut Rng,
+    field_width: f64,
+    enemy_width: f64,
+    guard_x: Option<f64>,
+) -> f64 {
+    let mut x = rng.next_f64() * (field_width - enemy_width);
+    let Some(guard_x) = guard_x else {
+        return x;
+    };
+    let guard_left = guard_x - SPAWN_GUARD_WIDTH / 2.0;
+    let guard_right = guard_x + SPAWN_GUARD_WIDTH / 2.0;
+    for _ in 0..SPAWN_GUARD_RETRIES {
+        if x + enemy_width <= guard_left || x >= guard_right {
+            break;
+        }
+        x = rng.next_f64() * (field_width - enemy_width);
+    }
+    x
+}
+
+// 敵の見た目(画像の番号)を選ぶ。画像を前半と後半に分け、遅い敵は前半、速い敵は後半から選ぶ
+// 画像が1枚しかなければ常に0。乱数は常に1回だけ使う
+pub fn pick_sprite(rng: &mut Rng, variants: usize, fast: bool) -> usize {
+    let slow_count = variants.div_ceil(2).max(1);
+    let (first, count) = if fast && variants > slow_count {
+        (slow_count, variants - slow_count)
+    } else {
+        (0, slow_count)
+    };
+    let index = first + (rng.next_f64() * count as f64) as usize;
+    index.min(first + count - 1)
+}
+
+// 出現待ちの敵
+#[derive(Serialize, Deserialize)]
+pub struct PendingSpawn {
+    pub enemy: Enemy,
+    pub remaining: f64, // 出現までの残り時間(ms)
+}
+
+impl PendingSpawn {
+    pub fn new(enemy: Enemy) -> Self {
+        PendingSpawn {
+            enemy,
+            remaining: SPAWN_TELEGRAPH_DURATION,
+        }
+    }
+
+    // 予告マーカーを表示するx座標(敵の中央)
+    pub fn marker_x(&self) -> f64 {
+        self.enemy.pos.x + self.enemy.width / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps_guard(x: f64, enemy_width: f64, guard_x: f64) -> bool {
+        x + enemy_width > guard_x - SPAWN_GUARD_WIDTH / 2.0
+            && x < guard_x + SPAWN_GUARD_WIDTH / 2.0
+    }
+
+    #[test]
+    fn spawns_inside_the_field() {
+        let mut rng = Rng::new(5);
+        for guard_x in [None, Some(0.0), Some(240.0), Some(480.0)] {
+            for _ in 0..200 {
+                let x = pick_spawn_x(&mut rng, 480.0, 50.0, guard_x);
+                assert!((0.0..=430.0).contains(&x));
+            }
+        }
+    }
+
+    #[test]
+    fn mostly_avoids_the_column_above_the_player() {
+        let mut rng = Rng::new(9);
+        let tries = 1000;
+        let unguarded = (0..tries)
+            .filter(|_| overlaps_guard(pick_spawn_x(&mut rng, 480.0, 50.0, None), 50.0, 240.0))
+            .count();
+        let guarded = (0..tries)
+            .filter(|_| {
+                overlaps_guard(pick_spawn_x(&mut rng, 480.0, 50.0, Some(240.0)), 50.0, 240.0)
+            })
+            .count();
+        // 引き直しを使い切った場合だけ重なる
+        assert!(unguarded > tries / 4);
+        assert!(guarded < tries / 50);
+    }
+
+    #[test]
+    fn same_seed_picks_the_same_positions() {
+        let mut a = Rng::new(3);
+        let mut b = Rng::new(3);
+        for _ in 0..100 {
+            assert_eq!(
+                pick_spawn_x(&mut a, 480.0, 50.0, Some(100.0)),
+                pick_spawn_x(&mut b, 480.0, 50.0, Some(100.0))
+            );
+        }
+    }
+
+    #[test]
+    fn gives_up_when_the_guard_covers_the_field() {
+        let mut rng = Rng::new(11);
+        let x = pick_spawn_x(&mut rng, 100.0, 50.0, Some(50.0));
+        assert!((0.0..=50.0).contains(&x));
+    }
+}
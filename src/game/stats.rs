@@ -0,0 +1,272 @@
+use serde::Serialize;
+
+use crate::game::{Difficulty, GameMode, RunFlags};
+
+// 保存しておく過去のプレイの数の上限(古いものから捨てる)
+pub const MAX_STORED_RUNS: usize = 100;
+
+// 1回のプレイの結果
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub struct RunSummary {
+    pub score: u32,
+    pub kills: u32,
+    pub enemies_escaped: u32, // 倒せずに画面下へ抜けた敵の数(撃破数とは重ならない)
+    pub shots_fired: u32,
+    pub shots_hit: u32,
+    pub duration_ms: f64, // 一時停止中を除いて遊んだ実時間(ms、遅くする設定の影響を受けない)
+    pub difficulty: Difficulty,
+    pub flags: RunFlags, // 公正さに影響する設定を使ったプレイは自己ベストの対象にしない
+    pub modifier_waves: u32, // 変化の掛かった波の数
+}
+
+impl RunSummary {
+    // 保存用の文字列
+    // "score,kills,shots_fired,shots_hit,duration_ms,enemies_escaped,difficulty,flags,
+    //  modifier_waves"
+    fn encode(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{}",
+            self.score,
+            self.kills,
+            self.shots_fired,
+            self.shots_hit,
+            self.duration_ms.round(),
+            self.enemies_escaped,
+            self.difficulty.name(),
+            self.flags.to_bits(),
+            self.modifier_waves
+        )
+    }
+
+    // 命中率(0.0〜1.0)。撃っていなければNone
+    pub fn accuracy(&self) -> Option<f64> {
+        (self.shots_fired > 0).then(|| self.shots_hit as f64 / self.shots_fired as f64)
+    }
+
+    fn decode(text: &str) -> Option<Self> {
+        let mut fields = text.split(',');
+        let mut next = || fields.next()?.trim().parse::<f64>().ok();
+        let mut summary = RunSummary {
+            score: next()? as u32,
+            kills: next()? as u32,
+            shots_fired: next()? as u32,
+            shots_hit: next()? as u32,
+            duration_ms: next()?.max(0.0),
+            // 以前の形式の記録には含まれていない
+            enemies_escaped: next().unwrap_or(0.0) as u32,
+            difficulty: Difficulty::Normal,
+            flags: RunFlags::default(),
+            modifier_waves: 0,
+        };
+        let difficulty = fields.next().and_then(|name| Difficulty::from_name(name.trim()));
+        if let Some(difficulty) = difficulty {
+            summary.difficulty = difficulty;
+        }
+        if let Some(bits) = fields.next().and_then(|bits| bits.trim().parse().ok()) {
+            summary.flags = RunFlags::from_bits(bits);
+        }
+        if let Some(count) = fields.next().and_then(|count| count.trim().parse().ok()) {
+            summary.modifier_waves = count;
+        }
+        Some(summary)
+    }
+}
+
+// ゲームオーバー画面に出す、終わったプレイのまとめ
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub struct RunResult {
+    pub score: u32,
+    pub high_score: u32, // このプレイを含めた最高スコア
+    pub new_best: bool, // このプレイで最高スコアを更新したか
+    pub best_combo: u32,
+    pub accuracy: Option<f64>,
+    pub kills: u32,
+    pub duration_ms: f64, // 一時停止中を除いて遊んだ実時間(ms)
+}
+
+// 全プレイの合計
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct LifetimeStats {
+    pub runs: usize,
+    pub total_kills: u64, // u32の合計があふれないよう広い型で足す
+    pub total_shots: u64,
+    pub total_hits: u64,
+    pub total_playtime_ms: f64, // 遊んだ実時間の合計
+    pub best_score: u32, // 公正さに影響する設定を使わなかったプレイの最高スコア
+}
+
+impl LifetimeStats {
+    // 命中率(0.0〜1.0)。撃っていなければNone
+    pub fn accuracy(&self) -> Option<f64> {
+        (self.total_shots > 0).then(|| self.total_hits as f64 / self.total_shots as f64)
+    }
+}
+
+// 保存されている過去のプレイ(古い順)
+#[derive(Default)]
+pub struct RunHistory {
+    pub runs: Vec<RunSummary>,
+}
+
+impl RunHistory {
+    // 保存用の文字列から読み込む。壊れた記録は読み飛ばす
+    pub fn decode(text: &str) -> Self {
+        let mut history = RunHistory::default();
+        for run in text.split(';').filter_map(RunSummary::decode) {
+            history.push(run);
+        }
+        history
+    }
+
+    pub fn encode(&self) -> String {
+        self.runs.iter().map(RunSummary::encode).collect::<Vec<_>>().join(";")
+    }
+
+    pub fn push(&mut self, run: RunSummary) {
+        self.runs.push(run);
+        if self.runs.len() > MAX_STORED_RUNS {
+            let excess = self.runs.len() - MAX_STORED_RUNS;
+            self.runs.drain(..excess);
+        }
+    }
+
+    // 直近 count 回のプレイ(古い順)
+    pub fn recent(&self, count: usize) -> &[RunSummary] {
+        &self.runs[self.runs.len().saturating_sub(count)..]
+    }
+
+    pub fn lifetime(&self) -> LifetimeStats {
+        self.runs.iter().fold(LifetimeStats::default(), |total, run| LifetimeStats {
+            runs: total.runs + 1,
+            total_kills: total.total_kills.saturating_add(run.kills as u64),
+            total_shots: total.total_shots.saturating_add(run.shots_fired as u64),
+            total_hits: total.total_hits.saturating_add(run.shots_hit as u64),
+            total_playtime_ms: total.total_playtime_ms + run.duration_ms,
+            best_score: if run.flags.is_ranked() {
+                total.best_score.max(run.score)
+            } else {
+                total.best_score
+            },
+        })
+    }
+}
+
+// プレイの記録の保存先(ブラウザではlocalStorage)。記録は遊び方と難易度ごとに分けて保存する
+pub trait RunStore {
+    fn load_runs(&self, mode: GameMode, difficulty: Difficulty) -> RunHistory;
+    fn save_runs(&self, mode: GameMode, difficulty: Difficulty, history: &RunHistory);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(score: u32, flags: RunFlags) -> RunSummary {
+        RunSummary {
+            score,
+            kills: 3,
+            enemies_escaped: 1,
+            shots_fired: 10,
+            shots_hit: 4,
+            duration_ms: 1000.0,
+            difficulty: Difficulty::Normal,
+            flags,
+            modifier_waves: 2,
+        }
+    }
+
+    const ASSISTED: RunFlags = RunFlags {
+        assist_speed: true,
+        practice: false,
+        custom_config: false,
+        gameplay_toggles: false,
+    };
+
+    const PRACTICE: RunFlags = RunFlags {
+        assist_speed: false,
+        practice: true,
+        custom_config: false,
+        gameplay_toggles: false,
+    };
+
+    #[test]
+    fn flags_survive_encoding() {
+        let mut history = RunHistory::default();
+        history.push(run(100, RunFlags::default()));
+        history.push(run(200, ASSISTED));
+        let decoded = RunHistory::decode(&history.encode());
+        assert_eq!(decoded.runs, history.runs);
+    }
+
+    #[test]
+    fn old_records_without_flags_are_ranked() {
+        let history = RunHistory::decode("150,3,10,4,1000,1,normal");
+        assert_eq!(history.runs.len(), 1);
+        assert!(history.runs[0].flags.is_ranked());
+        assert_eq!(history.runs[0].modifier_waves, 0);
+    }
+
+    #[test]
+    fn flagged_runs_do_not_set_the_best_score() {
+        let mut history = RunHistory::default();
+        history.push(run(100, RunFlags::default()));
+        history.push(run(500, ASSISTED));
+        let lifetime = history.lifetime();
+        assert_eq!(lifetime.best_score, 100);
+        // 合計には含める
+        assert_eq!(lifetime.runs, 2);
+        assert_eq!(lifetime.total_kills, 6);
+    }
+
+    #[test]
+    fn practice_runs_do_not_set_the_best_score() {
+        let mut history = RunHistory::default();
+        history.push(run(100, RunFlags::default()));
+        history.push(run(900, PRACTICE));
+        assert_eq!(history.lifetime().best_score, 100);
+        let decoded = RunHistory::decode(&history.encode());
+        assert!(decoded.runs[1].flags.practice);
+    }
+
+    #[test]
+    fn keeps_only_the_most_recent_runs() {
+        let mut history = RunHistory::default();
+        for score in 0..MAX_STORED_RUNS as u32 + 5 {
+            history.push(run(score, RunFlags::default()));
+        }
+        assert_eq!(history.runs.len(), MAX_STORED_RUNS);
+        assert_eq!(history.runs[0].score, 5);
+        let recent: Vec<u32> = history.recent(3).iter().map(|run| run.score).collect();
+        assert_eq!(recent, vec![102, 103, 104]);
+        // 記録が少なければあるだけ返す
+        assert_eq!(RunHistory::default().recent(10).len(), 0);
+    }
+
+    #[test]
+    fn skips_broken_records_and_reads_older_formats() {
+        let history = RunHistory::decode("100,1,2,1,500;garbage;;200,2,4,4,-30;300,3");
+        let scores: Vec<u32> = history.runs.iter().map(|run| run.score).collect();
+        assert_eq!(scores, vec![100, 200]);
+        // 撃破されずに抜けた数と難易度がない記録
+        assert_eq!(history.runs[0].enemies_escaped, 0);
+        assert_eq!(history.runs[0].difficulty, Difficulty::Normal);
+        // 負の時間は0として扱う
+        assert_eq!(history.runs[1].duration_ms, 0.0);
+        assert!(RunHistory::decode("").runs.is_empty());
+    }
+
+    #[test]
+    fn lifetime_totals_and_accuracy() {
+        let mut history = RunHistory::default();
+        assert_eq!(history.lifetime().accuracy(), None);
+        history.push(run(100, RunFlags::default()));
+        history.push(run(300, RunFlags::default()));
+        let lifetime = history.lifetime();
+        assert_eq!(lifetime.runs, 2);
+        assert_eq!((lifetime.total_shots, lifetime.total_hits), (20, 8));
+        assert_eq!(lifetime.total_playtime_ms, 2000.0);
+        assert_eq!(lifetime.accuracy(), Some(0.4));
+        assert_eq!(lifetime.best_score, 300);
+        assert_eq!(history.runs[0].accuracy(), Some(0.4));
+    }
+}
@@ -0,0 +1,144 @@
+// スコア送信用のペイロードと改ざん検出用のダイジェスト
+//
+// サーバー側での検証手順:
+// 1. 受け取った seed / duration_ms / score / input_hash / flags / version から
+//    score_digest と同じ計算でダイジェストを求め、digest と一致しなければ破棄する
+// 2. flags が空でない(ranked が false の)記録はランキングに載せない
+// 3. 疑わしい記録は、同じ version のビルドで seed を指定してゲームを開始し
+//    (start_game_with_seed)、入力を再生して score と input_hash が一致するかを確かめる
+//
+// ソルトはwasmに埋め込まれているため本当の意味での安全性はないが、
+// 手書きのJSONを送るだけの単純な改ざんは防げる
+
+use crate::game::RunFlags;
+
+// wasmに埋め込むソルト
+const SUBMISSION_SALT: &[u8] = b"alarm-shooter/score/v1";
+// FNV-1aの初期値と係数
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// バイト列をハッシュ値に混ぜ込む(FNV-1a)
+pub fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+// 入力記録のハッシュの初期値
+pub fn empty_input_hash() -> u64 {
+    FNV_OFFSET
+}
+
+// 送信内容からダイジェストを求める
+pub fn score_digest(
+    input_hash: u64,
+    seed: u64,
+    duration_ms: u64,
+    score: u32,
+    flags: RunFlags,
+) -> u64 {
+    let mut hash = fnv1a(FNV_OFFSET, SUBMISSION_SALT);
+    hash = fnv1a(hash, &[flags.to_bits()]);
+    hash = fnv1a(hash, &input_hash.to_le_bytes());
+    hash = fnv1a(hash, &seed.to_le_bytes());
+    hash = fnv1a(hash, &duration_ms.to_le_bytes());
+    hash = fnv1a(hash, &score.to_le_bytes());
+    fnv1a(hash, env!("CARGO_PKG_VERSION").as_bytes())
+}
+
+pub struct ScoreSubmission {
+    pub seed: u64,
+    pub duration_ms: u64,
+    pub score: u32,
+    pub input_hash: u64,
+    pub flags: RunFlags,
+    pub digest: u64,
+}
+
+impl ScoreSubmission {
+    pub fn new(
+        seed: u64,
+        duration_ms: u64,
+        score: u32,
+        input_hash: u64,
+        flags: RunFlags,
+    ) -> Self {
+        ScoreSubmission {
+            seed,
+            duration_ms,
+            score,
+            input_hash,
+            flags,
+            digest: score_digest(input_hash, seed, duration_ms, score, flags),
+        }
+    }
+
+    // サーバーに送るJSON。64bit値はJSの数値で丸められないよう16進文字列にする
+    pub fn to_json(&self) -> String {
+        let flags = self
+            .flags
+            .names()
+            .map(|name| format!("\"{}\"", name))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            "{{\"version\":\"{}\",\"seed\":\"{:016x}\",\"duration_ms\":{},\"score\":{},\
+             \"input_hash\":\"{:016x}\",\"flags\":[{}],\"ranked\":{},\"digest\":\"{:016x}\"}}",
+            env!("CARGO_PKG_VERSION"),
+            self.seed,
+            self.duration_ms,
+            self.score,
+            self.input_hash,
+            flags,
+            self.flags.is_ranked(),
+            self.digest,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRACTICE: RunFlags = RunFlags {
+        assist_speed: false,
+        practice: true,
+        custom_config: false,
+        gameplay_toggles: false,
+    };
+
+    #[test]
+    fn fnv1a_matches_the_reference_values() {
+        assert_eq!(fnv1a(FNV_OFFSET, b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(FNV_OFFSET, b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(FNV_OFFSET, b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn digest_changes_with_every_field() {
+        let base = score_digest(1, 2, 3, 4, RunFlags::default());
+        assert_eq!(base, score_digest(1, 2, 3, 4, RunFlags::default()));
+        assert_ne!(base, score_digest(9, 2, 3, 4, RunFlags::default()));
+        assert_ne!(base, score_digest(1, 9, 3, 4, RunFlags::default()));
+        assert_ne!(base, score_digest(1, 2, 9, 4, RunFlags::default()));
+        assert_ne!(base, score_digest(1, 2, 3, 9, RunFlags::default()));
+        assert_ne!(base, score_digest(1, 2, 3, 4, PRACTICE));
+    }
+
+    #[test]
+    fn json_carries_the_digest_and_ranking() {
+        let submission = ScoreSubmission::new(0xab, 61_000, 1200, 0x1234, PRACTICE);
+        let json: serde_json::Value = serde_json::from_str(&submission.to_json()).unwrap();
+        assert_eq!(json["seed"], "00000000000000ab");
+        assert_eq!(json["input_hash"], "0000000000001234");
+        assert_eq!(json["duration_ms"], 61_000);
+        assert_eq!(json["score"], 1200);
+        assert_eq!(json["flags"], serde_json::json!(["practice"]));
+        assert_eq!(json["ranked"], false);
+        let digest = score_digest(0x1234, 0xab, 61_000, 1200, PRACTICE);
+        assert_eq!(json["digest"], format!("{:016x}", digest));
+    }
+}
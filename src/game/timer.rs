@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+// ゲーム内時間
+// 実時間ではなく、一時停止中は進まず、ゲーム速度の倍率を掛けた分だけ進む
+#[derive(Serialize, Deserialize)]
+pub struct GameClock {
+    now: f64, // 経過したゲーム内時間(ms)
+}
+
+impl GameClock {
+    pub fn new() -> Self {
+        GameClock { now: 0.0 }
+    }
+
+    pub fn now(&self) -> f64 {
+        self.now
+    }
+
+    // ゲーム内時間の経過量を受け取って進め、進んだ量を返す
+    pub fn advance(&mut self, delta: f64) -> f64 {
+        let delta = delta.max(0.0);
+        self.now += delta;
+        delta
+    }
+}
+
+// ゲーム内時間で計るタイマー(効果時間やクールダウンに使う)
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct GameTimer {
+    ends_at: f64,
+}
+
+impl GameTimer {
+    // 今から duration だけ動くタイマー
+    pub fn started(clock: &GameClock, duration: f64) -> Self {
+        GameTimer {
+            ends_at: clock.now() + duration,
+        }
+    }
+
+    pub fn start(&mut self, clock: &GameClock, duration: f64) {
+        self.ends_at = clock.now() + duration;
+    }
+
+    pub fn is_running(&self, clock: &GameClock) -> bool {
+        clock.now() < self.ends_at
+    }
+
+    // 止まるまでの残り時間(止まっていれば0)
+    pub fn remaining(&self, clock: &GameClock) -> f64 {
+        (self.ends_at - clock.now()).max(0.0)
+    }
+
+    pub fn clear(&mut self) {
+        self.ends_at = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_accumulates_game_time() {
+        let mut clock = GameClock::new();
+        assert_eq!(clock.advance(100.0), 100.0);
+        assert_eq!(clock.advance(25.0), 25.0);
+        assert_eq!(clock.now(), 125.0);
+        // 実時間が巻き戻っても(時計の補正など)ゲーム内時間は戻らない
+        assert_eq!(clock.advance(-50.0), 0.0);
+        assert_eq!(clock.now(), 125.0);
+    }
+
+    #[test]
+    fn timer_runs_for_its_duration_in_game_time() {
+        let mut clock = GameClock::new();
+        let timer = GameTimer::started(&clock, 1000.0);
+        assert!(timer.is_running(&clock));
+        clock.advance(500.0);
+        assert!(timer.is_running(&clock));
+        assert_eq!(timer.remaining(&clock), 500.0);
+        clock.advance(500.0);
+        assert!(!timer.is_running(&clock));
+        assert_eq!(timer.remaining(&clock), 0.0);
+    }
+
+    #[test]
+    fn cleared_and_default_timers_are_stopped() {
+        let mut clock = GameClock::new();
+        assert!(!GameTimer::default().is_running(&clock));
+        let mut timer = GameTimer::default();
+        timer.start(&clock, 500.0);
+        clock.advance(100.0);
+        assert!(timer.is_running(&clock));
+        timer.clear();
+        assert!(!timer.is_running(&clock));
+        // 止めたタイマーは始め直せる
+        timer.start(&clock, 500.0);
+        assert_eq!(timer.remaining(&clock), 500.0);
+    }
+}
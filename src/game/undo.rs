@@ -0,0 +1,400 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{
+    Blast, BombMeter, Bullet, BulletPool, Combo, CritEffect, DebrisPool, Enemy, Game, GameClock,
+    GameEvent, GameState, GameTimer, Missile, PendingSpawn, Player, PowerUp, ReplayBuffer, Rng,
+    RunFlags, Particle, ScorePopup, Vec2, Wave, WaveModifier,
+};
+use crate::game::game::{COUNTDOWN_LABELS, COUNTDOWN_STEP, STARTING_BOMBS};
+use crate::game::particles::MAX_PARTICLES;
+use crate::game::submission::empty_input_hash;
+use crate::game::modifier::ActiveModifier;
+use crate::game::wave::{BossIntro, WAVE_BREAK_DURATION};
+
+// リセットを取り消せる時間(実時間、ms)
+pub const UNDO_RESET_WINDOW: f64 = 10000.0;
+
+// リセット直前のプレイの状態
+// 保存はメモリ上だけで、取り消しの受付時間を過ぎるか次のプレイを始めると捨てる
+pub struct RunStash {
+    pub expires_at: f64, // 取り消せなくなる実時間(ms)
+    state: String, // 書き出したプレイの状態(RunState のJSON)
+}
+
+// 1回のプレイの状態として保存する Game のフィールドの一覧から、
+// 保存の形式(RunState)と、書き出しと読み込みの処理を作る
+// 波の変化は調整値に重ねているので、外してから変化の種類だけを残し、読み込むときに掛け直す
+macro_rules! run_state {
+    ($($field:ident: $ty:ty,)*) => {
+        // 1回のプレイの状態
+        #[derive(Deserialize)]
+        struct RunState {
+            $($field: $ty,)*
+            wave_modifier: Option<WaveModifier>,
+        }
+
+        // 書き出すときは Game のフィールドを借りてそのまま書き出す
+        #[derive(Serialize)]
+        struct RunStateRef<'a> {
+            $($field: &'a $ty,)*
+            wave_modifier: Option<WaveModifier>,
+        }
+
+        impl Game {
+            // 今のプレイの状態をJSONに書き出す。波の変化は外してから呼ぶ
+            fn run_state_json(&self, wave_modifier: Option<WaveModifier>) -> Option<String> {
+                let state = RunStateRef {
+                    $($field: &self.$field,)*
+                    wave_modifier,
+                };
+                serde_json::to_string(&state).ok()
+            }
+
+            // プレイの状態を読み込み、波の変化を掛け直す
+            fn load_run_state(&mut self, state: RunState) {
+                self.end_wave_modifier();
+                $(self.$field = state.$field;)*
+                self.wave_modifier = state.wave_modifier.map(|modifier| {
+                    ActiveModifier::apply(modifier, &mut self.level, &mut self.current_wave)
+                });
+            }
+        }
+    };
+}
+
+// プレイごとの状態を Game に加えたら、ここと RunState::fresh に加える
+run_state! {
+    player: Player,
+    bullets: BulletPool,
+    enemy_bullets: Vec<Bullet>,
+    enemies: Vec<Enemy>,
+    pending_spawns: Vec<PendingSpawn>,
+    power_ups: Vec<PowerUp>,
+    speed_boost: GameTimer,
+    ricochet: GameTimer,
+    crit_effects: Vec<CritEffect>,
+    score_popups: Vec<ScorePopup>,
+    combo: Combo,
+    debris: DebrisPool,
+    particles: Vec<Particle>,
+    crit_hits: u32,
+    kills: u32,
+    enemies_escaped: u32,
+    next_life_at: u32,
+    bombs: u32,
+    bomb_meter: BombMeter,
+    shield: GameTimer,
+    pierce: GameTimer,
+    laser_energy: f64,
+    laser_firing: bool,
+    laser_damage: f64,
+    charge_start: Option<f64>,
+    weapon_level: u8,
+    missiles: Vec<Missile>,
+    missile_cooldown: GameTimer,
+    blasts: Vec<Blast>,
+    bomb_flash: GameTimer,
+    life_flash: GameTimer,
+    escape_flash: GameTimer,
+    play_time: f64,
+    survival_bonus: u32,
+    hits_taken: u32,
+    countdown: f64,
+    previous_best: u32,
+    shots_fired: u32,
+    shots_hit: u32,
+    current_wave: Wave,
+    wave_spawned: u32,
+    wave_break: GameTimer,
+    modifier_waves: u32,
+    spawn_timer: GameTimer,
+    next_boss_score: u32,
+    boss_intro: Option<BossIntro>,
+    spawn_guard: GameTimer,
+    score: u32,
+    lives: u32,
+    clock: GameClock,
+    run_flags: RunFlags,
+    replay: ReplayBuffer,
+    rng: Rng,
+    seed: u64,
+    input_hash: u64,
+}
+
+impl RunState {
+    // 新しいプレイの初期状態
+    fn fresh(game: &Game, seed: u64) -> Self {
+        let clock = GameClock::new();
+        let player = &game.player;
+        RunState {
+            player: Player {
+                pos: Vec2::new((game.width - player.width) / 2.0, game.height - player.height),
+                ..*player
+            },
+            bullets: BulletPool::new(),
+            enemy_bullets: Vec::new(),
+            enemies: Vec::new(),
+            pending_spawns: Vec::new(),
+            power_ups: Vec::new(),
+            speed_boost: GameTimer::default(),
+            ricochet: GameTimer::default(),
+            crit_effects: Vec::new(),
+            score_popups: Vec::new(),
+            combo: Combo::new(),
+            debris: DebrisPool::new(),
+            particles: Vec::with_capacity(MAX_PARTICLES),
+            crit_hits: 0,
+            kills: 0,
+            enemies_escaped: 0,
+            next_life_at: game.level.extra_life_interval(),
+            bombs: STARTING_BOMBS,
+            bomb_meter: BombMeter::new(),
+            shield: GameTimer::default(),
+            pierce: GameTimer::default(),
+            laser_energy: 1.0,
+            laser_firing: false,
+            laser_damage: 0.0,
+            charge_start: None,
+            weapon_level: 1,
+            missiles: Vec::new(),
+            missile_cooldown: GameTimer::default(),
+            blasts: Vec::new(),
+            bomb_flash: GameTimer::default(),
+            life_flash: GameTimer::default(),
+            escape_flash: GameTimer::default(),
+            play_time: 0.0,
+            survival_bonus: 0,
+            hits_taken: 0,
+            countdown: 0.0,
+            previous_best: 0,
+            shots_fired: 0,
+            shots_hit: 0,
+            current_wave: game.wave(1),
+            wave_spawned: 0,
+            wave_break: GameTimer::started(&clock, WAVE_BREAK_DURATION),
+            modifier_waves: 0,
+            spawn_timer: GameTimer::default(),
+            next_boss_score: game.level.boss_interval(),
+            boss_intro: None,
+            spawn_guard: GameTimer::default(),
+            score: 0,
+            lives: game.starting_lives(), // ライフの初期化
+            clock,
+            run_flags: RunFlags::default(),
+            replay: ReplayBuffer::new(),
+            rng: Rng::new(seed),
+            seed,
+            input_hash: empty_input_hash(),
+            wave_modifier: None,
+        }
+    }
+}
+
+impl Game {
+    // 今のプレイを書き出して保存する。波の変化は外す
+    pub fn stash_run(&mut self, expires_at: f64) -> Option<RunStash> {
+        let modifier = self.wave_modifier.as_ref().map(|active| active.modifier);
+        self.end_wave_modifier();
+        let state = self.run_state_json(modifier)?;
+        Some(RunStash { expires_at, state })
+    }
+
+    // 指定したシードで新しいプレイの状態にする
+    pub fn start_fresh_run(&mut self, seed: u64) {
+        self.end_wave_modifier();
+        let state = RunState::fresh(self, seed);
+        self.load_run_state(state);
+    }
+
+    // 保存しておいたプレイに戻す
+    pub fn undo_reset(&mut self) {
+        let Some(stash) = self.reset_stash.take() else {
+            return;
+        };
+        let Ok(state) = serde_json::from_str::<RunState>(&stash.state) else {
+            return;
+        };
+        self.load_run_state(state);
+        self.latch_run_flags();
+        // いきなり再開しないよう、プレイの開始と同じくカウントダウンから再開する
+        self.state = GameState::Playing;
+        self.paused = false;
+        self.countdown = COUNTDOWN_STEP * COUNTDOWN_LABELS.len() as f64;
+        self.last_frame_time = 0.0;
+        self.keys_pressed.clear();
+        self.charge_start = None;
+        // ページ側の表示は新しいプレイの値になっているので、戻した値を知らせ直す
+        self.events.push(GameEvent::Score { score: self.score });
+        self.events.push(GameEvent::Lives { lives: self.lives });
+    }
+
+    // 受付時間を過ぎた保存を捨てる
+    pub fn expire_reset_stash(&mut self, current_time: f64) {
+        if self
+            .reset_stash
+            .as_ref()
+            .is_some_and(|stash| current_time >= stash.expires_at)
+        {
+            self.reset_stash = None;
+        }
+    }
+
+    // 取り消しの受付が終わるまでの残り時間(ms)
+    pub fn undo_reset_remaining(&self) -> Option<f64> {
+        self.reset_stash
+            .as_ref()
+            .map(|stash| (stash.expires_at - self.last_frame_time).max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::render::mock::headless_game;
+    use crate::game::EnemyKind;
+
+    const FULL_COUNTDOWN: f64 = COUNTDOWN_STEP * COUNTDOWN_LABELS.len() as f64;
+
+    #[test]
+    fn expiry_follows_the_reset_time_even_after_timing_was_reset() {
+        let (game, _) = headless_game(1);
+        let mut game = game.borrow_mut();
+        game.begin_run();
+        // 再開直後で最後のフレームの時刻が0に戻っている
+        game.resume_timing();
+        game.reset(50_000.0);
+        let expires_at = 50_000.0 + UNDO_RESET_WINDOW;
+        assert_eq!(game.reset_stash.as_ref().map(|stash| stash.expires_at), Some(expires_at));
+
+        game.expire_reset_stash(expires_at - 1.0);
+        assert!(game.reset_stash.is_some());
+        game.expire_reset_stash(expires_at);
+        assert!(game.reset_stash.is_none());
+    }
+
+    #[test]
+    fn undo_resumes_through_the_countdown_and_resyncs_the_page() {
+        let (game, _) = headless_game(1);
+        let mut game = game.borrow_mut();
+        game.begin_run();
+        // カウントダウンの途中でリセットする
+        game.countdown = FULL_COUNTDOWN / 2.0;
+        game.score = 1200;
+        game.lives = 2;
+        game.reset(1000.0);
+        assert_eq!(game.state, GameState::Menu);
+        assert_eq!(game.score, 0);
+
+        game.undo_reset();
+        assert_eq!(game.state, GameState::Playing);
+        assert!(!game.paused);
+        assert_eq!(game.countdown, FULL_COUNTDOWN);
+        assert_eq!((game.score, game.lives), (1200, 2));
+        assert!(game.events.contains(&GameEvent::Score { score: 1200 }));
+        assert!(game.events.contains(&GameEvent::Lives { lives: 2 }));
+        assert!(game.reset_stash.is_none());
+    }
+
+    #[test]
+    fn undo_restores_the_field_and_the_random_sequence() {
+        let (game, _) = headless_game(1);
+        let mut game = game.borrow_mut();
+        game.begin_run();
+        game.enemies.push(Enemy::for_test(EnemyKind::Small, 40.0, 60.0));
+        game.kills = 7;
+        let mut expected = game.rng.clone();
+        game.reset(0.0);
+        assert!(game.enemies.is_empty());
+        assert_eq!(game.kills, 0);
+
+        game.undo_reset();
+        assert_eq!(game.enemies.len(), 1);
+        assert_eq!(game.enemies[0].pos, Vec2::new(40.0, 60.0));
+        assert_eq!(game.kills, 7);
+        // 次のシードを引いた後の続きから、同じ乱数の並びで再開する
+        expected.next_u64();
+        assert_eq!(game.rng.next_u64(), expected.next_u64());
+    }
+
+    #[test]
+    fn undo_restores_timers_colors_and_the_wave_modifier_exactly() {
+        let (game, _) = headless_game(1);
+        let mut game = game.borrow_mut();
+        game.begin_run();
+        game.clock.advance(1234.5678);
+        game.shield = GameTimer::started(&game.clock, 4321.123);
+        game.enemy_bullets.push(Bullet::new_enemy_shot(10.1, 20.2));
+        game.enemy_bullets[0].color = "violet";
+        let game = &mut *game;
+        game.wave_modifier = Some(ActiveModifier::apply(
+            WaveModifier::Swarm,
+            &mut game.level,
+            &mut game.current_wave,
+        ));
+        let level = game.level.clone();
+        let wave = game.current_wave;
+        game.reset(0.0);
+        // リセット後は変化を外した調整値に戻っている
+        assert_ne!(game.level, level);
+
+        game.undo_reset();
+        assert_eq!(game.clock.now(), 1234.5678);
+        assert_eq!(game.shield.remaining(&game.clock), 4321.123);
+        assert_eq!(game.enemy_bullets[0].color, "violet");
+        assert_eq!(game.enemy_bullets[0].pos, Vec2::new(10.1, 20.2));
+        let modifier = game.wave_modifier.as_ref().map(|active| active.modifier);
+        assert_eq!(modifier, Some(WaveModifier::Swarm));
+        assert_eq!((&game.level, game.current_wave), (&level, wave));
+    }
+
+    #[test]
+    fn the_stash_lasts_until_the_new_run_leaves_its_countdown() {
+        let (game, _) = headless_game(1);
+        let mut game = game.borrow_mut();
+        game.begin_run();
+        game.score = 500;
+        game.reset(1000.0);
+        game.begin_run();
+        game.render_frame(1000.0);
+        assert!(game.reset_stash.is_some());
+        let mut now = 1000.0;
+        while game.countdown > 0.0 {
+            now += 16.0;
+            game.render_frame(now);
+        }
+        assert!(game.reset_stash.is_none());
+        game.undo_reset();
+        assert_eq!(game.score, 0);
+    }
+
+    #[test]
+    fn pressing_u_mid_countdown_restores_the_previous_run() {
+        let (game, _) = headless_game(1);
+        let mut game = game.borrow_mut();
+        game.begin_run();
+        game.score = 800;
+        game.reset(1000.0);
+        game.begin_run();
+        for frame in 0..=60 {
+            game.render_frame(1000.0 + frame as f64 * 16.0);
+        }
+        assert!(game.countdown > 0.0 && game.countdown < FULL_COUNTDOWN);
+
+        game.key_down("u".to_string(), 2000.0);
+        assert_eq!(game.score, 800);
+        assert_eq!(game.state, GameState::Playing);
+        assert_eq!(game.countdown, FULL_COUNTDOWN);
+        assert!(game.reset_stash.is_none());
+    }
+
+    #[test]
+    fn resetting_from_the_title_keeps_nothing_to_undo() {
+        let (game, _) = headless_game(1);
+        let mut game = game.borrow_mut();
+        game.reset(0.0);
+        assert!(game.reset_stash.is_none());
+        assert_eq!(game.undo_reset_remaining(), None);
+        game.undo_reset();
+        assert_eq!(game.state, GameState::Menu);
+    }
+}
@@ -0,0 +1,87 @@
+// 敵の出現を区切る波。内容は番号から LevelConfig の waves に従って決めるので、何波でも続けられる
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::enemy::{BOSS_HOLD_Y, BOSS_SIZE};
+use crate::game::{GameClock, GameTimer};
+
+// 波と波の間の休憩時間(ms)。この間は「WAVE N」の表示を出す
+pub const WAVE_BREAK_DURATION: f64 = 3000.0;
+// ボスの登場前に「WARNING」を出す時間(ms)。この間に場の敵を抜けさせる
+pub const BOSS_WARNING_DURATION: f64 = 2500.0;
+// ボスが画面の外から定位置まで降りてくる時間(ms)。この間は攻撃しない
+pub const BOSS_ENTRY_DURATION: f64 = 1500.0;
+
+// 1回の波の内容
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Wave {
+    pub number: u32, // 1から始まる波の番号
+    pub enemy_count: u32, // この波で出す敵の数
+    pub speed_range: (f64, f64), // 敵の速さの最小と最大(1ステップあたりのpx)
+    pub spawn_interval: f64, // 敵を出す間隔(ms)
+}
+
+
+// ボス登場の演出の段階
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum BossIntroPhase {
+    Warning,  // 警告を出し、通常の敵の出現を止めて場の敵を抜けさせる
+    Entering, // ボスを定位置まで降ろす
+}
+
+// ボス登場の演出中の波の進行。演出中は通常の敵を出さず、敵に抜けられてもライフを減らさない
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct BossIntro {
+    pub phase: BossIntroPhase,
+    pub timer: GameTimer, // 今の段階が終わるまで
+    pub spawn_remaining: f64, // 止めた出現間隔の残り(ms)。演出が終わったら続きから数える
+}
+
+impl BossIntro {
+    // 今の段階の進み具合(0.0〜1.0)
+    pub fn progress(&self, clock: &GameClock) -> f64 {
+        let duration = match self.phase {
+            BossIntroPhase::Warning => BOSS_WARNING_DURATION,
+            BossIntroPhase::Entering => BOSS_ENTRY_DURATION,
+        };
+        1.0 - self.timer.remaining(clock) / duration
+    }
+}
+
+// 登場中のボスの高さ。画面の外から定位置まで、着く直前に減速しながら降ろす
+pub fn boss_entry_y(progress: f64) -> f64 {
+    let t = progress.clamp(0.0, 1.0);
+    let eased = 1.0 - (1.0 - t).powi(3);
+    -BOSS_SIZE + (BOSS_HOLD_Y + BOSS_SIZE) * eased
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boss_eases_from_off_screen_to_its_hold_position() {
+        assert_eq!(boss_entry_y(0.0), -BOSS_SIZE);
+        assert_eq!(boss_entry_y(1.0), BOSS_HOLD_Y);
+        assert_eq!(boss_entry_y(2.0), BOSS_HOLD_Y);
+        // 前半で大きく進み、後半はゆっくり近づく
+        let first_half = boss_entry_y(0.5) - boss_entry_y(0.0);
+        let second_half = boss_entry_y(1.0) - boss_entry_y(0.5);
+        assert!(first_half > second_half * 4.0);
+    }
+
+    #[test]
+    fn intro_progress_follows_the_phase_duration() {
+        let mut clock = GameClock::new();
+        let intro = BossIntro {
+            phase: BossIntroPhase::Warning,
+            timer: GameTimer::started(&clock, BOSS_WARNING_DURATION),
+            spawn_remaining: 0.0,
+        };
+        assert_eq!(intro.progress(&clock), 0.0);
+        clock.advance(BOSS_WARNING_DURATION / 2.0);
+        assert_eq!(intro.progress(&clock), 0.5);
+        clock.advance(BOSS_WARNING_DURATION);
+        assert_eq!(intro.progress(&clock), 1.0);
+    }
+}
@@ -0,0 +1,55 @@
+// 武器の強化段階ごとの弾の出方
+
+// 強化段階の上限
+pub const MAX_WEAPON_LEVEL: u8 = 3;
+// 2段階目で左右に並べる弾の、自機の中央からのずれ(自機の幅に対する割合)
+const TWIN_OFFSET: f64 = 0.35;
+// 3段階目で左右に広げる弾の、照準の向きからの角度(ラジアン)
+const SPREAD_ANGLE: f64 = 0.2;
+
+// 1回の射撃で撃つ弾ごとの、自機の中央からの横のずれ(px)と照準の向きからの角度(ラジアン)
+// 1段階目は1発、2段階目は自機の両端から平行に2発、3段階目は扇状に3発
+pub fn barrels(level: u8, player_width: f64) -> Vec<(f64, f64)> {
+    match level {
+        0 | 1 => vec![(0.0, 0.0)],
+        2 => {
+            let offset = player_width * TWIN_OFFSET;
+            vec![(-offset, 0.0), (offset, 0.0)]
+        }
+        _ => vec![(0.0, -SPREAD_ANGLE), (0.0, 0.0), (0.0, SPREAD_ANGLE)],
+    }
+}
+
+// 向き(dx, dy)を角度angle(ラジアン、時計回り)だけ回す
+pub fn rotate((dx, dy): (f64, f64), angle: f64) -> (f64, f64) {
+    let (sin, cos) = angle.sin_cos();
+    (dx * cos - dy * sin, dx * sin + dy * cos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_level_adds_barrels() {
+        assert_eq!(barrels(0, 40.0), vec![(0.0, 0.0)]);
+        assert_eq!(barrels(1, 40.0), vec![(0.0, 0.0)]);
+        assert_eq!(barrels(2, 40.0), vec![(-14.0, 0.0), (14.0, 0.0)]);
+        assert_eq!(
+            barrels(MAX_WEAPON_LEVEL, 40.0),
+            vec![(0.0, -SPREAD_ANGLE), (0.0, 0.0), (0.0, SPREAD_ANGLE)]
+        );
+        // 上限を超えた段階は最後の段階と同じ
+        assert_eq!(barrels(MAX_WEAPON_LEVEL + 1, 40.0), barrels(MAX_WEAPON_LEVEL, 40.0));
+    }
+
+    #[test]
+    fn rotate_keeps_length_and_turns_clockwise() {
+        let (dx, dy) = rotate((0.0, -1.0), std::f64::consts::FRAC_PI_2);
+        assert!((dx - 1.0).abs() < 1e-9 && dy.abs() < 1e-9);
+
+        let (dx, dy) = rotate((3.0, -4.0), SPREAD_ANGLE);
+        assert!((dx.hypot(dy) - 5.0).abs() < 1e-9);
+        assert_eq!(rotate((3.0, -4.0), 0.0), (3.0, -4.0));
+    }
+}
@@ -0,0 +1,37 @@
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{window, Response};
+
+use crate::console_log;
+use crate::game::LevelConfig;
+
+// 敵の出方やスコアの調整値を書いたファイル
+const LEVEL_CONFIG_URL: &str = "assets/levels.json";
+
+// 調整値を取得する。取得や解釈に失敗した場合は警告を出して組み込みの値を使う
+pub async fn fetch_level_config() -> LevelConfig {
+    let text = match fetch_text(LEVEL_CONFIG_URL).await {
+        Ok(text) => text,
+        Err(e) => {
+            console_log!("Warning: could not load {}, using defaults: {:?}", LEVEL_CONFIG_URL, e);
+            return LevelConfig::default();
+        }
+    };
+    LevelConfig::parse(&text).unwrap_or_else(|e| {
+        console_log!("Warning: {} is malformed, using defaults: {}", LEVEL_CONFIG_URL, e);
+        LevelConfig::default()
+    })
+}
+
+// url の中身を文字列で取得する
+pub async fn fetch_text(url: &str) -> Result<String, JsValue> {
+    let window = window().ok_or_else(|| JsValue::from_str("no global `window` exists"))?;
+    let response: Response = JsFuture::from(window.fetch_with_str(url)).await?.dyn_into()?;
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!("HTTP {}", response.status())));
+    }
+    JsFuture::from(response.text()?)
+        .await?
+        .as_string()
+        .ok_or_else(|| JsValue::from_str("response is not text"))
+}
@@ -2,14 +2,241 @@ use wasm_bindgen::prelude::*;
 
 mod game;
 mod app;
+mod assets;
+mod audio;
+mod canvas;
+mod dom;
+mod error;
+mod levels;
+mod lifecycle;
+mod page_events;
+mod settings;
+mod share_card;
+mod text;
 mod utils;
 
+// モジュールの読み込み時に呼ばれる
+#[wasm_bindgen(start)]
+pub fn init() {
+    utils::set_panic_hook();
+}
+
+// ゲームを開始する。必要な要素が見つからない場合はエラーで失敗する
+#[wasm_bindgen]
+pub async fn start_game() -> Result<(), JsValue> {
+    app::start_game().await.map_err(JsValue::from)
+}
+
+// 再現用にシードを指定してゲームを開始する
+#[wasm_bindgen]
+pub async fn start_game_with_seed(seed: u64) -> Result<(), JsValue> {
+    app::start_game_with_seed(seed).await.map_err(JsValue::from)
+}
+
+// 遊び方を選んで開始する
+// "endless": ライフが尽きるまで、"score_attack": 90秒のスコアアタック、"practice": 終わりのない練習
+#[wasm_bindgen]
+pub async fn start_game_with_mode(mode: String) -> Result<(), JsValue> {
+    app::start_game_with_mode(&mode).await.map_err(JsValue::from)
+}
+
+// 難易度("easy"、"normal"、"hard"、"hardcore")を選んで開始する。大文字と小文字は区別しない
+// start_game は "normal" で始める
+#[wasm_bindgen]
+pub async fn start_game_with_difficulty(difficulty: String) -> Result<(), JsValue> {
+    app::start_game_with_difficulty(&difficulty).await.map_err(JsValue::from)
+}
+
+// 埋め込むページから設定を渡して開始する。項目は game/config.rs の GameConfig を参照
+// 知らない項目は読み飛ばし、値がおかしければエラーで失敗する
 #[wasm_bindgen]
-pub fn start_game() {
-    app::start_game();
+pub async fn start_game_with_config(config: JsValue) -> Result<(), JsValue> {
+    app::start_game_with_config(config).await.map_err(JsValue::from)
+}
+
+// ゲームを止めて後片付けをする。もう一度 start_game で始められる
+#[wasm_bindgen]
+pub fn stop_game() {
+    app::stop_game();
 }
 
 #[wasm_bindgen]
 pub fn reset_game() {
     app::reset_game();
 }
+
+// assets/levels.json を取得し直し、以降の敵の出方やスコアに反映する
+#[wasm_bindgen]
+pub fn reload_config() {
+    app::reload_config();
+}
+
+// 現在のスコア。ゲーム開始前は0
+#[wasm_bindgen]
+pub fn get_score() -> u32 {
+    app::get_score()
+}
+
+// 残りライフ。ゲーム開始前は0
+#[wasm_bindgen]
+pub fn get_lives() -> u32 {
+    app::get_lives()
+}
+
+#[wasm_bindgen]
+pub fn is_game_over() -> bool {
+    app::is_game_over()
+}
+
+// 設定画面を開いている間などにゲームを止める。止めたときだけtrueを返す
+#[wasm_bindgen]
+pub fn pause_game() -> bool {
+    app::pause_game()
+}
+
+// pause_game やキー操作で止めたゲームを再開する。再開したときだけtrueを返す
+#[wasm_bindgen]
+pub fn resume_game() -> bool {
+    app::resume_game()
+}
+
+// 選んでいる難易度の名前。ゲーム開始前は "normal"
+#[wasm_bindgen]
+pub fn get_difficulty() -> String {
+    app::get_difficulty()
+}
+
+// 当たり判定の枠を重ねて描くか(F4キーでも切り替えられる)
+#[wasm_bindgen]
+pub fn set_debug_hitboxes(enabled: bool) {
+    app::set_debug_hitboxes(enabled);
+}
+
+// 弾と敵の当たり判定を格子で絞らず全組み合わせで行うか(格子の結果と見比べる確認用)
+#[wasm_bindgen]
+pub fn set_brute_force_collisions(enabled: bool) {
+    app::set_brute_force_collisions(enabled);
+}
+
+#[wasm_bindgen]
+pub fn set_player_wrap(enabled: bool) {
+    app::set_player_wrap(enabled);
+}
+
+// 画面の向き("BottomUp": 自機が下、"LeftToRight": 自機が左)
+#[wasm_bindgen]
+pub fn set_orientation(name: &str) -> Result<(), JsValue> {
+    app::set_orientation(name)
+}
+
+// 動きを抑える設定(破片や画面の揺れなどの演出を出さない)
+#[wasm_bindgen]
+pub fn set_reduced_motion(enabled: bool) {
+    app::set_reduced_motion(enabled);
+}
+
+// アシスト設定のゲーム速度(0.5〜1.0)を変更し、実際に設定された値を返す
+#[wasm_bindgen]
+pub fn set_game_speed(speed: f64) -> f64 {
+    app::set_game_speed(speed)
+}
+
+#[wasm_bindgen]
+pub fn get_game_speed() -> f64 {
+    app::get_game_speed()
+}
+
+// 効果音の音量(0.0〜1.0)を変更する。範囲外の値は丸める
+#[wasm_bindgen]
+pub fn set_volume(v: f64) {
+    app::set_volume(v)
+}
+
+#[wasm_bindgen]
+pub fn get_volume() -> f64 {
+    app::get_volume()
+}
+
+// BGMの音量(0.0〜1.0)を効果音とは別に変更する
+#[wasm_bindgen]
+pub fn set_music_volume(v: f64) {
+    app::set_music_volume(v)
+}
+
+#[wasm_bindgen]
+pub fn get_music_volume() -> f64 {
+    app::get_music_volume()
+}
+
+// 消音を切り替え、消音中ならtrueを返す
+#[wasm_bindgen]
+pub fn toggle_mute() -> bool {
+    app::toggle_mute()
+}
+
+// リーダーボードに送信する署名付きのスコア(ゲームオーバー時のみ)
+#[wasm_bindgen]
+pub fn get_score_submission() -> Option<String> {
+    app::get_score_submission()
+}
+
+// 操作(MoveLeft, MoveRight, MoveUp, MoveDown, Fire, Pause)に割り当てるキーを変更する
+#[wasm_bindgen]
+pub fn set_key_binding(action: &str, code: &str) -> Result<(), JsValue> {
+    app::set_key_binding(action, code)
+}
+
+#[wasm_bindgen]
+pub fn get_key_bindings() -> JsValue {
+    app::get_key_bindings()
+}
+
+// 結果の共有カード(PNGのdata URL)
+#[wasm_bindgen]
+pub fn get_share_card() -> Result<String, JsValue> {
+    app::get_share_card()
+}
+
+// 結果の共有カードをダウンロードする
+#[wasm_bindgen]
+pub fn download_share_card() -> Result<(), JsValue> {
+    app::download_share_card()
+}
+
+// 対戦相手の自機の位置(ゲーム内座標)を渡す。sent_at は相手側で送信した時刻(ms)
+#[wasm_bindgen]
+pub fn push_ghost_position(x: f64, y: f64, sent_at: f64) {
+    app::push_ghost_position(x, y, sent_at);
+}
+
+// ゲームオーバーになったプレイの結果(スコア、撃破数、逃した数、撃った数、命中数、
+// プレイ時間、命中率)。ゲームオーバー以外のときはnull
+#[wasm_bindgen]
+pub fn get_run_stats() -> JsValue {
+    app::get_run_stats()
+}
+
+// ゲームオーバー画面のまとめ(スコア、最高スコア、更新したか、最高コンボ、命中率、
+// 撃破数、プレイ時間)。ゲームオーバー以外のときはnull
+#[wasm_bindgen]
+pub fn get_last_run_summary() -> JsValue {
+    app::get_last_run_summary()
+}
+
+// 登録したままのイベントリスナーの数。stop_game の後は開始前と同じ数に戻る
+#[wasm_bindgen]
+pub fn get_listener_count() -> usize {
+    app::get_listener_count()
+}
+
+// 停止し忘れを確かめるための数({ listeners, frame_closures, audio_contexts })
+#[wasm_bindgen]
+pub fn get_debug_counts() -> JsValue {
+    app::get_debug_counts()
+}
+
+// このプレイで使われた、ランキング対象外になる設定(空ならランキング対象)
+#[wasm_bindgen]
+pub fn get_run_flags() -> JsValue {
+    app::get_run_flags()
+}
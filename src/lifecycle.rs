@@ -0,0 +1,275 @@
+// ゲームの停止と後片付け
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use wasm_bindgen::closure::WasmClosure;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, EventTarget};
+
+use serde::Serialize;
+
+use crate::canvas::CanvasRestore;
+use crate::console_log;
+use crate::game::Game;
+use crate::page_events;
+use crate::settings;
+
+// 停止時に後片付けが必要な部分
+// 何度呼ばれても問題ないようにし、2回目以降は何もしない
+pub trait Subsystem {
+    fn shutdown(&mut self);
+}
+
+// 片付け忘れを確かめるための、今残っている資源の数
+#[derive(Clone, Copy, Default, PartialEq, Debug, Serialize)]
+pub struct DebugCounts {
+    pub listeners: usize,      // 登録したままのイベントリスナー
+    pub frame_closures: usize, // 保持しているゲームループのクロージャ
+    pub audio_contexts: usize, // 閉じていないAudioContext
+}
+
+thread_local! {
+    static DEBUG_COUNTS: Cell<DebugCounts> = Cell::new(DebugCounts::default());
+}
+
+pub fn debug_counts() -> DebugCounts {
+    DEBUG_COUNTS.with(Cell::get)
+}
+
+// 資源を作ったときと片付けたときに数を合わせる
+pub fn update_debug_counts(f: impl FnOnce(&mut DebugCounts)) {
+    DEBUG_COUNTS.with(|counts| {
+        let mut current = counts.get();
+        f(&mut current);
+        counts.set(current);
+    });
+}
+
+// 登録したイベントリスナー。停止時にすべて取り外す
+#[derive(Default)]
+pub struct EventListeners {
+    listeners: Vec<(EventTarget, &'static str, js_sys::Function, Box<dyn Any>)>,
+}
+
+impl EventListeners {
+    // リスナーを登録し、取り外すまでクロージャを保持する
+    pub fn add<T: ?Sized + WasmClosure + 'static>(
+        &mut self,
+        target: &EventTarget,
+        event: &'static str,
+        closure: Closure<T>,
+    ) {
+        let function = closure.as_ref().unchecked_ref::<js_sys::Function>().clone();
+        if let Err(e) = target.add_event_listener_with_callback(event, &function) {
+            console_log!("Error adding {} listener: {:?}", event, e);
+            return;
+        }
+        self.listeners.push((target.clone(), event, function, Box::new(closure)));
+        update_debug_counts(|counts| counts.listeners += 1);
+    }
+}
+
+impl Subsystem for EventListeners {
+    fn shutdown(&mut self) {
+        for (target, event, function, _closure) in self.listeners.drain(..) {
+            if let Err(e) = target.remove_event_listener_with_callback(event, &function) {
+                console_log!("Error removing {} listener: {:?}", event, e);
+            }
+            update_debug_counts(|counts| counts.listeners -= 1);
+        }
+    }
+}
+
+// 毎フレーム呼ばれるクロージャ(自分自身を次のフレームに登録し直す)
+type FrameCallback = Rc<RefCell<Option<Closure<dyn FnMut(f64)>>>>;
+
+// requestAnimationFrameで回すゲームループ。停止するまではどの状態でも回り続ける
+// 毎フレーム同じクロージャを使い回し、停止時に取り消して解放する
+// フレームの処理中(ページへ送った出来事のリスナーの中など)に止められたら、
+// 動いているクロージャを解放しないよう、フレームの終わりに stop を呼んで止める
+pub struct FrameLoop {
+    frame: FrameCallback,
+    request_id: Rc<Cell<Option<i32>>>,
+    game: Rc<RefCell<Game>>,
+    in_frame: Rc<Cell<bool>>,       // フレームの処理中か
+    stop_requested: Rc<Cell<bool>>, // フレームの処理中に停止を頼まれたか
+}
+
+impl FrameLoop {
+    pub fn start(game_rc: Rc<RefCell<Game>>, stop: fn()) -> Self {
+        let frame: FrameCallback = Rc::new(RefCell::new(None));
+        let request_id = Rc::new(Cell::new(None));
+        let in_frame = Rc::new(Cell::new(false));
+        let stop_requested = Rc::new(Cell::new(false));
+        // 世代を進め、前のループのクロージャが残っていても次のフレームで止まるようにする
+        let generation = {
+            let mut game = game_rc.borrow_mut();
+            game.loop_generation = game.loop_generation.wrapping_add(1);
+            game.loop_generation
+        };
+
+        let next_frame = frame.clone();
+        let next_request_id = request_id.clone();
+        let game = game_rc.clone();
+        let frame_in_frame = in_frame.clone();
+        let frame_stop_requested = stop_requested.clone();
+        let mut first_frame = true;
+        *frame.borrow_mut() = Some(Closure::wrap(Box::new(move |timestamp: f64| {
+            next_request_id.set(None);
+            if game.borrow().loop_generation != generation {
+                return;
+            }
+            frame_in_frame.set(true);
+            // このループで最初のフレームにかかった時間を1度だけ記録する
+            // (リセットや再開で last_frame_time が0に戻っても記録し直さない)
+            let started = js_sys::Date::now();
+            game.borrow_mut().render_frame(timestamp);
+            if first_frame {
+                first_frame = false;
+                console_log!("First frame took {} ms", js_sys::Date::now() - started);
+            }
+            // 受け取ったページ側の処理がゲームを参照できるよう、借用を外してから送る
+            let events = std::mem::take(&mut game.borrow_mut().events);
+            for event in &events {
+                page_events::dispatch(event);
+            }
+            frame_in_frame.set(false);
+            // 処理中に頼まれた停止は、このクロージャを抜けてから行う
+            // それまでに別の停止でこのループが止まっていれば、次に始めたゲームは止めない
+            if frame_stop_requested.get() {
+                let game = game.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    if game.borrow().loop_generation == generation {
+                        stop();
+                    }
+                });
+                return;
+            }
+            // 再度アニメーションフレームを要求
+            if let Some(closure) = next_frame.borrow().as_ref() {
+                next_request_id.set(request_animation_frame(closure));
+            }
+        }) as Box<dyn FnMut(f64)>));
+
+        if let Some(closure) = frame.borrow().as_ref() {
+            request_id.set(request_animation_frame(closure));
+        }
+        update_debug_counts(|counts| counts.frame_closures += 1);
+        FrameLoop {
+            frame,
+            request_id,
+            game: game_rc,
+            in_frame,
+            stop_requested,
+        }
+    }
+
+    // フレームの処理中なら停止をフレームの終わりまで待たせ、trueを返す
+    pub fn defer_stop(&self) -> bool {
+        if self.in_frame.get() {
+            self.stop_requested.set(true);
+        }
+        self.in_frame.get()
+    }
+}
+
+fn request_animation_frame(closure: &Closure<dyn FnMut(f64)>) -> Option<i32> {
+    match window()?.request_animation_frame(closure.as_ref().unchecked_ref()) {
+        Ok(id) => Some(id),
+        Err(e) => {
+            console_log!("requestAnimationFrame failed: {:?}", e);
+            None
+        }
+    }
+}
+
+impl Subsystem for FrameLoop {
+    fn shutdown(&mut self) {
+        {
+            let mut game = self.game.borrow_mut();
+            game.loop_generation = game.loop_generation.wrapping_add(1);
+        }
+        if let (Some(id), Some(window)) = (self.request_id.take(), window()) {
+            if let Err(e) = window.cancel_animation_frame(id) {
+                console_log!("Error cancelling animation frame: {:?}", e);
+            }
+        }
+        // クロージャは自分自身への参照を持っているので、取り出して循環を断つ
+        if self.frame.borrow_mut().take().is_some() {
+            update_debug_counts(|counts| counts.frame_closures -= 1);
+        }
+    }
+}
+
+// キーボードやマウス、フォーカスの入力。停止時にリスナーを外し、押していたキーを離す
+pub struct Input {
+    pub listeners: EventListeners,
+    game: Rc<RefCell<Game>>,
+}
+
+impl Input {
+    pub fn new(game: Rc<RefCell<Game>>) -> Self {
+        Input {
+            listeners: EventListeners::default(),
+            game,
+        }
+    }
+}
+
+impl Subsystem for Input {
+    fn shutdown(&mut self) {
+        self.listeners.shutdown();
+        self.game.borrow_mut().keys_pressed.clear();
+    }
+}
+
+// localStorageへの設定の保存。停止時に今の設定を書き出す
+pub struct StorageFlusher {
+    game: Rc<RefCell<Game>>,
+    flushed: bool,
+}
+
+impl StorageFlusher {
+    pub fn new(game: Rc<RefCell<Game>>) -> Self {
+        StorageFlusher {
+            game,
+            flushed: false,
+        }
+    }
+}
+
+impl Subsystem for StorageFlusher {
+    fn shutdown(&mut self) {
+        if std::mem::replace(&mut self.flushed, true) {
+            return;
+        }
+        let game = self.game.borrow();
+        settings::save_game_speed(game.assist_speed);
+        settings::save_volume(game.master_volume);
+        settings::save_music_volume(game.music_volume);
+    }
+}
+
+// 開始したゲームと、停止時に片付けるもの一式
+pub struct Session {
+    pub game: Rc<RefCell<Game>>,
+    pub frame_loop: FrameLoop,
+    pub input: Input,
+    pub listeners: EventListeners, // 入力以外のリスナー(画面の拡大率の変化など)
+    pub storage: StorageFlusher,
+    pub canvas: CanvasRestore,
+}
+
+impl Subsystem for Session {
+    // 先にループとイベントを止め、止まったゲームに何も届かなくなってから設定を書き出し、音を止める
+    fn shutdown(&mut self) {
+        self.frame_loop.shutdown();
+        self.input.shutdown();
+        self.listeners.shutdown();
+        self.canvas.shutdown();
+        self.storage.shutdown();
+        self.game.borrow_mut().audio.shutdown();
+    }
+}
@@ -0,0 +1,56 @@
+// ゲーム内の出来事をwindowのCustomEventとしてページ側に知らせる
+use wasm_bindgen::JsValue;
+use web_sys::{window, CustomEvent, CustomEventInit};
+
+use crate::console_log;
+use crate::game::GameEvent;
+
+// イベント名と detail に入れる値
+fn describe(event: &GameEvent) -> (&'static str, Vec<(&'static str, JsValue)>) {
+    let number = |value: f64| JsValue::from_f64(value);
+    match *event {
+        GameEvent::Score { score } => {
+            ("alarmshooter:score", vec![("score", number(score as f64))])
+        }
+        GameEvent::LifeLost { lives } => {
+            ("alarmshooter:lifelost", vec![("lives", number(lives as f64))])
+        }
+        GameEvent::Lives { lives } => {
+            ("alarmshooter:lives", vec![("lives", number(lives as f64))])
+        }
+        // 外部のランキングが難易度ごとに分けられるよう、難易度の名前も送る
+        GameEvent::GameOver {
+            score,
+            duration_ms,
+            difficulty,
+        } => (
+            "alarmshooter:gameover",
+            vec![
+                ("score", number(score as f64)),
+                ("duration", number(duration_ms)),
+                ("difficulty", JsValue::from_str(difficulty.name())),
+            ],
+        ),
+    }
+}
+
+// 送れなかった場合は記録だけ残し、ゲームは止めない
+pub fn dispatch(event: &GameEvent) {
+    let (name, fields) = describe(event);
+    let detail = js_sys::Object::new();
+    for (key, value) in fields {
+        if let Err(e) = js_sys::Reflect::set(&detail, &JsValue::from_str(key), &value) {
+            console_log!("Error building {} detail: {:?}", name, e);
+        }
+    }
+
+    let init = CustomEventInit::new();
+    init.set_detail(&detail);
+    let result = CustomEvent::new_with_event_init_dict(name, &init).and_then(|custom_event| {
+        let window = window().ok_or_else(|| JsValue::from_str("no global `window` exists"))?;
+        window.dispatch_event(&custom_event)
+    });
+    if let Err(e) = result {
+        console_log!("Error dispatching {}: {:?}", name, e);
+    }
+}
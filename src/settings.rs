@@ -0,0 +1,91 @@
+use web_sys::{window, Storage};
+
+use crate::game::{Difficulty, GameMode, RunHistory, RunStore};
+
+// localStorageに保存する設定のキー
+const GAME_SPEED_KEY: &str = "alarmShooter.gameSpeed";
+const RUN_HISTORY_KEY: &str = "alarmShooter.runHistory";
+const VOLUME_KEY: &str = "alarmShooter.volume";
+const MUSIC_VOLUME_KEY: &str = "alarmShooter.musicVolume";
+
+fn local_storage() -> Option<Storage> {
+    window()?.local_storage().ok()?
+}
+
+// 保存されているゲーム速度(未保存の場合は等速)
+pub fn load_game_speed() -> f64 {
+    local_storage()
+        .and_then(|storage| storage.get_item(GAME_SPEED_KEY).ok()?)
+        .and_then(|value| value.parse::<f64>().ok())
+        .unwrap_or(1.0)
+}
+
+pub fn save_game_speed(speed: f64) {
+    if let Some(storage) = local_storage() {
+        let _ = storage.set_item(GAME_SPEED_KEY, &speed.to_string());
+    }
+}
+
+// 保存されている効果音の音量(未保存の場合は最大)
+pub fn load_volume() -> f64 {
+    load_volume_item(VOLUME_KEY)
+}
+
+pub fn save_volume(volume: f64) {
+    save_volume_item(VOLUME_KEY, volume);
+}
+
+// 保存されているBGMの音量(未保存の場合は最大)
+pub fn load_music_volume() -> f64 {
+    load_volume_item(MUSIC_VOLUME_KEY)
+}
+
+pub fn save_music_volume(volume: f64) {
+    save_volume_item(MUSIC_VOLUME_KEY, volume);
+}
+
+fn load_volume_item(key: &str) -> f64 {
+    local_storage()
+        .and_then(|storage| storage.get_item(key).ok()?)
+        .and_then(|value| value.parse::<f64>().ok())
+        .unwrap_or(1.0)
+}
+
+fn save_volume_item(key: &str, volume: f64) {
+    if let Some(storage) = local_storage() {
+        let _ = storage.set_item(key, &volume.to_string());
+    }
+}
+
+// プレイの記録のキー。エンドレスの通常の難易度は以前からのキーのまま、
+// 他のモードや難易度は名前を付け足す
+fn run_history_key(mode: GameMode, difficulty: Difficulty) -> String {
+    let mut key = RUN_HISTORY_KEY.to_string();
+    if mode != GameMode::Endless {
+        key = format!("{}.{}", key, mode.name());
+    }
+    if difficulty != Difficulty::Normal {
+        key = format!("{}.{}", key, difficulty.name());
+    }
+    key
+}
+
+// プレイの記録をlocalStorageに保存する
+pub struct LocalRunStore;
+
+impl RunStore for LocalRunStore {
+    // 保存されているプレイの記録(未保存の場合は空)
+    fn load_runs(&self, mode: GameMode, difficulty: Difficulty) -> RunHistory {
+        local_storage()
+            .and_then(|storage| storage.get_item(&run_history_key(mode, difficulty)).ok()?)
+            .map(|value| RunHistory::decode(&value))
+            .unwrap_or_default()
+    }
+
+    fn save_runs(&self, mode: GameMode, difficulty: Difficulty, history: &RunHistory) {
+        if let Some(storage) = local_storage() {
+            let key = run_history_key(mode, difficulty);
+            let _ = storage.set_item(&key, &history.encode());
+        }
+    }
+}
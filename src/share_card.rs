@@ -0,0 +1,103 @@
+// プレイ結果を共有用の画像(PNG)にする
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{window, CanvasRenderingContext2d, HtmlAnchorElement, HtmlCanvasElement};
+
+use crate::game::{Game, TextAlign, TextStyle, MAX_GAME_SPEED};
+use crate::text::TextPainter;
+
+// 共有カードの大きさ
+const CARD_WIDTH: u32 = 600;
+const CARD_HEIGHT: u32 = 315;
+
+// 結果を描いたカードをdata URLで返す
+// 画面とは別のCanvasに描くので、プレイ中のCanvasの状態には影響しない
+pub fn render_share_card(game: &Game) -> Result<String, JsValue> {
+    let document = window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| JsValue::from_str("no document available"))?;
+    let canvas = document
+        .create_element("canvas")?
+        .dyn_into::<HtmlCanvasElement>()?;
+    canvas.set_width(CARD_WIDTH);
+    canvas.set_height(CARD_HEIGHT);
+    let context = canvas
+        .get_context("2d")?
+        .ok_or_else(|| JsValue::from_str("2d context unavailable"))?
+        .dyn_into::<CanvasRenderingContext2d>()?;
+
+    let width = CARD_WIDTH as f64;
+    let height = CARD_HEIGHT as f64;
+    context.set_fill_style(&JsValue::from_str("#111"));
+    context.fill_rect(0.0, 0.0, width, height);
+    context.set_fill_style(&JsValue::from_str("orange"));
+    context.fill_rect(0.0, 0.0, width, 6.0);
+
+    let text = TextPainter::new();
+    let center = width / 2.0;
+    text.draw_text(
+        &context,
+        "ALARM SHOOTER",
+        (center, 50.0),
+        36.0,
+        TextAlign::Center,
+        &TextStyle::BANNER,
+    );
+    text.draw_text(
+        &context,
+        &format!("SCORE {}", game.score),
+        (center, 120.0),
+        48.0,
+        TextAlign::Center,
+        &TextStyle::BANNER.with_color("orange"),
+    );
+
+    let accuracy = if game.shots_fired == 0 {
+        0.0
+    } else {
+        game.shots_hit as f64 / game.shots_fired as f64 * 100.0
+    };
+    let difficulty = if game.assist_speed < MAX_GAME_SPEED {
+        format!("Assist x{:.2}", game.assist_speed)
+    } else {
+        "Normal".to_string()
+    };
+    let date = js_sys::Date::new_0().to_iso_string();
+    let date = date.as_string().unwrap_or_default();
+    let lines = [
+        format!("Accuracy {:.0}%", accuracy),
+        format!("Difficulty {}", difficulty),
+        format!("Date {}", date.get(..10).unwrap_or(&date)),
+        format!("Seed {:016x}", game.seed),
+    ];
+    if !game.run_flags.is_ranked() {
+        text.draw_text(
+            &context,
+            "UNRANKED",
+            (width - 20.0, 30.0),
+            16.0,
+            TextAlign::Right,
+            &TextStyle::PLAIN.with_color("orange"),
+        );
+    }
+    for (index, line) in lines.iter().enumerate() {
+        let y = 180.0 + index as f64 * 30.0;
+        text.draw_text(&context, line, (center, y), 20.0, TextAlign::Center, &TextStyle::PLAIN);
+    }
+
+    canvas.to_data_url_with_type("image/png")
+}
+
+// カードの画像をファイルとして保存させる
+pub fn download_share_card(game: &Game) -> Result<(), JsValue> {
+    let url = render_share_card(game)?;
+    let document = window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| JsValue::from_str("no document available"))?;
+    let link = document
+        .create_element("a")?
+        .dyn_into::<HtmlAnchorElement>()?;
+    link.set_href(&url);
+    link.set_download(&format!("alarm-shooter-{}.png", game.score));
+    link.click();
+    Ok(())
+}
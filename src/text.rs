@@ -0,0 +1,179 @@
+// Canvasへの文字描画の補助
+use std::cell::{Cell, RefCell};
+use std::fmt::Write;
+use wasm_bindgen::JsValue;
+use web_sys::CanvasRenderingContext2d;
+
+use crate::console_log;
+use crate::game::{TextAlign, TextStyle};
+
+// 数値の文字列化はCanvas上のHUDと共通のものを使う
+pub use crate::game::format_number;
+
+// 文字幅を覚えておく件数
+const MEASURE_CACHE_CAPACITY: usize = 64;
+
+// (文字列, フォント) ごとの文字幅。最近使ったものほど後ろに並べ、溢れたら先頭から捨てる
+pub struct MeasureCache {
+    entries: RefCell<Vec<(String, String, f64)>>,
+    pub measure_count: Cell<u32>, // 実際にmeasure_textを呼んだ回数
+    pub hit_count: Cell<u32>,     // 覚えていた文字幅を使い回した回数
+}
+
+impl MeasureCache {
+    pub fn new() -> Self {
+        MeasureCache {
+            entries: RefCell::new(Vec::with_capacity(MEASURE_CACHE_CAPACITY)),
+            measure_count: Cell::new(0),
+            hit_count: Cell::new(0),
+        }
+    }
+
+    // 現在のフォントでの文字幅(contextのフォントは設定済みであること)
+    pub fn width(&self, context: &CanvasRenderingContext2d, text: &str, font: &str) -> f64 {
+        let mut entries = self.entries.borrow_mut();
+        if let Some(index) = entries
+            .iter()
+            .position(|(t, f, _)| t.as_str() == text && f.as_str() == font)
+        {
+            let entry = entries.remove(index);
+            let width = entry.2;
+            entries.push(entry);
+            self.hit_count.set(self.hit_count.get() + 1);
+            return width;
+        }
+
+        self.measure_count.set(self.measure_count.get() + 1);
+        let width = match context.measure_text(text) {
+            Ok(metrics) => metrics.width(),
+            Err(e) => {
+                console_log!("Error measuring text: {:?}", e);
+                0.0
+            }
+        };
+        if entries.len() >= MEASURE_CACHE_CAPACITY {
+            entries.remove(0);
+        }
+        entries.push((text.to_string(), font.to_string(), width));
+        width
+    }
+}
+
+// 文字の描画。揃え位置はキャッシュした文字幅から計算し、常に左揃えで描く
+pub struct TextPainter {
+    pub measures: MeasureCache,
+    font: RefCell<String>, // フォント指定の文字列を毎回作り直さないための領域
+}
+
+impl TextPainter {
+    pub fn new() -> Self {
+        TextPainter {
+            measures: MeasureCache::new(),
+            font: RefCell::new(String::new()),
+        }
+    }
+
+    pub fn draw_text(
+        &self,
+        context: &CanvasRenderingContext2d,
+        text: &str,
+        (x, y): (f64, f64),
+        size: f64,
+        align: TextAlign,
+        style: &TextStyle,
+    ) {
+        let mut font = self.font.borrow_mut();
+        font.clear();
+        let weight = if style.bold { "bold " } else { "" };
+        let _ = write!(font, "{}{}px sans-serif", weight, size);
+        context.set_font(&font);
+        context.set_text_align("left");
+        context.set_text_baseline("middle");
+
+        let x = match align {
+            TextAlign::Left => x,
+            TextAlign::Center => x - self.measures.width(context, text, &font) / 2.0,
+            TextAlign::Right => x - self.measures.width(context, text, &font),
+        };
+
+        // 影 → 縁取り → 塗りの順に重ねる
+        if let Some((color, offset)) = style.shadow {
+            context.set_fill_style(&JsValue::from_str(color));
+            fill(context, text, x + offset, y + offset);
+        }
+        if let Some((color, width)) = style.outline {
+            context.set_stroke_style(&JsValue::from_str(color));
+            context.set_line_width(width);
+            context.set_line_join("round");
+            if let Err(e) = context.stroke_text(text, x, y) {
+                console_log!("Error drawing text: {:?}", e);
+            }
+        }
+        context.set_fill_style(&JsValue::from_str(style.color));
+        fill(context, text, x, y);
+    }
+}
+
+fn fill(context: &CanvasRenderingContext2d, text: &str, x: f64, y: f64) {
+    if let Err(e) = context.fill_text(text, x, y) {
+        console_log!("Error drawing text: {:?}", e);
+    }
+}
+
+// ブラウザで動かすテスト(wasm-pack test --headless --firefox)
+#[cfg(all(test, target_arch = "wasm32"))]
+mod browser_tests {
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+    use web_sys::{window, HtmlCanvasElement};
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn context() -> CanvasRenderingContext2d {
+        let document = window().and_then(|window| window.document()).unwrap();
+        let canvas = document.create_element("canvas").unwrap();
+        let canvas: HtmlCanvasElement = canvas.dyn_into().unwrap();
+        canvas.get_context("2d").unwrap().unwrap().dyn_into().unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn aligned_text_reuses_cached_measurements() {
+        let context = context();
+        let painter = TextPainter::new();
+        let measures = &painter.measures;
+        let draw = |size, align| {
+            painter.draw_text(&context, "SCORE", (100.0, 20.0), size, align, &TextStyle::PLAIN);
+        };
+        for _ in 0..3 {
+            draw(16.0, TextAlign::Center);
+        }
+        assert_eq!((measures.measure_count.get(), measures.hit_count.get()), (1, 2));
+
+        // 大きさが変わればフォントが違うので測り直す。左揃えは測らない
+        draw(24.0, TextAlign::Right);
+        draw(24.0, TextAlign::Left);
+        assert_eq!((measures.measure_count.get(), measures.hit_count.get()), (2, 2));
+    }
+
+    #[wasm_bindgen_test]
+    fn the_least_recently_used_width_is_evicted_first() {
+        let context = context();
+        let cache = MeasureCache::new();
+        let font = "16px sans-serif";
+        context.set_font(font);
+        cache.width(&context, "first", font);
+        for i in 0..MEASURE_CACHE_CAPACITY - 1 {
+            cache.width(&context, &i.to_string(), font);
+        }
+        // 使い直した "first" は残り、次に古い "0" が追い出される
+        cache.width(&context, "first", font);
+        cache.width(&context, "overflow", font);
+        let measured = cache.measure_count.get();
+        cache.width(&context, "first", font);
+        assert_eq!(cache.measure_count.get(), measured);
+        cache.width(&context, "0", font);
+        assert_eq!(cache.measure_count.get(), measured + 1);
+    }
+}
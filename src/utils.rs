@@ -7,7 +7,13 @@ extern "C" {
     pub fn log(s: &str);
 }
 
+// パニック時にRustのメッセージとスタックトレースをコンソールに出す
+pub fn set_panic_hook() {
+    #[cfg(feature = "console_error_panic_hook")]
+    console_error_panic_hook::set_once();
+}
+
 #[macro_export]
 macro_rules! console_log {
-    ($($t:tt)*) => (crate::utils::log(&format_args!($($t)*).to_string()))
+    ($($t:tt)*) => ($crate::utils::log(&format_args!($($t)*).to_string()))
 }